@@ -0,0 +1,60 @@
+use image::RgbaImage;
+use tauri_utils::config::Color;
+
+/// The appearance to get the variant of an icon for, see [`IconOptions::with_appearance`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum IconAppearance {
+    /// The regular icon, also reported when there is no variant for the requested appearance.
+    #[default]
+    Light,
+    Dark,
+    HighContrast,
+    /// The monochrome icon the system tints with an accent color: the template of an `.icns`,
+    /// or the `-symbolic` icon of a Linux icon theme.
+    Tinted,
+}
+
+/// Which variant of an icon to draw, apart from its [`crate::IconSize`], for
+/// [`crate::get_icon_variant`]. The default is the regular icon, from the
+/// icon theme the user configured on Linux.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct IconOptions {
+    /// The appearance to get the variant of the icon for, see [`IconOptions::with_appearance`].
+    /// `None` keeps the look of the configured icon theme, e.g. a dark one.
+    pub appearance: Option<IconAppearance>,
+    /// The color symbolic icons of Linux icon themes are drawn with, see
+    /// [`IconOptions::with_symbolic_color`].
+    pub symbolic_color: Option<Color>,
+}
+
+impl IconOptions {
+    /// Prefers the variant of the icon for `appearance`, e.g. the dark one for
+    /// a dark mode UI. [`crate::get_icon_variant`] tells which one was used.
+    pub fn with_appearance(mut self, appearance: IconAppearance) -> Self {
+        self.appearance = Some(appearance);
+        self
+    }
+
+    /// Prefers the `-symbolic` variant of themed icons, recolored with `color`
+    /// (a `tauri::window::Color`), e.g. the foreground color of a dark panel.
+    pub fn with_symbolic_color(mut self, color: Color) -> Self {
+        self.symbolic_color = Some(color);
+        self
+    }
+}
+
+/// An icon, with the appearance of the variant it was drawn from.
+#[derive(Debug, Clone, PartialEq)]
+pub struct IconVariant {
+    pub image: RgbaImage,
+    pub appearance: IconAppearance,
+}
+
+impl IconVariant {
+    pub(crate) fn light(image: RgbaImage) -> Self {
+        Self {
+            image,
+            appearance: IconAppearance::Light,
+        }
+    }
+}
@@ -0,0 +1,23 @@
+use std::path::PathBuf;
+
+/// An installed application, as listed by [`crate::list_applications`].
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Application {
+    /// The id of the application, its desktop file id on Linux.
+    pub id: String,
+    /// The untranslated name.
+    pub name: String,
+    /// The name in the user's language, or the untranslated name when there is no translation.
+    pub localized_name: String,
+    /// The program the application runs.
+    pub executable: Option<String>,
+    /// The path to pass to [`crate::get_icon`].
+    pub path: PathBuf,
+    pub categories: Vec<String>,
+    /// Whether the application asks not to be shown in menus, e.g. for apps that only open files.
+    pub no_display: bool,
+    /// Whether the application was removed by the user, launchers should skip it.
+    pub hidden: bool,
+    /// The icon name or absolute icon path.
+    pub icon: Option<String>,
+}
@@ -0,0 +1,137 @@
+//! Extracting many icons at once on a bounded pool of worker threads, e.g.
+//! for every installed app when a launcher starts.
+
+use std::{
+    collections::VecDeque,
+    path::PathBuf,
+    sync::{mpsc, Arc, Mutex},
+    thread,
+};
+
+use crate::{get_icon_bytes_with_format, GetAppIconError, IconFormat, IconSize};
+
+mod tests;
+
+/// An icon to extract with [`get_icons`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct IconRequest {
+    pub app_path: PathBuf,
+    pub size: IconSize,
+    pub format: IconFormat,
+}
+
+impl IconRequest {
+    /// A request for the icon of `app_path` as PNG.
+    pub fn new(app_path: impl Into<PathBuf>, size: impl Into<IconSize>) -> Self {
+        Self {
+            app_path: app_path.into(),
+            size: size.into(),
+            format: IconFormat::Png,
+        }
+    }
+
+    pub fn with_format(mut self, format: IconFormat) -> Self {
+        self.format = format;
+        self
+    }
+}
+
+/// The outcome of a request, sent as soon as its icon is extracted.
+#[derive(Debug)]
+pub struct IconResponse {
+    /// The position of the request in the batch, as responses arrive in the
+    /// order the icons are done.
+    pub index: usize,
+    pub request: IconRequest,
+    pub result: Result<Vec<u8>, GetAppIconError>,
+}
+
+/// Sets up a worker thread for the platform's APIs while it lives.
+struct WorkerGuard {
+    #[cfg(target_os = "windows")]
+    _com: crate::windows::ComGuard,
+}
+
+impl WorkerGuard {
+    fn new() -> Self {
+        Self {
+            #[cfg(target_os = "windows")]
+            _com: crate::windows::ComGuard::new(),
+        }
+    }
+}
+
+fn extract(request: &IconRequest) -> Result<Vec<u8>, GetAppIconError> {
+    let extract = || get_icon_bytes_with_format(&request.app_path, request.size, request.format);
+
+    // AppKit objects created off the main thread are only released by a pool
+    // on that thread, so each icon gets its own instead of piling up
+    #[cfg(target_os = "macos")]
+    let result = objc::rc::autoreleasepool(extract);
+    #[cfg(not(target_os = "macos"))]
+    let result = extract();
+
+    result
+}
+
+/// Extracts the icons of `requests` on up to `workers` threads (at least one),
+/// and sends each [`IconResponse`] through the returned channel as soon as it
+/// is ready. The channel closes once every request is answered, and dropping
+/// the receiver stops the workers after the icons they're extracting.
+///
+/// `get_icons` returns right away, so a Tauri setup hook isn't held up by the
+/// extraction; receive the responses on a blocking thread, e.g. in
+/// `tauri::async_runtime::spawn_blocking`:
+///
+/// ```no_run
+/// use app_icon::batch::{self, IconRequest};
+///
+/// let requests = ["/Applications/Safari.app", "/Applications/Notes.app"]
+///     .into_iter()
+///     .map(|path| IconRequest::new(path, 32.0));
+///
+/// for response in batch::get_icons(requests, 4) {
+///     match response.result {
+///         Ok(png) => println!("{}: {} bytes", response.request.app_path.display(), png.len()),
+///         Err(error) => eprintln!("{}: {error}", response.request.app_path.display()),
+///     }
+/// }
+/// ```
+pub fn get_icons(
+    requests: impl IntoIterator<Item = IconRequest>,
+    workers: usize,
+) -> mpsc::Receiver<IconResponse> {
+    let queue: VecDeque<(usize, IconRequest)> = requests.into_iter().enumerate().collect();
+    let workers = workers.clamp(1, queue.len().max(1));
+    let queue = Arc::new(Mutex::new(queue));
+    let (sender, receiver) = mpsc::channel();
+
+    for _ in 0..workers {
+        let queue = Arc::clone(&queue);
+        let sender = sender.clone();
+
+        thread::spawn(move || {
+            let _guard = WorkerGuard::new();
+
+            loop {
+                let next = queue.lock().ok().and_then(|mut queue| queue.pop_front());
+                let Some((index, request)) = next else {
+                    break;
+                };
+
+                let result = extract(&request);
+                let response = IconResponse {
+                    index,
+                    request,
+                    result,
+                };
+
+                if sender.send(response).is_err() {
+                    break;
+                }
+            }
+        });
+    }
+
+    receiver
+}
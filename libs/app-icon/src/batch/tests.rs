@@ -0,0 +1,68 @@
+#![cfg(test)]
+use std::path::Path;
+
+use super::{get_icons, IconRequest};
+use crate::IconFormat;
+
+#[test]
+fn it_answers_every_request() {
+    let requests: Vec<IconRequest> = (0..20)
+        .map(|index| IconRequest::new(format!("/foo/bar-{index}"), 32.0))
+        .collect();
+
+    let mut responses: Vec<_> = get_icons(requests.clone(), 4).into_iter().collect();
+    responses.sort_by_key(|response| response.index);
+
+    assert_eq!(responses.len(), 20);
+    for (response, request) in responses.iter().zip(&requests) {
+        assert_eq!(&response.request, request);
+        assert!(response.result.is_err());
+    }
+
+    assert_eq!(get_icons(vec![], 0).into_iter().count(), 0);
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn it_extracts_every_icon_of_a_batch() {
+    let dir = crate::fixture_dir("batch");
+
+    let icon_path = dir.join("foo.png");
+    image::RgbaImage::new(64, 64).save(&icon_path).unwrap();
+
+    let requests: Vec<IconRequest> = (0..8)
+        .map(|index| {
+            let app_path = dir.join(format!("foo-{index}.desktop"));
+            std::fs::write(
+                &app_path,
+                format!("[Desktop Entry]\nIcon={}\n", icon_path.display()),
+            )
+            .unwrap();
+
+            IconRequest::new(app_path, 16.0 + index as f64).with_format(IconFormat::WebP)
+        })
+        .chain([IconRequest::new(Path::new("/foo/bar.desktop"), 16.0)])
+        .collect();
+
+    let mut responses: Vec<_> = get_icons(requests, 3).into_iter().collect();
+    responses.sort_by_key(|response| response.index);
+
+    // each request is answered exactly once, whichever worker took it
+    assert_eq!(
+        responses
+            .iter()
+            .map(|response| response.index)
+            .collect::<Vec<_>>(),
+        (0..9).collect::<Vec<_>>()
+    );
+    for response in responses {
+        match response.index {
+            8 => assert!(response.result.is_err()),
+            index => {
+                let bytes = response.result.unwrap();
+                let image = image::load_from_memory(&bytes).unwrap();
+                assert_eq!(image.width(), 16 + index as u32);
+            }
+        }
+    }
+}
@@ -0,0 +1,109 @@
+//! Reading the icon of a macOS `.app` bundle without AppKit, so bundles can be
+//! inspected from any OS (e.g. on a mounted macOS volume).
+
+use std::{
+    fs,
+    io::{self, Cursor},
+    path::{Path, PathBuf},
+};
+
+use image::RgbaImage;
+use thiserror::Error;
+
+use crate::{
+    icns::{self, IcnsError},
+    resample::{self, IconSize},
+    IconAppearance, IconVariant,
+};
+
+mod tests;
+
+#[derive(Error, Debug)]
+pub enum BundleIconError {
+    #[error("failed to read the bundle's Info.plist")]
+    InfoPlistReadError(#[source] io::Error),
+    #[error("the bundle's Info.plist is not a valid property list")]
+    InvalidInfoPlist,
+    #[error("Info.plist does not name an icon file")]
+    NoIconFile,
+    #[error("the icon file named in Info.plist does not exist")]
+    IconFileDoesNotExist,
+    #[error("failed to read the bundle's icon file")]
+    IconFileReadError(#[source] io::Error),
+    #[error("failed to decode the bundle's icon file: {0}")]
+    IcnsDecodeError(#[from] IcnsError),
+}
+
+/// Whether `path` is a `.app` bundle directory.
+pub fn is_app_bundle(path: &Path) -> bool {
+    path.is_dir() && path.extension().map_or(false, |ext| ext == "app")
+}
+
+/// The `.icns` file names an `Info.plist` (XML or binary) points to, from
+/// `CFBundleIconFile` then `CFBundleIconName`. The extension is optional in
+/// both keys, so it is added when missing.
+pub fn icon_file_names(info_plist: &[u8]) -> Result<Vec<String>, BundleIconError> {
+    let plist = plist::Value::from_reader(Cursor::new(info_plist))
+        .map_err(|_| BundleIconError::InvalidInfoPlist)?;
+
+    let dictionary = plist
+        .as_dictionary()
+        .ok_or(BundleIconError::InvalidInfoPlist)?;
+
+    let names: Vec<String> = ["CFBundleIconFile", "CFBundleIconName"]
+        .iter()
+        .filter_map(|key| dictionary.get(key).and_then(plist::Value::as_string))
+        .filter(|name| !name.is_empty())
+        .map(|name| {
+            if name.ends_with(".icns") {
+                name.to_string()
+            } else {
+                format!("{name}.icns")
+            }
+        })
+        .collect();
+
+    if names.is_empty() {
+        return Err(BundleIconError::NoIconFile);
+    }
+
+    Ok(names)
+}
+
+/// The `.icns` file in `Contents/Resources` of the bundle at `app_path`.
+pub fn icon_file(app_path: &Path) -> Result<PathBuf, BundleIconError> {
+    let contents = app_path.join("Contents");
+
+    let info_plist =
+        fs::read(contents.join("Info.plist")).map_err(BundleIconError::InfoPlistReadError)?;
+
+    icon_file_names(&info_plist)?
+        .into_iter()
+        .map(|name| contents.join("Resources").join(name))
+        .find(|path| path.is_file())
+        .ok_or(BundleIconError::IconFileDoesNotExist)
+}
+
+/// Decodes the bundle's icon closest to `size` and resamples it to exactly `size`.
+pub fn get_icon_image(app_path: &Path, size: IconSize) -> Result<RgbaImage, BundleIconError> {
+    get_icon_variant(app_path, size, IconAppearance::Light).map(|variant| variant.image)
+}
+
+/// Like [`get_icon_image`], using the variant of the `.icns` for
+/// `appearance` when it has one.
+pub fn get_icon_variant(
+    app_path: &Path,
+    size: IconSize,
+    appearance: IconAppearance,
+) -> Result<IconVariant, BundleIconError> {
+    let icon_file = icon_file(app_path)?;
+
+    let bytes = fs::read(icon_file).map_err(BundleIconError::IconFileReadError)?;
+
+    let variant = icns::decode_variant(&bytes, size.source_size(), appearance)?;
+
+    Ok(IconVariant {
+        image: resample::resample(variant.image, &size),
+        appearance: variant.appearance,
+    })
+}
@@ -0,0 +1,101 @@
+#![cfg(test)]
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use image::{Rgba, RgbaImage};
+
+use super::{get_icon_image, icon_file_names, BundleIconError};
+use crate::{fixture_dir, icns, IconSize};
+
+fn fixture_bundle(name: &str, info_plist: &[u8]) -> PathBuf {
+    let app_path = fixture_dir(name).join("Foo.app");
+    fs::create_dir_all(app_path.join("Contents/Resources")).unwrap();
+    fs::write(app_path.join("Contents/Info.plist"), info_plist).unwrap();
+    app_path
+}
+
+fn write_icns(path: &Path) {
+    let images = [16, 128].map(|size| RgbaImage::from_pixel(size, size, Rgba([9, 9, 9, 255])));
+    fs::write(path, icns::write(&images).unwrap()).unwrap();
+}
+
+fn xml_plist(entries: &str) -> Vec<u8> {
+    format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE plist PUBLIC "-//Apple//DTD PLIST 1.0//EN" "http://www.apple.com/DTDs/PropertyList-1.0.dtd">
+<plist version="1.0">
+<dict>
+    <key>CFBundleName</key>
+    <string>Foo</string>
+    {entries}
+</dict>
+</plist>"#
+    )
+    .into_bytes()
+}
+
+#[test]
+fn it_reads_icon_file_names() {
+    let info_plist = xml_plist(
+        "<key>CFBundleIconFile</key><string>AppIcon</string>\
+         <key>CFBundleIconName</key><string>Other.icns</string>",
+    );
+    assert_eq!(
+        icon_file_names(&info_plist).unwrap(),
+        ["AppIcon.icns", "Other.icns"]
+    );
+}
+
+#[test]
+fn it_reads_binary_info_plist() {
+    let mut dictionary = plist::Dictionary::new();
+    dictionary.insert("CFBundleIconName".into(), "AppIcon".into());
+    let mut info_plist = vec![];
+    plist::Value::Dictionary(dictionary)
+        .to_writer_binary(&mut info_plist)
+        .unwrap();
+
+    assert_eq!(icon_file_names(&info_plist).unwrap(), ["AppIcon.icns"]);
+}
+
+#[test]
+fn info_plist_without_icon() {
+    assert!(matches!(
+        icon_file_names(&xml_plist("")),
+        Err(BundleIconError::NoIconFile)
+    ));
+    assert!(matches!(
+        icon_file_names(b"garbage"),
+        Err(BundleIconError::InvalidInfoPlist)
+    ));
+}
+
+#[test]
+fn icon_file_does_not_exist() {
+    let app_path = fixture_bundle(
+        "bundle-missing-icon",
+        &xml_plist("<key>CFBundleIconFile</key><string>AppIcon</string>"),
+    );
+    assert!(matches!(
+        get_icon_image(&app_path, IconSize::square(32.0)),
+        Err(BundleIconError::IconFileDoesNotExist)
+    ));
+}
+
+#[test]
+fn it_works() {
+    let app_path = fixture_bundle(
+        "bundle-icon",
+        &xml_plist(
+            "<key>CFBundleIconFile</key><string>Missing</string>\
+             <key>CFBundleIconName</key><string>AppIcon</string>",
+        ),
+    );
+    write_icns(&app_path.join("Contents/Resources/AppIcon.icns"));
+
+    let image = get_icon_image(&app_path, IconSize::square(32.0)).unwrap();
+    assert_eq!(image.dimensions(), (32, 32));
+    assert_eq!(image.get_pixel(16, 16), &Rgba([9, 9, 9, 255]));
+}
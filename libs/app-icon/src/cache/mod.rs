@@ -0,0 +1,382 @@
+//! An on-disk cache of encoded icons, so apps showing many icons (like an app
+//! switcher) don't re-render every one of them on each launch.
+
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicUsize, Ordering},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use filetime::FileTime;
+use thiserror::Error;
+
+use crate::{get_icon_variant_bytes, GetAppIconError, IconFormat, IconOptions, IconSize};
+
+mod tests;
+
+/// The default limit of the total size of the cached icons, 64 MiB.
+const DEFAULT_MAX_SIZE: u64 = 64 * 1024 * 1024;
+
+/// How old a temporary file gets before it's taken for the leftover of a
+/// process which died while writing it, as writes take milliseconds.
+const STALE_TEMP_AGE: Duration = Duration::from_secs(60 * 60);
+
+#[derive(Error, Debug)]
+pub enum IconCacheError {
+    #[error("failed to get the icon")]
+    GetIconError(#[from] GetAppIconError),
+    #[error("failed to create the cache directory '{}'", .path.display())]
+    CacheDirCreateError {
+        path: PathBuf,
+        #[source]
+        source: io::Error,
+    },
+    #[error("failed to write the cached icon '{}'", .path.display())]
+    CacheWriteError {
+        path: PathBuf,
+        #[source]
+        source: io::Error,
+    },
+}
+
+/// Numbers the temporary files, which are unique per process only by the pid.
+static TEMP_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/// FNV-1a, which unlike `DefaultHasher` is stable across Rust versions, so the
+/// cache survives toolchain updates.
+fn hash(parts: &[&[u8]]) -> u64 {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+
+    for part in parts {
+        for byte in part.iter().chain(&[0]) {
+            hash ^= u64::from(*byte);
+            hash = hash.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+
+    hash
+}
+
+fn modified_nanos(metadata: &fs::Metadata) -> u128 {
+    metadata
+        .modified()
+        .ok()
+        .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+        .map_or(0, |duration| duration.as_nanos())
+}
+
+#[cfg(unix)]
+fn inode(metadata: &fs::Metadata) -> u64 {
+    std::os::unix::fs::MetadataExt::ino(metadata)
+}
+
+#[cfg(not(unix))]
+fn inode(_metadata: &fs::Metadata) -> u64 {
+    0
+}
+
+fn is_hex_hash(text: &str) -> bool {
+    text.len() == 16 && text.bytes().all(|byte| byte.is_ascii_hexdigit())
+}
+
+/// Whether `name` is the name of a cache entry, `<entry>-<stamp>.<ext>` with
+/// both hashes in hex, so that other files in the directory are left alone.
+fn is_entry_name(name: &str) -> bool {
+    let Some((stem, extension)) = name.split_once('.') else {
+        return false;
+    };
+    let Some((entry, stamp)) = stem.split_once('-') else {
+        return false;
+    };
+
+    is_hex_hash(entry)
+        && is_hex_hash(stamp)
+        && !extension.is_empty()
+        && extension.bytes().all(|byte| byte.is_ascii_alphanumeric())
+}
+
+/// Whether `name` is the name of a temporary file of an entry being written,
+/// `<entry>-<stamp>.<pid>.<counter>.tmp`.
+fn is_temp_name(name: &str) -> bool {
+    let Some(name) = name.strip_suffix(".tmp") else {
+        return false;
+    };
+    let mut parts = name.split('.');
+    let (Some(stem), Some(pid), Some(counter), None) =
+        (parts.next(), parts.next(), parts.next(), parts.next())
+    else {
+        return false;
+    };
+    let is_number = |text: &str| !text.is_empty() && text.bytes().all(|byte| byte.is_ascii_digit());
+
+    is_number(pid) && is_number(counter) && is_entry_name(&format!("{stem}.tmp"))
+}
+
+/// Identifies the installed version of the app at `app_path`: the mtime, inode
+/// and length of the app path, and of a bundle's `Contents/Info.plist` which
+/// changes with every update while the bundle directory itself may not.
+fn source_stamp(app_path: &Path) -> u64 {
+    let mut stamp = Vec::new();
+
+    for path in [app_path.to_path_buf(), app_path.join("Contents/Info.plist")] {
+        if let Ok(metadata) = fs::metadata(path) {
+            stamp.extend_from_slice(&modified_nanos(&metadata).to_le_bytes());
+            stamp.extend_from_slice(&inode(&metadata).to_le_bytes());
+            stamp.extend_from_slice(&metadata.len().to_le_bytes());
+        }
+    }
+
+    hash(&[&stamp])
+}
+
+/// A cache of encoded icons stored under a directory, keyed by the app path,
+/// the requested size and format, and the app's modification time and inode.
+///
+/// Entries of an app that changed since they were cached are replaced on the
+/// next request, and the least recently used entries are removed once the
+/// cache grows over its size limit.
+///
+/// Only the app path itself tells whether the app changed, e.g. the
+/// `.desktop` file on Linux, and on Linux the name of the icon theme. An
+/// update of the theme's icon files which leaves both alone keeps the old
+/// icon cached, until [`IconCache::clear`] is called.
+#[derive(Debug, Clone)]
+pub struct IconCache {
+    dir: PathBuf,
+    max_size: u64,
+}
+
+impl IconCache {
+    /// A cache stored in `dir`, which is created when needed, holding up to 64 MiB.
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: dir.into(),
+            max_size: DEFAULT_MAX_SIZE,
+        }
+    }
+
+    /// Limits the total size of the cached icons to `max_size` bytes.
+    pub fn with_max_size(mut self, max_size: u64) -> Self {
+        self.max_size = max_size;
+        self
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    pub fn max_size(&self) -> u64 {
+        self.max_size
+    }
+
+    /// The cache file names of an icon are `<entry>-<stamp>.<ext>`, so the
+    /// stale versions of an entry can be found by their prefix. On Linux the
+    /// entry also depends on the icon theme, which themed icons come from.
+    fn entry_prefix(
+        app_path: &Path,
+        size: &IconSize,
+        options: &IconOptions,
+        format: IconFormat,
+    ) -> String {
+        let key = format!(
+            "{}x{}:{:?}:{:?}",
+            size.pixel_width(),
+            size.pixel_height(),
+            size.filter,
+            format
+        );
+        let variant = format!("{:?}:{:?}", options.appearance, options.symbolic_color);
+
+        #[cfg(target_os = "linux")]
+        let theme = crate::linux::icon_theme_name();
+        #[cfg(not(target_os = "linux"))]
+        let theme = String::new();
+
+        format!(
+            "{:016x}-",
+            hash(&[
+                app_path.to_string_lossy().as_bytes(),
+                key.as_bytes(),
+                variant.as_bytes(),
+                theme.as_bytes(),
+            ])
+        )
+    }
+
+    /// Writes through a file next to the entry, so concurrent readers never
+    /// see a partial icon.
+    fn write_entry(&self, path: &Path, bytes: &[u8]) -> Result<(), IconCacheError> {
+        let temp_path = path.with_extension(format!(
+            "{}.{}.tmp",
+            std::process::id(),
+            TEMP_COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+
+        fs::write(&temp_path, bytes).map_err(|source| IconCacheError::CacheWriteError {
+            path: temp_path.clone(),
+            source,
+        })?;
+        fs::rename(&temp_path, path).map_err(|source| {
+            let _ = fs::remove_file(&temp_path);
+            IconCacheError::CacheWriteError {
+                path: path.to_path_buf(),
+                source,
+            }
+        })
+    }
+
+    /// Gets the variant of the icon for `options` from the cache, extracting
+    /// and caching it first if needed. The icon is returned with the path of
+    /// its entry, or with the error of caching it once extracted.
+    pub(crate) fn lookup(
+        &self,
+        app_path: &Path,
+        size: IconSize,
+        options: IconOptions,
+        format: IconFormat,
+    ) -> Result<(Result<PathBuf, IconCacheError>, Vec<u8>), GetAppIconError> {
+        let prefix = Self::entry_prefix(app_path, &size, &options, format);
+        let path = self.dir.join(format!(
+            "{prefix}{:016x}.{}",
+            source_stamp(app_path),
+            format.extension()
+        ));
+
+        if let Ok(bytes) = fs::read(&path) {
+            // the modification time orders the entries for eviction
+            let _ = filetime::set_file_mtime(&path, FileTime::now());
+
+            return Ok((Ok(path), bytes));
+        }
+
+        let bytes = get_icon_variant_bytes(app_path, size, options, format)?;
+
+        Ok((self.store(&prefix, path, &bytes), bytes))
+    }
+
+    /// Writes the entry at `path`, replacing the other versions of the entry
+    /// named `prefix`.
+    fn store(&self, prefix: &str, path: PathBuf, bytes: &[u8]) -> Result<PathBuf, IconCacheError> {
+        fs::create_dir_all(&self.dir).map_err(|source| IconCacheError::CacheDirCreateError {
+            path: self.dir.clone(),
+            source,
+        })?;
+
+        self.remove_entries(|name| name.starts_with(prefix));
+        self.write_entry(&path, bytes)?;
+        self.evict(&path);
+
+        Ok(path)
+    }
+
+    /// Gets the path of the cached icon, extracting and caching it first if
+    /// it isn't cached yet or the app changed since.
+    pub fn get_icon_path(
+        &self,
+        app_path: &Path,
+        size: impl Into<IconSize>,
+        format: IconFormat,
+    ) -> Result<PathBuf, IconCacheError> {
+        self.lookup(app_path, size.into(), IconOptions::default(), format)?
+            .0
+    }
+
+    /// Gets the icon from the cache, like [`crate::get_icon_bytes_with_format`].
+    /// The cache is best effort: when it can't be written, the freshly
+    /// extracted icon is returned anyway.
+    pub fn get_icon_bytes(
+        &self,
+        app_path: &Path,
+        size: impl Into<IconSize>,
+        format: IconFormat,
+    ) -> Result<Vec<u8>, GetAppIconError> {
+        self.lookup(app_path, size.into(), IconOptions::default(), format)
+            .map(|(_, bytes)| bytes)
+    }
+
+    /// Removes every cached icon, leaving any other file in the directory.
+    pub fn clear(&self) {
+        self.remove_entries(|_| true);
+        self.remove_stale_temp_files();
+    }
+
+    /// Removes the temporary files left by processes which died while writing
+    /// an entry, which would never be renamed nor evicted.
+    fn remove_stale_temp_files(&self) {
+        let Ok(entries) = fs::read_dir(&self.dir) else {
+            return;
+        };
+
+        for entry in entries.flatten() {
+            let is_temp = entry.file_name().to_str().map_or(false, is_temp_name);
+            let is_stale = entry
+                .metadata()
+                .and_then(|metadata| metadata.modified())
+                .ok()
+                .and_then(|modified| modified.elapsed().ok())
+                .map_or(false, |age| age > STALE_TEMP_AGE);
+
+            if is_temp && is_stale {
+                let _ = fs::remove_file(entry.path());
+            }
+        }
+    }
+
+    fn remove_entries(&self, matches: impl Fn(&str) -> bool) {
+        let Ok(entries) = fs::read_dir(&self.dir) else {
+            return;
+        };
+
+        for entry in entries.flatten() {
+            let name = entry.file_name();
+            let Some(name) = name.to_str().filter(|name| is_entry_name(name)) else {
+                continue;
+            };
+
+            if matches(name) {
+                let _ = fs::remove_file(entry.path());
+            }
+        }
+    }
+
+    /// Removes the least recently used entries, other than the `keep` one just
+    /// written, until the cache fits its size limit.
+    fn evict(&self, keep: &Path) {
+        self.remove_stale_temp_files();
+
+        let Ok(entries) = fs::read_dir(&self.dir) else {
+            return;
+        };
+
+        let mut files: Vec<(SystemTime, u64, PathBuf)> = entries
+            .flatten()
+            .filter(|entry| entry.file_name().to_str().map_or(false, is_entry_name))
+            .filter_map(|entry| {
+                let metadata = entry.metadata().ok()?;
+                (metadata.is_file() && entry.path() != keep).then(|| {
+                    (
+                        metadata.modified().unwrap_or(UNIX_EPOCH),
+                        metadata.len(),
+                        entry.path(),
+                    )
+                })
+            })
+            .collect();
+
+        let kept = fs::metadata(keep).map_or(0, |metadata| metadata.len());
+        let mut total: u64 = kept + files.iter().map(|(_, len, _)| len).sum::<u64>();
+
+        files.sort();
+
+        for (_, len, path) in files {
+            if total <= self.max_size {
+                break;
+            }
+
+            if fs::remove_file(path).is_ok() {
+                total -= len;
+            }
+        }
+    }
+}
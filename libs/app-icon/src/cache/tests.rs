@@ -0,0 +1,258 @@
+#![cfg(test)]
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+
+use filetime::FileTime;
+use image::{Rgba, RgbaImage};
+
+use super::{IconCache, IconCacheError};
+use crate::{fixture_dir, icns, IconAppearance, IconFormat, IconOptions, IconSize};
+
+fn write_bundle(app_path: &Path, color: [u8; 4]) {
+    fs::create_dir_all(app_path.join("Contents/Resources")).unwrap();
+    fs::write(
+        app_path.join("Contents/Info.plist"),
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<plist version="1.0">
+<dict>
+    <key>CFBundleIconFile</key>
+    <string>AppIcon</string>
+</dict>
+</plist>"#,
+    )
+    .unwrap();
+    fs::write(
+        app_path.join("Contents/Resources/AppIcon.icns"),
+        icns::write(&[RgbaImage::from_pixel(64, 64, Rgba(color))]).unwrap(),
+    )
+    .unwrap();
+}
+
+fn cached_files(cache: &IconCache) -> Vec<PathBuf> {
+    let mut files: Vec<PathBuf> = fs::read_dir(cache.dir())
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .collect();
+    files.sort();
+    files
+}
+
+fn set_modified(path: &Path, modified: SystemTime) {
+    filetime::set_file_mtime(path, FileTime::from_system_time(modified)).unwrap();
+}
+
+#[test]
+fn it_reuses_cached_icons() {
+    let dir = fixture_dir("cache-hit");
+    let app_path = dir.join("Foo.app");
+    write_bundle(&app_path, [255, 0, 0, 255]);
+    let cache = IconCache::new(dir.join("cache"));
+
+    let path = cache
+        .get_icon_path(&app_path, IconSize::square(32.0), IconFormat::Png)
+        .unwrap();
+    assert_eq!(cached_files(&cache), vec![path.clone()]);
+
+    // removing the icon shows the second request never reads the bundle
+    fs::remove_file(app_path.join("Contents/Resources/AppIcon.icns")).unwrap();
+    assert_eq!(
+        cache
+            .get_icon_path(&app_path, IconSize::square(32.0), IconFormat::Png)
+            .unwrap(),
+        path
+    );
+    assert!(cache
+        .get_icon_bytes(&app_path, IconSize::square(32.0), IconFormat::Png)
+        .is_ok());
+}
+
+#[test]
+fn it_keys_entries_by_size_and_format() {
+    let dir = fixture_dir("cache-keys");
+    let app_path = dir.join("Foo.app");
+    write_bundle(&app_path, [255, 0, 0, 255]);
+    let cache = IconCache::new(dir.join("cache"));
+
+    let png = cache
+        .get_icon_path(&app_path, IconSize::square(32.0), IconFormat::Png)
+        .unwrap();
+    let large_png = cache
+        .get_icon_path(&app_path, IconSize::square(64.0), IconFormat::Png)
+        .unwrap();
+    let webp = cache
+        .get_icon_path(&app_path, IconSize::square(32.0), IconFormat::WebP)
+        .unwrap();
+
+    let (dark, _) = cache
+        .lookup(
+            &app_path,
+            IconSize::square(32.0),
+            IconOptions::default().with_appearance(IconAppearance::Dark),
+            IconFormat::Png,
+        )
+        .unwrap();
+    let dark = dark.unwrap();
+
+    assert_ne!(png, large_png);
+    assert_ne!(png, dark);
+    assert_eq!(webp.extension().unwrap(), "webp");
+    assert_eq!(cached_files(&cache).len(), 4);
+}
+
+#[test]
+fn it_replaces_stale_entries() {
+    let dir = fixture_dir("cache-stale");
+    let app_path = dir.join("Foo.app");
+    write_bundle(&app_path, [255, 0, 0, 255]);
+    let cache = IconCache::new(dir.join("cache"));
+
+    let stale = cache
+        .get_icon_path(&app_path, IconSize::square(32.0), IconFormat::Png)
+        .unwrap();
+
+    // an update rewrites Info.plist
+    write_bundle(&app_path, [0, 0, 255, 255]);
+    set_modified(
+        &app_path.join("Contents/Info.plist"),
+        SystemTime::now() + Duration::from_secs(60),
+    );
+
+    let bytes = cache
+        .get_icon_bytes(&app_path, IconSize::square(32.0), IconFormat::Png)
+        .unwrap();
+    let image = image::load_from_memory(&bytes).unwrap().into_rgba8();
+
+    assert_eq!(image.get_pixel(0, 0), &Rgba([0, 0, 255, 255]));
+    assert!(!stale.exists());
+    assert_eq!(cached_files(&cache).len(), 1);
+}
+
+#[test]
+fn it_evicts_least_recently_used_entries() {
+    let dir = fixture_dir("cache-eviction");
+    let app_path = dir.join("Foo.app");
+    write_bundle(&app_path, [255, 0, 0, 255]);
+    let cache = IconCache::new(dir.join("cache"));
+
+    let first = cache
+        .get_icon_path(&app_path, IconSize::square(16.0), IconFormat::Png)
+        .unwrap();
+    let second = cache
+        .get_icon_path(&app_path, IconSize::square(24.0), IconFormat::Png)
+        .unwrap();
+    set_modified(&first, SystemTime::now() - Duration::from_secs(120));
+    set_modified(&second, SystemTime::now() - Duration::from_secs(60));
+
+    // using the first entry again makes the second one the least recently used
+    cache
+        .get_icon_path(&app_path, IconSize::square(16.0), IconFormat::Png)
+        .unwrap();
+
+    // room for the first entry and the new one only
+    let third_len =
+        crate::get_icon_bytes_with_format(&app_path, IconSize::square(32.0), IconFormat::Png)
+            .unwrap()
+            .len() as u64;
+    let cache = cache.with_max_size(fs::metadata(&first).unwrap().len() + third_len);
+    let third = cache
+        .get_icon_path(&app_path, IconSize::square(32.0), IconFormat::Png)
+        .unwrap();
+
+    let files = cached_files(&cache);
+    assert_eq!(files.len(), 2);
+    assert!(files.contains(&first) && files.contains(&third));
+
+    let cache = cache.with_max_size(u64::MAX);
+    cache.clear();
+    assert!(cached_files(&cache).is_empty());
+}
+
+#[test]
+fn it_leaves_other_files_alone() {
+    let dir = fixture_dir("cache-other-files");
+    let app_path = dir.join("Foo.app");
+    write_bundle(&app_path, [255, 0, 0, 255]);
+    let cache = IconCache::new(dir.join("cache"));
+
+    let entry = cache
+        .get_icon_path(&app_path, IconSize::square(16.0), IconFormat::Png)
+        .unwrap();
+
+    // e.g. a cache directory shared with the rest of the app
+    let others = [
+        cache.dir().join("settings.json"),
+        cache.dir().join("0123456789abcdef-notes.png"),
+        cache
+            .dir()
+            .join("0123456789abcdef-0123456789abcdef.png.tmp"),
+    ];
+    for other in &others {
+        fs::write(other, vec![0; 1024]).unwrap();
+        set_modified(other, SystemTime::now() - Duration::from_secs(600));
+    }
+
+    let cache = cache.with_max_size(fs::metadata(&entry).unwrap().len() * 4);
+    cache
+        .get_icon_path(&app_path, IconSize::square(24.0), IconFormat::Png)
+        .unwrap();
+    assert!(others.iter().all(|other| other.exists()));
+
+    cache.clear();
+    let mut remaining = others.to_vec();
+    remaining.sort();
+    assert_eq!(cached_files(&cache), remaining);
+}
+
+#[test]
+fn it_returns_the_icon_when_it_cant_be_cached() {
+    let dir = fixture_dir("cache-unwritable");
+    let app_path = dir.join("Foo.app");
+    write_bundle(&app_path, [255, 0, 0, 255]);
+    // the cache directory can't be created where a file is
+    fs::write(dir.join("cache"), "").unwrap();
+    let cache = IconCache::new(dir.join("cache"));
+
+    let (path, bytes) = cache
+        .lookup(
+            &app_path,
+            IconSize::square(32.0),
+            IconOptions::default(),
+            IconFormat::Png,
+        )
+        .unwrap();
+
+    assert!(matches!(
+        path,
+        Err(IconCacheError::CacheDirCreateError { path, .. }) if path == dir.join("cache")
+    ));
+    assert_eq!(
+        bytes,
+        cache
+            .get_icon_bytes(&app_path, IconSize::square(32.0), IconFormat::Png)
+            .unwrap()
+    );
+}
+
+#[test]
+fn it_removes_stale_temporary_files() {
+    let dir = fixture_dir("cache-temp-files");
+    let cache = IconCache::new(dir.join("cache"));
+    fs::create_dir_all(cache.dir()).unwrap();
+
+    // left by a process which died while writing an entry, and being written
+    let stale = cache
+        .dir()
+        .join("0123456789abcdef-0123456789abcdef.4242.0.tmp");
+    let fresh = cache
+        .dir()
+        .join("0123456789abcdef-0123456789abcdef.4242.1.tmp");
+    fs::write(&stale, vec![0; 1024]).unwrap();
+    fs::write(&fresh, vec![0; 1024]).unwrap();
+    set_modified(&stale, SystemTime::now() - Duration::from_secs(2 * 60 * 60));
+
+    cache.clear();
+    assert_eq!(cached_files(&cache), vec![fresh]);
+}
@@ -0,0 +1,555 @@
+//! Treatments applied to extracted icons before they're encoded, e.g. to give
+//! the icons of a dock a consistent look.
+//!
+//! Effects work on the image in place of its pixels and keep its size, so
+//! they compose in any order: pad the icon first to leave room for a shadow
+//! or a badge that should not cover it.
+
+use image::{imageops, Rgba, RgbaImage};
+use resvg::tiny_skia::{
+    FillRule, LineCap, LineJoin, Paint, Path, PathBuilder, Pixmap, Rect, Stroke, Transform,
+};
+use tauri_utils::config::Color;
+
+use crate::{
+    resample::{self, IconSize},
+    svg::pixmap_to_image,
+};
+
+mod tests;
+
+/// How far the control points of a cubic Bézier approximating a quarter
+/// circle are from its ends, as a fraction of the radius.
+const ARC_KAPPA: f32 = 0.552_284_8;
+/// The exponent of the superellipse approximating the squircle of app icons.
+const SQUIRCLE_EXPONENT: f32 = 5.0;
+const SQUIRCLE_POINTS: usize = 256;
+
+/// Counts above this are shown as `99+`.
+const MAX_COUNT: u32 = 99;
+
+/// A character and its strokes.
+type Glyph = (char, &'static [&'static [(f32, f32)]]);
+
+/// The glyphs of count badges, on a 4×6 grid.
+const GLYPHS: &[Glyph] = &[
+    (
+        '0',
+        &[&[
+            (0.0, 1.0),
+            (0.0, 5.0),
+            (1.0, 6.0),
+            (3.0, 6.0),
+            (4.0, 5.0),
+            (4.0, 1.0),
+            (3.0, 0.0),
+            (1.0, 0.0),
+            (0.0, 1.0),
+        ]],
+    ),
+    ('1', &[&[(1.0, 1.0), (2.0, 0.0), (2.0, 6.0)]]),
+    (
+        '2',
+        &[&[
+            (0.0, 1.0),
+            (1.0, 0.0),
+            (3.0, 0.0),
+            (4.0, 1.0),
+            (4.0, 2.0),
+            (0.0, 6.0),
+            (4.0, 6.0),
+        ]],
+    ),
+    (
+        '3',
+        &[&[
+            (0.0, 0.0),
+            (4.0, 0.0),
+            (2.0, 2.5),
+            (3.0, 2.5),
+            (4.0, 3.5),
+            (4.0, 5.0),
+            (3.0, 6.0),
+            (1.0, 6.0),
+            (0.0, 5.0),
+        ]],
+    ),
+    ('4', &[&[(3.0, 6.0), (3.0, 0.0), (0.0, 4.0), (4.0, 4.0)]]),
+    (
+        '5',
+        &[&[
+            (4.0, 0.0),
+            (0.0, 0.0),
+            (0.0, 2.5),
+            (3.0, 2.5),
+            (4.0, 3.5),
+            (4.0, 5.0),
+            (3.0, 6.0),
+            (0.0, 6.0),
+        ]],
+    ),
+    (
+        '6',
+        &[&[
+            (3.0, 0.0),
+            (1.0, 0.0),
+            (0.0, 1.0),
+            (0.0, 5.0),
+            (1.0, 6.0),
+            (3.0, 6.0),
+            (4.0, 5.0),
+            (4.0, 3.5),
+            (3.0, 2.5),
+            (0.0, 2.5),
+        ]],
+    ),
+    ('7', &[&[(0.0, 0.0), (4.0, 0.0), (1.5, 6.0)]]),
+    (
+        '8',
+        &[&[
+            (1.0, 0.0),
+            (3.0, 0.0),
+            (4.0, 1.0),
+            (4.0, 2.0),
+            (3.0, 3.0),
+            (1.0, 3.0),
+            (0.0, 4.0),
+            (0.0, 5.0),
+            (1.0, 6.0),
+            (3.0, 6.0),
+            (4.0, 5.0),
+            (4.0, 4.0),
+            (3.0, 3.0),
+            (1.0, 3.0),
+            (0.0, 2.0),
+            (0.0, 1.0),
+            (1.0, 0.0),
+        ]],
+    ),
+    (
+        '9',
+        &[&[
+            (4.0, 3.5),
+            (1.0, 3.5),
+            (0.0, 2.5),
+            (0.0, 1.0),
+            (1.0, 0.0),
+            (3.0, 0.0),
+            (4.0, 1.0),
+            (4.0, 5.0),
+            (3.0, 6.0),
+            (1.0, 6.0),
+        ]],
+    ),
+    ('+', &[&[(2.0, 1.5), (2.0, 4.5)], &[(0.5, 3.0), (3.5, 3.0)]]),
+];
+const GLYPH_WIDTH: f32 = 4.0;
+const GLYPH_HEIGHT: f32 = 6.0;
+const GLYPH_SPACING: f32 = 1.5;
+
+/// A treatment of the icon, see [`apply`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum Effect {
+    /// Shrinks the icon to leave this many transparent pixels on every side.
+    Pad(u32),
+    /// Cuts the icon to a shape.
+    Mask(Mask),
+    /// Draws a blurred shadow under the icon.
+    Shadow(Shadow),
+    /// Removes the colors, e.g. for a disabled app.
+    Grayscale,
+    /// Scales the opacity of the icon by a factor in `0..=1`, e.g. 0.5 for a disabled app.
+    Dim(f32),
+    /// Draws a badge in a corner of the icon.
+    Badge(Badge),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Mask {
+    /// A rectangle with rounded corners, whose radius is a fraction of the smallest side (`0..=0.5`).
+    Rounded(f32),
+    /// The superellipse of iOS and macOS app icons.
+    Squircle,
+    Circle,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Shadow {
+    /// The offset of the shadow in pixels, positive values going right and down.
+    pub offset_x: f32,
+    pub offset_y: f32,
+    /// The standard deviation of the blur in pixels.
+    pub blur: f32,
+    /// A `tauri::window::Color`, usually a translucent black.
+    pub color: Color,
+}
+
+impl Default for Shadow {
+    fn default() -> Self {
+        Self {
+            offset_x: 0.0,
+            offset_y: 2.0,
+            blur: 3.0,
+            color: Color(0, 0, 0, 96),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Corner {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum BadgeContent {
+    /// A number drawn on a pill, like unread notifications.
+    Count(u32),
+    /// A small image, like the icon of the app that opens a document.
+    Image(RgbaImage),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Badge {
+    pub content: BadgeContent,
+    pub corner: Corner,
+    /// The height of the badge as a fraction of the icon's smallest side.
+    pub size: f32,
+    /// The color of the pill of count badges, a `tauri::window::Color`.
+    pub color: Color,
+    /// The color of the digits of count badges.
+    pub text_color: Color,
+}
+
+impl Badge {
+    /// A red count badge in the top right corner.
+    pub fn count(count: u32) -> Self {
+        Self {
+            content: BadgeContent::Count(count),
+            corner: Corner::TopRight,
+            size: 0.4,
+            color: Color(255, 59, 48, 255),
+            text_color: Color(255, 255, 255, 255),
+        }
+    }
+
+    /// An image badge in the bottom right corner.
+    pub fn image(image: RgbaImage) -> Self {
+        Self {
+            content: BadgeContent::Image(image),
+            corner: Corner::BottomRight,
+            ..Self::count(0)
+        }
+    }
+
+    pub fn with_corner(mut self, corner: Corner) -> Self {
+        self.corner = corner;
+        self
+    }
+
+    pub fn with_size(mut self, size: f32) -> Self {
+        self.size = size;
+        self
+    }
+
+    pub fn with_colors(mut self, color: Color, text_color: Color) -> Self {
+        self.color = color;
+        self.text_color = text_color;
+        self
+    }
+}
+
+impl Effect {
+    pub fn apply(&self, mut image: RgbaImage) -> RgbaImage {
+        match self {
+            Effect::Pad(padding) => return pad(&image, *padding),
+            Effect::Mask(shape) => mask(&mut image, *shape),
+            Effect::Shadow(shadow) => return drop_shadow(&image, shadow),
+            Effect::Grayscale => grayscale(&mut image),
+            Effect::Dim(opacity) => dim(&mut image, *opacity),
+            Effect::Badge(badge) => draw_badge(&mut image, badge),
+        }
+
+        image
+    }
+}
+
+/// Applies `effects` to `image` one after the other, e.g. to render the icon
+/// of a disabled app with a count badge:
+///
+/// ```
+/// use app_icon::effects::{self, Badge, Effect, Mask};
+///
+/// let icon = app_icon::RgbaImage::new(64, 64);
+/// let icon = effects::apply(
+///     icon,
+///     &[
+///         Effect::Mask(Mask::Squircle),
+///         Effect::Grayscale,
+///         Effect::Badge(Badge::count(3)),
+///     ],
+/// );
+/// assert_eq!(icon.dimensions(), (64, 64));
+/// ```
+pub fn apply(image: RgbaImage, effects: &[Effect]) -> RgbaImage {
+    effects
+        .iter()
+        .fold(image, |image, effect| effect.apply(image))
+}
+
+fn to_paint(color: Color) -> Paint<'static> {
+    let Color(red, green, blue, alpha) = color;
+
+    let mut paint = Paint::default();
+    paint.set_color_rgba8(red, green, blue, alpha);
+    paint
+}
+
+/// A `width`×`height` rectangle at the origin whose corners are rounded with `radius`.
+fn rounded_rect(width: f32, height: f32, radius: f32) -> Option<Path> {
+    let radius = radius.clamp(0.0, width.min(height) / 2.0);
+    if radius == 0.0 {
+        return Rect::from_xywh(0.0, 0.0, width, height).map(PathBuilder::from_rect);
+    }
+
+    let control = radius * (1.0 - ARC_KAPPA);
+    let mut builder = PathBuilder::new();
+    builder.move_to(radius, 0.0);
+    builder.line_to(width - radius, 0.0);
+    builder.cubic_to(width - control, 0.0, width, control, width, radius);
+    builder.line_to(width, height - radius);
+    builder.cubic_to(
+        width,
+        height - control,
+        width - control,
+        height,
+        width - radius,
+        height,
+    );
+    builder.line_to(radius, height);
+    builder.cubic_to(control, height, 0.0, height - control, 0.0, height - radius);
+    builder.line_to(0.0, radius);
+    builder.cubic_to(0.0, control, control, 0.0, radius, 0.0);
+    builder.close();
+    builder.finish()
+}
+
+fn pad(image: &RgbaImage, padding: u32) -> RgbaImage {
+    let (width, height) = image.dimensions();
+    let mut canvas = RgbaImage::new(width, height);
+
+    let (inner_width, inner_height) = (
+        width.saturating_sub(padding.saturating_mul(2)),
+        height.saturating_sub(padding.saturating_mul(2)),
+    );
+    if inner_width == 0 || inner_height == 0 {
+        return canvas;
+    }
+
+    let inner = resample::resample(
+        image.clone(),
+        &IconSize::new(f64::from(inner_width), f64::from(inner_height)),
+    );
+    imageops::overlay(&mut canvas, &inner, i64::from(padding), i64::from(padding));
+
+    canvas
+}
+
+/// The outline of `shape` filling a `width`×`height` image.
+fn mask_path(shape: Mask, width: f32, height: f32) -> Option<Path> {
+    match shape {
+        Mask::Rounded(radius) => {
+            rounded_rect(width, height, radius.clamp(0.0, 0.5) * width.min(height))
+        }
+        Mask::Circle => PathBuilder::from_oval(Rect::from_xywh(0.0, 0.0, width, height)?),
+        Mask::Squircle => {
+            let exponent = 2.0 / SQUIRCLE_EXPONENT;
+            let mut builder = PathBuilder::new();
+
+            for index in 0..SQUIRCLE_POINTS {
+                let angle = std::f32::consts::TAU * index as f32 / SQUIRCLE_POINTS as f32;
+                let (sin, cos) = angle.sin_cos();
+                let x = width / 2.0 * (1.0 + cos.signum() * cos.abs().powf(exponent));
+                let y = height / 2.0 * (1.0 + sin.signum() * sin.abs().powf(exponent));
+
+                if index == 0 {
+                    builder.move_to(x, y);
+                } else {
+                    builder.line_to(x, y);
+                }
+            }
+
+            builder.close();
+            builder.finish()
+        }
+    }
+}
+
+fn mask(image: &mut RgbaImage, shape: Mask) {
+    let (width, height) = image.dimensions();
+    let (Some(mut coverage), Some(path)) = (
+        Pixmap::new(width, height),
+        mask_path(shape, width as f32, height as f32),
+    ) else {
+        return;
+    };
+
+    coverage.fill_path(
+        &path,
+        &to_paint(Color(255, 255, 255, 255)),
+        FillRule::Winding,
+        Transform::identity(),
+        None,
+    );
+
+    for (pixel, covered) in image.pixels_mut().zip(coverage.pixels()) {
+        let covered = covered.alpha();
+        pixel[3] = ((u16::from(pixel[3]) * u16::from(covered) + 127) / 255) as u8;
+    }
+}
+
+fn drop_shadow(image: &RgbaImage, shadow: &Shadow) -> RgbaImage {
+    let (width, height) = image.dimensions();
+    let Color(red, green, blue, alpha) = shadow.color;
+    let (offset_x, offset_y) = (
+        shadow.offset_x.round() as i64,
+        shadow.offset_y.round() as i64,
+    );
+
+    // the shadow keeps its color everywhere, so blurring only spreads its alpha
+    let mut layer = RgbaImage::from_pixel(width, height, Rgba([red, green, blue, 0]));
+    for (x, y, pixel) in layer.enumerate_pixels_mut() {
+        let (source_x, source_y) = (i64::from(x) - offset_x, i64::from(y) - offset_y);
+
+        if (0..i64::from(width)).contains(&source_x) && (0..i64::from(height)).contains(&source_y) {
+            let source = image.get_pixel(source_x as u32, source_y as u32);
+            pixel[3] = ((u16::from(source[3]) * u16::from(alpha) + 127) / 255) as u8;
+        }
+    }
+
+    let mut layer = if shadow.blur > 0.0 {
+        imageops::blur(&layer, shadow.blur)
+    } else {
+        layer
+    };
+    imageops::overlay(&mut layer, image, 0, 0);
+
+    layer
+}
+
+fn grayscale(image: &mut RgbaImage) {
+    for pixel in image.pixels_mut() {
+        let [red, green, blue, alpha] = pixel.0;
+        let luma = 0.2126 * f32::from(red) + 0.7152 * f32::from(green) + 0.0722 * f32::from(blue);
+        let luma = luma.round().min(255.0) as u8;
+
+        *pixel = Rgba([luma, luma, luma, alpha]);
+    }
+}
+
+fn dim(image: &mut RgbaImage, opacity: f32) {
+    let opacity = opacity.clamp(0.0, 1.0);
+
+    for pixel in image.pixels_mut() {
+        pixel[3] = (f32::from(pixel[3]) * opacity).round() as u8;
+    }
+}
+
+/// A pill of height `size` with `count` written on it.
+fn count_badge(count: u32, size: f32, badge: &Badge) -> RgbaImage {
+    let text = if count > MAX_COUNT {
+        format!("{MAX_COUNT}+")
+    } else {
+        count.to_string()
+    };
+
+    // the digits are half as high as the pill
+    let unit = size / 2.0 / GLYPH_HEIGHT;
+    let text_width =
+        unit * (text.chars().count() as f32 * (GLYPH_WIDTH + GLYPH_SPACING) - GLYPH_SPACING);
+    let width = size.max(text_width + size * 0.6);
+
+    let Some(mut pixmap) = Pixmap::new(width.ceil() as u32, size.ceil() as u32) else {
+        return RgbaImage::new(0, 0);
+    };
+    if let Some(pill) = rounded_rect(width, size, size / 2.0) {
+        pixmap.fill_path(
+            &pill,
+            &to_paint(badge.color),
+            FillRule::Winding,
+            Transform::identity(),
+            None,
+        );
+    }
+
+    let (left, top) = (
+        (width - text_width) / 2.0,
+        (size - GLYPH_HEIGHT * unit) / 2.0,
+    );
+    let mut strokes = PathBuilder::new();
+    for (index, character) in text.chars().enumerate() {
+        let Some((_, glyph_strokes)) = GLYPHS.iter().find(|(glyph, _)| *glyph == character) else {
+            continue;
+        };
+        let x = left + index as f32 * (GLYPH_WIDTH + GLYPH_SPACING) * unit;
+
+        for stroke in glyph_strokes.iter() {
+            for (point, (glyph_x, glyph_y)) in stroke.iter().enumerate() {
+                let (point_x, point_y) = (x + glyph_x * unit, top + glyph_y * unit);
+
+                if point == 0 {
+                    strokes.move_to(point_x, point_y);
+                } else {
+                    strokes.line_to(point_x, point_y);
+                }
+            }
+        }
+    }
+
+    if let Some(strokes) = strokes.finish() {
+        let stroke = Stroke {
+            width: unit * 1.2,
+            line_cap: LineCap::Round,
+            line_join: LineJoin::Round,
+            ..Stroke::default()
+        };
+        pixmap.stroke_path(
+            &strokes,
+            &to_paint(badge.text_color),
+            &stroke,
+            Transform::identity(),
+            None,
+        );
+    }
+
+    pixmap_to_image(&pixmap)
+}
+
+fn draw_badge(image: &mut RgbaImage, badge: &Badge) {
+    let (width, height) = image.dimensions();
+    let size = (width.min(height) as f32 * badge.size.clamp(0.0, 1.0)).round();
+    if size < 1.0 {
+        return;
+    }
+
+    let overlay = match &badge.content {
+        BadgeContent::Count(count) => count_badge(*count, size, badge),
+        BadgeContent::Image(badge_image) => {
+            resample::resample(badge_image.clone(), &IconSize::square(f64::from(size)))
+        }
+    };
+
+    let (overlay_width, overlay_height) = overlay.dimensions();
+    let x = match badge.corner {
+        Corner::TopLeft | Corner::BottomLeft => 0,
+        Corner::TopRight | Corner::BottomRight => i64::from(width) - i64::from(overlay_width),
+    };
+    let y = match badge.corner {
+        Corner::TopLeft | Corner::TopRight => 0,
+        Corner::BottomLeft | Corner::BottomRight => i64::from(height) - i64::from(overlay_height),
+    };
+
+    imageops::overlay(image, &overlay, x, y);
+}
@@ -0,0 +1,90 @@
+#![cfg(test)]
+use image::{Rgba, RgbaImage};
+use tauri_utils::config::Color;
+
+use super::{apply, Badge, Corner, Effect, Mask, Shadow};
+
+fn icon() -> RgbaImage {
+    RgbaImage::from_pixel(64, 64, Rgba([200, 40, 40, 255]))
+}
+
+#[test]
+fn it_pads_to_the_same_canvas() {
+    let image = apply(icon(), &[Effect::Pad(8)]);
+
+    assert_eq!(image.dimensions(), (64, 64));
+    assert_eq!(image.get_pixel(4, 4)[3], 0);
+    assert_eq!(image.get_pixel(32, 32), &Rgba([200, 40, 40, 255]));
+
+    // padding larger than the icon leaves an empty canvas
+    let image = apply(icon(), &[Effect::Pad(u32::MAX)]);
+    assert_eq!(image.dimensions(), (64, 64));
+    assert_eq!(image.get_pixel(32, 32)[3], 0);
+}
+
+#[test]
+fn it_masks_shapes() {
+    for shape in [Mask::Rounded(0.25), Mask::Squircle, Mask::Circle] {
+        let image = apply(icon(), &[Effect::Mask(shape)]);
+
+        assert_eq!(image.get_pixel(0, 0)[3], 0, "{shape:?}");
+        assert_eq!(image.get_pixel(32, 32)[3], 255, "{shape:?}");
+        assert_eq!(image.get_pixel(32, 1)[3], 255, "{shape:?}");
+    }
+
+    // a squircle is fuller than a circle in the corners
+    let squircle = apply(icon(), &[Effect::Mask(Mask::Squircle)]);
+    let circle = apply(icon(), &[Effect::Mask(Mask::Circle)]);
+    assert!(squircle.get_pixel(8, 8)[3] > circle.get_pixel(8, 8)[3]);
+}
+
+#[test]
+fn it_draws_shadows_under_the_icon() {
+    let shadow = Shadow {
+        offset_x: 0.0,
+        offset_y: 4.0,
+        blur: 0.0,
+        color: Color(0, 0, 0, 128),
+    };
+    let image = apply(icon(), &[Effect::Pad(8), Effect::Shadow(shadow)]);
+
+    assert_eq!(image.get_pixel(32, 32), &Rgba([200, 40, 40, 255]));
+    assert_eq!(image.get_pixel(32, 58), &Rgba([0, 0, 0, 128]));
+    assert_eq!(image.get_pixel(32, 4)[3], 0);
+
+    let blurred = apply(icon(), &[Effect::Pad(8), Effect::Shadow(Shadow::default())]);
+    let alpha = blurred.get_pixel(32, 59)[3];
+    assert!(alpha > 0 && alpha < 96);
+}
+
+#[test]
+fn it_renders_disabled_icons() {
+    let image = apply(icon(), &[Effect::Grayscale, Effect::Dim(0.5)]);
+
+    assert_eq!(image.get_pixel(10, 10), &Rgba([74, 74, 74, 128]));
+}
+
+#[test]
+fn it_draws_badges_in_corners() {
+    let image = apply(icon(), &[Effect::Badge(Badge::count(7))]);
+
+    // the pill is as wide as it's high for a single digit
+    assert_eq!(image.get_pixel(63 - 13, 2), &Rgba([255, 59, 48, 255]));
+    assert_eq!(image.get_pixel(63 - 13, 63 - 2), &Rgba([200, 40, 40, 255]));
+    assert_eq!(image.get_pixel(20, 13), &Rgba([200, 40, 40, 255]));
+
+    let image = apply(icon(), &[Effect::Badge(Badge::count(120))]);
+    let white = image
+        .pixels()
+        .filter(|pixel| **pixel == Rgba([255, 255, 255, 255]))
+        .count();
+    assert!(white > 20);
+
+    let badge = Badge::image(RgbaImage::from_pixel(8, 8, Rgba([0, 0, 255, 255])))
+        .with_corner(Corner::BottomLeft)
+        .with_size(0.25);
+    let image = apply(icon(), &[Effect::Badge(badge)]);
+    assert_eq!(image.get_pixel(2, 61), &Rgba([0, 0, 255, 255]));
+    assert_eq!(image.get_pixel(17, 61), &Rgba([200, 40, 40, 255]));
+    assert_eq!(image.get_pixel(2, 2), &Rgba([200, 40, 40, 255]));
+}
@@ -0,0 +1,158 @@
+use std::io::Cursor;
+
+use image::{
+    codecs::{
+        ico::{IcoEncoder, IcoFrame},
+        jpeg::JpegEncoder,
+        webp::WebPEncoder,
+    },
+    imageops::{self, FilterType},
+    ExtendedColorType, ImageFormat, ImageResult, RgbImage, RgbaImage,
+};
+
+use crate::icns;
+
+mod tests;
+
+/// Sizes stored in a multi-resolution `.ico`, the format can't hold anything larger than 256px.
+const ICO_SIZES: &[u32] = &[16, 24, 32, 48, 64, 128, 256];
+
+/// The format the extracted icon is encoded in.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum IconFormat {
+    #[default]
+    Png,
+    /// Lossless WebP.
+    WebP,
+    /// JPEG at `quality` (1-100). JPEG has no alpha channel, so transparent
+    /// pixels are blended over the `background` RGB color.
+    Jpeg { quality: u8, background: [u8; 3] },
+    /// Windows `.ico` holding every standard size (16 to 256px) up to the requested size.
+    Ico,
+    /// Apple `.icns` holding every standard size (16 to 1024px) up to the requested size.
+    Icns,
+}
+
+impl IconFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            IconFormat::Png => "png",
+            IconFormat::WebP => "webp",
+            IconFormat::Jpeg { .. } => "jpg",
+            IconFormat::Ico => "ico",
+            IconFormat::Icns => "icns",
+        }
+    }
+
+    pub fn mime_type(&self) -> &'static str {
+        match self {
+            IconFormat::Png => "image/png",
+            IconFormat::WebP => "image/webp",
+            IconFormat::Jpeg { .. } => "image/jpeg",
+            IconFormat::Ico => "image/vnd.microsoft.icon",
+            IconFormat::Icns => "image/icns",
+        }
+    }
+}
+
+/// The standard sizes up to the icon's own size, or the smallest one for tiny icons.
+fn sizes_up_to(image: &RgbaImage, standard_sizes: &[u32]) -> Vec<u32> {
+    let size = image.width().max(image.height());
+
+    let sizes: Vec<u32> = standard_sizes
+        .iter()
+        .copied()
+        .filter(|standard_size| *standard_size <= size)
+        .collect();
+
+    if sizes.is_empty() {
+        standard_sizes[..1].to_vec()
+    } else {
+        sizes
+    }
+}
+
+fn resized(image: &RgbaImage, size: u32) -> RgbaImage {
+    if image.dimensions() == (size, size) {
+        image.clone()
+    } else {
+        imageops::resize(image, size, size, FilterType::Lanczos3)
+    }
+}
+
+fn flatten(image: &RgbaImage, background: [u8; 3]) -> RgbImage {
+    RgbImage::from_fn(image.width(), image.height(), |x, y| {
+        let [r, g, b, a] = image.get_pixel(x, y).0;
+        let a = u16::from(a);
+        let blend = |channel: u8, background: u8| {
+            ((u16::from(channel) * a + u16::from(background) * (255 - a) + 127) / 255) as u8
+        };
+
+        image::Rgb([
+            blend(r, background[0]),
+            blend(g, background[1]),
+            blend(b, background[2]),
+        ])
+    })
+}
+
+fn ico(image: &RgbaImage) -> ImageResult<Vec<u8>> {
+    let frames = sizes_up_to(image, ICO_SIZES)
+        .into_iter()
+        .map(|size| {
+            let frame = resized(image, size);
+            IcoFrame::as_png(frame.as_raw(), size, size, ExtendedColorType::Rgba8)
+        })
+        .collect::<ImageResult<Vec<_>>>()?;
+
+    let mut bytes = Vec::new();
+    IcoEncoder::new(&mut bytes).encode_images(&frames)?;
+
+    Ok(bytes)
+}
+
+/// Encodes the icon in `format`.
+pub(crate) fn encode(image: &RgbaImage, format: IconFormat) -> ImageResult<Vec<u8>> {
+    match format {
+        IconFormat::Png => png(image),
+        IconFormat::WebP => {
+            let mut bytes = Vec::new();
+            WebPEncoder::new_lossless(&mut bytes).encode(
+                image.as_raw(),
+                image.width(),
+                image.height(),
+                ExtendedColorType::Rgba8,
+            )?;
+
+            Ok(bytes)
+        }
+        IconFormat::Jpeg {
+            quality,
+            background,
+        } => {
+            let mut bytes = Vec::new();
+            JpegEncoder::new_with_quality(&mut bytes, quality.clamp(1, 100))
+                .encode_image(&flatten(image, background))?;
+
+            Ok(bytes)
+        }
+        IconFormat::Ico => ico(image),
+        IconFormat::Icns => {
+            let images = sizes_up_to(image, icns::SIZES)
+                .into_iter()
+                .map(|size| resized(image, size))
+                .collect::<Vec<_>>();
+
+            icns::write(&images)
+        }
+    }
+}
+
+/// Encodes the icon as PNG bytes.
+pub(crate) fn png(image: &RgbaImage) -> ImageResult<Vec<u8>> {
+    let mut bytes = Cursor::new(Vec::new());
+
+    image.write_to(&mut bytes, ImageFormat::Png)?;
+
+    Ok(bytes.into_inner())
+}
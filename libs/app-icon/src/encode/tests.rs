@@ -0,0 +1,67 @@
+#![cfg(test)]
+use image::{ImageFormat, Rgba, RgbaImage};
+
+use super::{encode, IconFormat};
+
+fn icon(size: u32) -> RgbaImage {
+    RgbaImage::from_pixel(size, size, Rgba([0, 0, 255, 255]))
+}
+
+#[test]
+fn it_encodes_png() {
+    let bytes = encode(&icon(32), IconFormat::Png).unwrap();
+    let image = image::load_from_memory_with_format(&bytes, ImageFormat::Png).unwrap();
+    assert_eq!(image.to_rgba8(), icon(32));
+}
+
+#[test]
+fn it_encodes_lossless_webp() {
+    let bytes = encode(&icon(32), IconFormat::WebP).unwrap();
+    let image = image::load_from_memory_with_format(&bytes, ImageFormat::WebP).unwrap();
+    assert_eq!(image.to_rgba8(), icon(32));
+}
+
+#[test]
+fn it_fills_jpeg_transparency_with_background() {
+    let transparent = RgbaImage::new(16, 16);
+    let format = IconFormat::Jpeg {
+        quality: 90,
+        background: [255, 0, 0],
+    };
+    let bytes = encode(&transparent, format).unwrap();
+    let image = image::load_from_memory_with_format(&bytes, ImageFormat::Jpeg).unwrap();
+    let [r, g, b] = image.to_rgb8().get_pixel(8, 8).0;
+    assert!(r > 240 && g < 16 && b < 16);
+}
+
+#[test]
+fn it_encodes_every_ico_size_up_to_the_icon_size() {
+    let bytes = encode(&icon(48), IconFormat::Ico).unwrap();
+    let count = u16::from_le_bytes([bytes[4], bytes[5]]);
+    assert_eq!(count, 4);
+
+    let widths: Vec<u8> = (0..usize::from(count))
+        .map(|entry| bytes[6 + entry * 16])
+        .collect();
+    assert_eq!(widths, [16, 24, 32, 48]);
+
+    let image = image::load_from_memory_with_format(&bytes, ImageFormat::Ico).unwrap();
+    assert_eq!(image.width(), 48);
+}
+
+#[test]
+fn it_keeps_at_least_one_size_for_tiny_icons() {
+    let bytes = encode(&icon(8), IconFormat::Ico).unwrap();
+    assert_eq!(u16::from_le_bytes([bytes[4], bytes[5]]), 1);
+    assert_eq!(bytes[6], 16);
+}
+
+#[test]
+fn it_encodes_icns() {
+    let bytes = encode(&icon(64), IconFormat::Icns).unwrap();
+    assert_eq!(&bytes[..4], b"icns");
+    assert_eq!(
+        u32::from_be_bytes(bytes[4..8].try_into().unwrap()) as usize,
+        bytes.len()
+    );
+}
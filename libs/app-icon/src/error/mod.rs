@@ -0,0 +1,139 @@
+//! The error of every backend, so the cause of a failure can be matched the
+//! same way on each OS, e.g. to tell the user an app has no icon.
+
+use std::{
+    error::Error,
+    io,
+    path::{Path, PathBuf},
+};
+
+use image::ImageError;
+use thiserror::Error;
+
+use crate::{bundle::BundleIconError, icns::IcnsError, pe::PeIconError};
+
+mod tests;
+
+#[derive(Error, Debug)]
+#[non_exhaustive]
+pub enum GetAppIconError {
+    /// No installed application has the id, or nothing exists at the path.
+    #[error("could not find '{0}'")]
+    NotFound(String),
+    /// The path exists, but is not an application the icon can be got from.
+    #[error("'{}' is not an application", .0.display())]
+    NotAnApplication(PathBuf),
+    /// The application or file type doesn't declare an icon, or the icon it
+    /// names can't be found.
+    #[error("'{0}' has no icon")]
+    NoIcon(String),
+    /// The icon is stored in a format this crate can't read.
+    #[error("unsupported icon format: {0}")]
+    UnsupportedFormat(String),
+    /// The icon is corrupted, or the OS failed to draw it.
+    #[error("failed to decode the icon")]
+    Decode(#[source] Box<dyn Error + Send + Sync>),
+    /// The icon couldn't be encoded in the requested format.
+    #[error("failed to encode the icon")]
+    Encode(#[source] ImageError),
+    /// Reading the application or writing the icon failed.
+    #[error("failed to access '{}'", .path.display())]
+    Io {
+        path: PathBuf,
+        #[source]
+        source: io::Error,
+    },
+}
+
+impl GetAppIconError {
+    pub(crate) fn not_found(path: &Path) -> Self {
+        Self::NotFound(path.display().to_string())
+    }
+
+    pub(crate) fn no_icon(path: &Path) -> Self {
+        Self::NoIcon(path.display().to_string())
+    }
+
+    pub(crate) fn decode(error: impl Into<Box<dyn Error + Send + Sync>>) -> Self {
+        Self::Decode(error.into())
+    }
+
+    pub(crate) fn io(path: &Path, source: io::Error) -> Self {
+        Self::Io {
+            path: path.to_path_buf(),
+            source,
+        }
+    }
+
+    /// The error of decoding an icon file, telling formats the `image` crate
+    /// wasn't built with apart from corrupted files.
+    pub(crate) fn image(error: ImageError) -> Self {
+        match error {
+            ImageError::Unsupported(error) => Self::UnsupportedFormat(error.to_string()),
+            error => Self::decode(error),
+        }
+    }
+
+    /// The error of rendering `data` as an SVG, after it failed to decode as a
+    /// raster image: data that doesn't parse as SVG either is in a format that
+    /// isn't supported, like XPM.
+    #[cfg(target_os = "linux")]
+    pub(crate) fn svg(error: crate::svg::SvgError) -> Self {
+        use crate::svg::SvgError;
+
+        match error {
+            SvgError::ParseError => {
+                Self::UnsupportedFormat("not a raster image or SVG document".to_string())
+            }
+            error => Self::decode(error),
+        }
+    }
+
+    pub(crate) fn icns(error: IcnsError) -> Self {
+        match error {
+            IcnsError::NoSupportedIcon => Self::UnsupportedFormat(error.to_string()),
+            error => Self::decode(error),
+        }
+    }
+
+    pub(crate) fn bundle(app_path: &Path, error: BundleIconError) -> Self {
+        match error {
+            BundleIconError::InfoPlistReadError(source) => {
+                Self::io(&app_path.join("Contents/Info.plist"), source)
+            }
+            BundleIconError::InvalidInfoPlist => Self::decode(error),
+            BundleIconError::NoIconFile | BundleIconError::IconFileDoesNotExist => {
+                Self::no_icon(app_path)
+            }
+            BundleIconError::IconFileReadError(source) => Self::io(app_path, source),
+            BundleIconError::IcnsDecodeError(error) => Self::icns(error),
+        }
+    }
+
+    pub(crate) fn pe(app_path: &Path, error: PeIconError) -> Self {
+        match error {
+            PeIconError::ReadError(source) => Self::io(app_path, source),
+            PeIconError::InvalidHeader => Self::NotAnApplication(app_path.to_path_buf()),
+            PeIconError::NoIcon => Self::no_icon(app_path),
+            PeIconError::DecodeError => Self::decode(error),
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    pub(crate) fn appimage(app_path: &Path, error: crate::linux::AppImageError) -> Self {
+        use crate::linux::{AppImageError, SquashfsError};
+
+        match error {
+            AppImageError::ReadError(source) => Self::io(app_path, source),
+            AppImageError::NotAnAppImage => Self::NotAnApplication(app_path.to_path_buf()),
+            AppImageError::NoIcon => Self::no_icon(app_path),
+            AppImageError::SquashfsError(SquashfsError::ReadError(source)) => {
+                Self::io(app_path, source)
+            }
+            AppImageError::SquashfsError(SquashfsError::UnsupportedCompression(_)) => {
+                Self::UnsupportedFormat(error.to_string())
+            }
+            AppImageError::SquashfsError(_) => Self::decode(error),
+        }
+    }
+}
@@ -0,0 +1,84 @@
+#![cfg(test)]
+use std::{error::Error, io, path::Path};
+
+use super::GetAppIconError;
+use crate::{
+    bundle::BundleIconError, check_save_path, fixture_dir, icns::IcnsError, pe::PeIconError,
+};
+
+#[test]
+fn it_classifies_parser_errors() {
+    let app_path = Path::new("/Applications/Foo.app");
+
+    assert!(matches!(
+        GetAppIconError::bundle(app_path, BundleIconError::NoIconFile),
+        GetAppIconError::NoIcon(app) if app == "/Applications/Foo.app"
+    ));
+    assert!(matches!(
+        GetAppIconError::bundle(
+            app_path,
+            BundleIconError::IcnsDecodeError(IcnsError::NoSupportedIcon)
+        ),
+        GetAppIconError::UnsupportedFormat(_)
+    ));
+    let error = GetAppIconError::bundle(
+        app_path,
+        BundleIconError::InfoPlistReadError(io::Error::from(io::ErrorKind::PermissionDenied)),
+    );
+    assert!(matches!(
+        &error,
+        GetAppIconError::Io { path, source }
+            if *path == app_path.join("Contents/Info.plist")
+                && source.kind() == io::ErrorKind::PermissionDenied
+    ));
+    assert!(matches!(
+        GetAppIconError::bundle(app_path, BundleIconError::InvalidInfoPlist),
+        GetAppIconError::Decode(_)
+    ));
+
+    let exe_path = Path::new("/foo/Foo.exe");
+    assert!(matches!(
+        GetAppIconError::pe(exe_path, PeIconError::InvalidHeader),
+        GetAppIconError::NotAnApplication(path) if path == exe_path
+    ));
+
+    let error = GetAppIconError::pe(exe_path, PeIconError::DecodeError);
+    assert!(matches!(error, GetAppIconError::Decode(_)));
+    // the cause is only told by the source, not repeated in the message
+    assert_eq!(error.to_string(), "failed to decode the icon");
+    assert_eq!(
+        error.source().unwrap().to_string(),
+        "failed to decode the icon resource"
+    );
+}
+
+#[test]
+fn it_tells_unsupported_images_from_corrupted_ones() {
+    // a PNG signature followed by garbage
+    let corrupted = image::load_from_memory(b"\x89PNG\r\n\x1a\n garbage").unwrap_err();
+    assert!(matches!(
+        GetAppIconError::image(corrupted),
+        GetAppIconError::Decode(_)
+    ));
+
+    let unsupported = image::load_from_memory(b"/* XPM */").unwrap_err();
+    assert!(matches!(
+        GetAppIconError::image(unsupported),
+        GetAppIconError::UnsupportedFormat(_)
+    ));
+}
+
+#[test]
+fn it_checks_the_save_path() {
+    let dir = fixture_dir("save-path");
+
+    assert!(check_save_path(&dir.join("foo.png")).is_ok());
+    assert!(check_save_path(Path::new("foo.png")).is_ok());
+
+    let error = check_save_path(&dir.join("missing/foo.png")).unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        format!("failed to access '{}'", dir.join("missing").display())
+    );
+    assert_eq!(error.source().unwrap().to_string(), "entity not found");
+}
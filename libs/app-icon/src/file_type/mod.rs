@@ -0,0 +1,12 @@
+use std::path::Path;
+
+/// What to get the file type icon of, see [`crate::get_file_type_icon`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FileType<'a> {
+    /// An existing file or directory, whose type is told from its name and content.
+    Path(&'a Path),
+    /// A file name extension, with or without the leading dot (`pdf`, `.tar.gz`).
+    Extension(&'a str),
+    /// A MIME type (`image/png`).
+    MimeType(&'a str),
+}
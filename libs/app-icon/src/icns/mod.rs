@@ -0,0 +1,304 @@
+//! Reading and writing Apple `.icns` files without AppKit.
+
+use image::{ImageFormat, ImageResult, RgbaImage};
+use thiserror::Error;
+
+use crate::{encode, IconAppearance, IconVariant};
+
+mod tests;
+
+/// The pixel sizes an `.icns` file can hold.
+pub(crate) const SIZES: &[u32] = &[16, 32, 64, 128, 256, 512, 1024];
+
+/// The PNG backed icon types and the pixel size of each, `ic11` to `ic14` are
+/// the @2x variants of the next smaller point size.
+const PNG_TYPES: &[(&[u8; 4], u32)] = &[
+    (b"icp4", 16),
+    (b"icp5", 32),
+    (b"ic11", 32),
+    (b"icp6", 64),
+    (b"ic12", 64),
+    (b"ic07", 128),
+    (b"ic08", 256),
+    (b"ic13", 256),
+    (b"ic09", 512),
+    (b"ic14", 512),
+    (b"ic10", 1024),
+];
+
+/// The legacy RLE compressed RGB icon types, with the pixel size and the
+/// type of the 8-bit mask holding their alpha channel.
+const RGB_TYPES: &[(&[u8; 4], u32, &[u8; 4])] = &[
+    (b"is32", 16, b"s8mk"),
+    (b"il32", 32, b"l8mk"),
+    (b"ih32", 48, b"h8mk"),
+    (b"it32", 128, b"t8mk"),
+];
+
+/// The RLE compressed ARGB icon types, which may also hold a PNG.
+const ARGB_TYPES: &[(&[u8; 4], u32)] = &[(b"ic04", 16), (b"ic05", 32)];
+
+/// The types of the nested `.icns` files holding the variants for other
+/// appearances: the dark mode icon, and the template the system tints.
+const VARIANT_TYPES: &[(&[u8; 4], IconAppearance)] = &[
+    (b"\xfd\xd9\x2f\xa8", IconAppearance::Dark),
+    (b"sbtp", IconAppearance::Tinted),
+];
+
+const HEADER_LEN: usize = 8;
+
+const PNG_MAGIC: &[u8] = b"\x89PNG\r\n\x1a\n";
+
+#[derive(Error, Debug, PartialEq)]
+pub enum IcnsError {
+    #[error("data is not an icns file")]
+    InvalidHeader,
+    #[error("icns file has no icon in a supported format")]
+    NoSupportedIcon,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Entry<'a> {
+    /// PNG, or JPEG 2000 which isn't supported.
+    Encoded(&'a [u8]),
+    Argb(&'a [u8]),
+    Rgb {
+        data: &'a [u8],
+        mask: Option<&'a [u8]>,
+    },
+}
+
+/// The four character type and the data of a block.
+type Block<'a> = (&'a [u8], &'a [u8]);
+
+fn blocks(bytes: &[u8]) -> Result<Vec<Block<'_>>, IcnsError> {
+    if bytes.len() < HEADER_LEN || &bytes[..4] != b"icns" {
+        return Err(IcnsError::InvalidHeader);
+    }
+
+    let total_len = u32::from_be_bytes([bytes[4], bytes[5], bytes[6], bytes[7]]) as usize;
+    let bytes = &bytes[..total_len.clamp(HEADER_LEN, bytes.len())];
+
+    let mut blocks = vec![];
+    let mut offset = HEADER_LEN;
+
+    while offset + HEADER_LEN <= bytes.len() {
+        let kind = &bytes[offset..offset + 4];
+        let len = u32::from_be_bytes([
+            bytes[offset + 4],
+            bytes[offset + 5],
+            bytes[offset + 6],
+            bytes[offset + 7],
+        ]) as usize;
+
+        if len < HEADER_LEN || offset + len > bytes.len() {
+            break;
+        }
+
+        blocks.push((kind, &bytes[offset + HEADER_LEN..offset + len]));
+        offset += len;
+    }
+
+    Ok(blocks)
+}
+
+fn entries<'a>(blocks: &[Block<'a>]) -> Vec<(u32, Entry<'a>)> {
+    let find = |kind: &[u8; 4]| {
+        blocks
+            .iter()
+            .find(|(block_kind, _)| *block_kind == kind)
+            .map(|(_, data)| *data)
+    };
+
+    let mut entries = vec![];
+
+    for (kind, size) in PNG_TYPES {
+        if let Some(data) = find(kind) {
+            entries.push((*size, Entry::Encoded(data)));
+        }
+    }
+
+    for (kind, size) in ARGB_TYPES {
+        if let Some(data) = find(kind) {
+            if data.starts_with(b"ARGB") {
+                entries.push((*size, Entry::Argb(&data[4..])));
+            } else {
+                entries.push((*size, Entry::Encoded(data)));
+            }
+        }
+    }
+
+    for (kind, size, mask_kind) in RGB_TYPES {
+        if let Some(data) = find(kind) {
+            // it32 data starts with four zero bytes
+            let data = if *kind == b"it32" && data.len() >= 4 {
+                &data[4..]
+            } else {
+                data
+            };
+
+            entries.push((
+                *size,
+                Entry::Rgb {
+                    data,
+                    mask: find(mask_kind),
+                },
+            ));
+        }
+    }
+
+    entries
+}
+
+/// Unpacks the icns flavor of PackBits: a header byte below 0x80 is followed
+/// by `header + 1` literal bytes, otherwise the next byte repeats
+/// `header - 125` times. Each channel is packed one after the other.
+fn unpack_channels(data: &[u8], channels: usize, pixels: usize) -> Option<Vec<Vec<u8>>> {
+    let mut planes = Vec::with_capacity(channels);
+    let mut offset = 0;
+
+    for _ in 0..channels {
+        let mut plane = Vec::with_capacity(pixels);
+
+        while plane.len() < pixels {
+            let header = *data.get(offset)? as usize;
+            offset += 1;
+
+            if header < 0x80 {
+                let literal = data.get(offset..offset + header + 1)?;
+                plane.extend_from_slice(literal);
+                offset += header + 1;
+            } else {
+                let value = *data.get(offset)?;
+                plane.extend(std::iter::repeat(value).take(header - 125));
+                offset += 1;
+            }
+        }
+
+        plane.truncate(pixels);
+        planes.push(plane);
+    }
+
+    Some(planes)
+}
+
+fn decode_entry(size: u32, entry: Entry) -> Option<RgbaImage> {
+    let pixels = (size * size) as usize;
+
+    match entry {
+        Entry::Encoded(data) => {
+            if !data.starts_with(PNG_MAGIC) {
+                return None;
+            }
+
+            image::load_from_memory_with_format(data, ImageFormat::Png)
+                .ok()
+                .map(|image| image.into_rgba8())
+        }
+        Entry::Argb(data) => {
+            let planes = unpack_channels(data, 4, pixels)?;
+            let buf = (0..pixels)
+                .flat_map(|i| [planes[1][i], planes[2][i], planes[3][i], planes[0][i]])
+                .collect();
+
+            RgbaImage::from_raw(size, size, buf)
+        }
+        Entry::Rgb { data, mask } => {
+            let planes = if data.len() == pixels * 4 {
+                // uncompressed, with an unused leading byte per pixel
+                (1..4)
+                    .map(|channel| data.iter().skip(channel).step_by(4).copied().collect())
+                    .collect()
+            } else {
+                unpack_channels(data, 3, pixels)?
+            };
+            let alpha = mask.filter(|mask| mask.len() >= pixels);
+            let buf = (0..pixels)
+                .flat_map(|i| {
+                    [
+                        planes[0][i],
+                        planes[1][i],
+                        planes[2][i],
+                        alpha.map_or(255, |alpha| alpha[i]),
+                    ]
+                })
+                .collect();
+
+            RgbaImage::from_raw(size, size, buf)
+        }
+    }
+}
+
+/// Decodes the icon of an `.icns` file closest to `size` pixels, preferring
+/// the smallest one that is at least as large, then the largest smaller one.
+/// PNG and the legacy ARGB and RGB + mask entries are supported, JPEG 2000
+/// entries are skipped.
+pub fn decode(bytes: &[u8], size: u32) -> Result<RgbaImage, IcnsError> {
+    let blocks = blocks(bytes)?;
+
+    let mut entries = entries(&blocks);
+
+    entries.sort_by_key(|(entry_size, _)| {
+        if *entry_size >= size {
+            (0, *entry_size)
+        } else {
+            (1, u32::MAX - *entry_size)
+        }
+    });
+
+    entries
+        .into_iter()
+        .find_map(|(entry_size, entry)| decode_entry(entry_size, entry))
+        .ok_or(IcnsError::NoSupportedIcon)
+}
+
+/// Like [`decode`], but decodes the variant for `appearance` when the file
+/// has one, telling which appearance the decoded icon is for.
+pub fn decode_variant(
+    bytes: &[u8],
+    size: u32,
+    appearance: IconAppearance,
+) -> Result<IconVariant, IcnsError> {
+    let blocks = blocks(bytes)?;
+
+    let variant = VARIANT_TYPES
+        .iter()
+        .filter(|(_, variant_appearance)| *variant_appearance == appearance)
+        .filter_map(|(kind, _)| blocks.iter().find(|(block_kind, _)| block_kind == kind))
+        .find_map(|(_, data)| decode(data, size).ok());
+
+    match variant {
+        Some(image) => Ok(IconVariant { image, appearance }),
+        None => decode(bytes, size).map(IconVariant::light),
+    }
+}
+
+fn push_block(bytes: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    bytes.extend_from_slice(kind);
+    bytes.extend_from_slice(&((HEADER_LEN + data.len()) as u32).to_be_bytes());
+    bytes.extend_from_slice(data);
+}
+
+/// Writes an `.icns` file with a PNG entry for every image whose size is one of [`SIZES`].
+pub(crate) fn write(images: &[RgbaImage]) -> ImageResult<Vec<u8>> {
+    let mut entries = Vec::new();
+
+    for image in images {
+        let size = image.width();
+
+        if image.height() != size || !SIZES.contains(&size) {
+            continue;
+        }
+
+        let png = encode::png(image)?;
+
+        for (kind, _) in PNG_TYPES.iter().filter(|(_, type_size)| *type_size == size) {
+            push_block(&mut entries, kind, &png);
+        }
+    }
+
+    let mut bytes = Vec::with_capacity(HEADER_LEN + entries.len());
+    push_block(&mut bytes, b"icns", &entries);
+
+    Ok(bytes)
+}
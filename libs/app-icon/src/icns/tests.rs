@@ -0,0 +1,154 @@
+#![cfg(test)]
+use image::{Rgba, RgbaImage};
+
+use super::{decode, decode_variant, write, IcnsError};
+use crate::IconAppearance;
+
+fn block_types(bytes: &[u8]) -> Vec<String> {
+    let mut types = vec![];
+    let mut offset = 8;
+
+    while offset < bytes.len() {
+        let len = u32::from_be_bytes(bytes[offset + 4..offset + 8].try_into().unwrap()) as usize;
+        types.push(String::from_utf8_lossy(&bytes[offset..offset + 4]).into_owned());
+        offset += len;
+    }
+
+    types
+}
+
+#[test]
+fn it_writes_png_entries_for_standard_sizes() {
+    let images = [
+        RgbaImage::from_pixel(16, 16, Rgba([255, 0, 0, 255])),
+        RgbaImage::from_pixel(32, 32, Rgba([255, 0, 0, 255])),
+        RgbaImage::from_pixel(48, 48, Rgba([255, 0, 0, 255])),
+    ];
+
+    let bytes = write(&images).unwrap();
+
+    assert_eq!(&bytes[..4], b"icns");
+    assert_eq!(
+        u32::from_be_bytes(bytes[4..8].try_into().unwrap()) as usize,
+        bytes.len()
+    );
+    assert_eq!(block_types(&bytes), ["icp4", "icp5", "ic11"]);
+    assert_eq!(&bytes[16..20], b"\x89PNG");
+}
+
+fn icns(blocks: &[(&[u8; 4], Vec<u8>)]) -> Vec<u8> {
+    let mut bytes = vec![];
+    for (kind, data) in blocks {
+        bytes.extend_from_slice(*kind);
+        bytes.extend_from_slice(&(data.len() as u32 + 8).to_be_bytes());
+        bytes.extend_from_slice(data);
+    }
+
+    let mut file = b"icns".to_vec();
+    file.extend_from_slice(&(bytes.len() as u32 + 8).to_be_bytes());
+    file.extend_from_slice(&bytes);
+    file
+}
+
+fn png(size: u32) -> Vec<u8> {
+    crate::encode::png(&RgbaImage::from_pixel(size, size, Rgba([0, 255, 0, 255]))).unwrap()
+}
+
+/// Packs `count` copies of `value` as runs of at most 130 bytes.
+fn rle_run(value: u8, mut count: usize) -> Vec<u8> {
+    let mut bytes = vec![];
+    while count > 0 {
+        let run = count.min(130);
+        if run >= 3 {
+            bytes.extend_from_slice(&[(run + 125) as u8, value]);
+        } else {
+            bytes.push(run as u8 - 1);
+            bytes.extend(std::iter::repeat(value).take(run));
+        }
+        count -= run;
+    }
+    bytes
+}
+
+#[test]
+fn it_rejects_invalid_header() {
+    assert_eq!(decode(b"not an icns", 32), Err(IcnsError::InvalidHeader));
+}
+
+#[test]
+fn it_picks_the_closest_larger_size() {
+    let bytes = icns(&[(b"icp4", png(16)), (b"icp5", png(32)), (b"ic08", png(256))]);
+
+    assert_eq!(decode(&bytes, 16).unwrap().width(), 16);
+    assert_eq!(decode(&bytes, 20).unwrap().width(), 32);
+    assert_eq!(decode(&bytes, 128).unwrap().width(), 256);
+    assert_eq!(decode(&bytes, 512).unwrap().width(), 256);
+}
+
+#[test]
+fn it_skips_jpeg_2000_entries() {
+    let jpeg_2000 = b"\0\0\0\x0cjP  \r\n\x87\n".to_vec();
+    let bytes = icns(&[(b"icp4", png(16)), (b"ic08", jpeg_2000)]);
+
+    assert_eq!(decode(&bytes, 256).unwrap().width(), 16);
+
+    let bytes = icns(&[(b"ic08", b"\0\0\0\x0cjP  \r\n\x87\n".to_vec())]);
+    assert_eq!(decode(&bytes, 256), Err(IcnsError::NoSupportedIcon));
+}
+
+#[test]
+fn it_decodes_rle_argb() {
+    let mut data = b"ARGB".to_vec();
+    for value in [128, 255, 0, 64] {
+        data.extend(rle_run(value, 16 * 16));
+    }
+    let bytes = icns(&[(b"ic04", data)]);
+
+    let image = decode(&bytes, 16).unwrap();
+    assert_eq!(image.dimensions(), (16, 16));
+    assert_eq!(image.get_pixel(3, 7), &Rgba([255, 0, 64, 128]));
+}
+
+#[test]
+fn it_decodes_rle_rgb_with_mask() {
+    let mut data = vec![];
+    for value in [10, 20, 30] {
+        data.extend(rle_run(value, 16 * 16));
+    }
+    let bytes = icns(&[(b"is32", data), (b"s8mk", vec![200; 16 * 16])]);
+
+    let image = decode(&bytes, 16).unwrap();
+    assert_eq!(image.get_pixel(15, 15), &Rgba([10, 20, 30, 200]));
+}
+
+#[test]
+fn it_decodes_what_it_writes() {
+    let bytes = write(&[RgbaImage::from_pixel(64, 64, Rgba([1, 2, 3, 4]))]).unwrap();
+
+    let image = decode(&bytes, 64).unwrap();
+    assert_eq!(image.dimensions(), (64, 64));
+    assert_eq!(image.get_pixel(0, 0), &Rgba([1, 2, 3, 4]));
+}
+
+#[test]
+fn it_decodes_appearance_variants() {
+    let dark = icns(&[(
+        b"ic07",
+        crate::encode::png(&RgbaImage::from_pixel(128, 128, Rgba([0, 0, 0, 255]))).unwrap(),
+    )]);
+    let bytes = icns(&[(b"ic07", png(128)), (b"\xfd\xd9\x2f\xa8", dark)]);
+
+    let variant = decode_variant(&bytes, 128, IconAppearance::Dark).unwrap();
+    assert_eq!(variant.appearance, IconAppearance::Dark);
+    assert_eq!(variant.image.get_pixel(0, 0), &Rgba([0, 0, 0, 255]));
+
+    // there is no template, so the regular icon is used
+    let variant = decode_variant(&bytes, 128, IconAppearance::Tinted).unwrap();
+    assert_eq!(variant.appearance, IconAppearance::Light);
+    assert_eq!(variant.image.get_pixel(0, 0), &Rgba([0, 255, 0, 255]));
+
+    assert_eq!(
+        decode(&bytes, 128).unwrap().get_pixel(0, 0),
+        &Rgba([0, 255, 0, 255])
+    );
+}
@@ -1,5 +1,30 @@
-use std::path::Path;
-use thiserror::Error;
+use std::path::{Path, PathBuf};
+
+pub use appearance::{IconAppearance, IconOptions, IconVariant};
+pub use application::Application;
+pub use encode::IconFormat;
+pub use error::GetAppIconError;
+pub use file_type::FileType;
+pub use image::RgbaImage;
+pub use resample::{IconSize, ResampleFilter};
+
+mod appearance;
+mod application;
+pub mod batch;
+pub mod bundle;
+pub mod cache;
+pub mod effects;
+mod encode;
+mod error;
+mod file_type;
+pub mod icns;
+pub mod palette;
+pub mod pe;
+#[cfg(feature = "plugin")]
+pub mod plugin;
+pub mod protocol;
+mod resample;
+pub(crate) mod svg;
 
 #[cfg(target_os = "macos")]
 mod macos;
@@ -7,32 +32,223 @@ mod macos;
 #[cfg(target_os = "windows")]
 mod windows;
 
-#[derive(Error, Debug)]
-#[error("get app icon error")]
-pub struct GetAppIconError {
+#[cfg(target_os = "linux")]
+mod linux;
+
+/// Fails with the `NotFound` I/O error of the directory to save an icon in
+/// when it doesn't exist, before the icon is extracted for nothing.
+pub(crate) fn check_save_path(save_path: &Path) -> Result<(), GetAppIconError> {
+    let parent = save_path.parent().unwrap_or(save_path);
+
+    if parent.as_os_str().is_empty() || parent.is_dir() {
+        return Ok(());
+    }
+
+    Err(GetAppIconError::io(
+        parent,
+        std::io::Error::from(std::io::ErrorKind::NotFound),
+    ))
+}
+
+/// An empty directory of the temp dir for the fixtures of a test, unique to
+/// the process so concurrent test runs don't share it.
+#[cfg(test)]
+pub(crate) fn fixture_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("app-icon-{}-{name}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// Get app icon from app bundle. You specify the path to save the icon, and the desired icon size (like 16, 32, 48, 128, 256, 512).
+///
+/// The size is either a plain `f64` for a square icon, or an [`IconSize`] with separate width and height,
+/// a HiDPI scale factor and the [`ResampleFilter`] used to scale the closest larger source icon to exactly that size.
+///
+/// On Linux the app is a `.desktop` file, or an application directory containing one.
+/// Its `Icon` key is resolved through the freedesktop icon theme, and the closest match
+/// is scaled to the desired icon size.
+pub fn get_icon(
+    app_path: &Path,
+    save_path: &Path,
+    size: impl Into<IconSize>,
+) -> Result<(), GetAppIconError> {
+    get_icon_with_format(app_path, save_path, size, IconFormat::Png)
+}
+
+/// Like [`get_icon_image`], but draws the variant of the icon for [`IconOptions::appearance`] and
+/// tells which variant was used, [`IconAppearance::Light`] when the app has none for it.
+///
+/// On Linux the variants are the `-dark` and `-high-contrast` versions of the icon theme, and the
+/// `-symbolic` icons for [`IconAppearance::Tinted`]. On macOS the icon is drawn for the matching
+/// `NSAppearance`, which picks the variants of asset catalogs and `.icns` files. `.app` bundles
+/// read on other OSes use the dark and template variants of their `.icns`.
+///
+/// On Linux, [`IconOptions::symbolic_color`] also picks the `-symbolic` icon and paints it in
+/// that color.
+pub fn get_icon_variant(
+    app_path: &Path,
+    size: impl Into<IconSize>,
+    options: IconOptions,
+) -> Result<IconVariant, GetAppIconError> {
     #[cfg(target_os = "macos")]
-    #[from]
-    source: macos::request::GetIconError,
+    let variant = macos::request::get_icon_variant(app_path, size.into(), &options)?;
     #[cfg(target_os = "windows")]
-    #[from]
-    source: windows::GetIconError,
+    let variant = windows::get_icon_variant(app_path, size.into(), &options)?;
+    #[cfg(target_os = "linux")]
+    let variant = linux::get_icon_variant(app_path, size.into(), &options)?;
+
+    Ok(variant)
 }
 
-#[cfg(target_os = "windows")]
-pub fn get_icon(app_path: &Path, save_path: &Path, size: f64) -> Result<(), GetAppIconError> {
-    windows::get_icon(app_path, save_path, size)?;
-    Ok(())
+/// The icon variant for `options` encoded in `format`, which with the default options is
+/// the icon of [`get_icon_bytes_with_format`].
+pub(crate) fn get_icon_variant_bytes(
+    app_path: &Path,
+    size: IconSize,
+    options: IconOptions,
+    format: IconFormat,
+) -> Result<Vec<u8>, GetAppIconError> {
+    let variant = get_icon_variant(app_path, size, options)?;
+
+    encode::encode(&variant.image, format).map_err(GetAppIconError::Encode)
 }
 
+/// Finds the path of an installed application, to pass to [`get_icon`] and friends, from:
+///
+/// - on macOS, its bundle id (`com.apple.Safari`) or name (`Safari`), through Launch Services
+/// - on Linux, its desktop file id (`org.gnome.Nautilus.desktop`), `StartupWMClass` or executable
+///   name (`firefox`), from the desktop entries in `$XDG_DATA_DIRS/applications`
+/// - on Windows, its executable name (`notepad`), from `PATH`
+///
+/// An absolute path that exists is returned as is.
+pub fn find_app_path(id: &str) -> Result<PathBuf, GetAppIconError> {
+    let path = Path::new(id);
+
+    if path.is_absolute() && path.exists() {
+        return Ok(path.to_path_buf());
+    }
+
+    #[cfg(target_os = "macos")]
+    let app_path = macos::request::find_app_path(id)?;
+    #[cfg(target_os = "windows")]
+    let app_path = windows::find_app_path(id)?;
+    #[cfg(target_os = "linux")]
+    let app_path = linux::find_app_path(id)?;
+
+    Ok(app_path)
+}
+
+/// Whether `path` is an application of the platform, as opposed to any other
+/// file: a `.app` bundle on macOS, an executable on Windows, a desktop entry,
+/// AppImage or application directory on Linux.
+pub(crate) fn is_app_path(path: &Path) -> bool {
+    #[cfg(target_os = "macos")]
+    let is_app = macos::request::is_app_path(path);
+    #[cfg(target_os = "windows")]
+    let is_app = windows::is_app_path(path);
+    #[cfg(target_os = "linux")]
+    let is_app = linux::is_app_path(path);
+
+    is_app
+}
+
+/// Get the icon the OS shows for a file, a file name extension or a MIME type, e.g. in a file browser.
+///
+/// On Linux the type is told by shared-mime-info (from the file name, then its content) and its
+/// icon looked up in the freedesktop icon theme. On macOS the icon comes from `NSWorkspace`.
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+pub fn get_file_type_icon(
+    file_type: FileType,
+    size: impl Into<IconSize>,
+) -> Result<RgbaImage, GetAppIconError> {
+    #[cfg(target_os = "linux")]
+    let image = linux::get_file_type_icon(file_type, size.into())?;
+    #[cfg(target_os = "macos")]
+    let image = macos::request::get_file_type_icon(file_type, size.into())?;
+
+    Ok(image)
+}
+
+/// Like [`get_file_type_icon`], but encoded in `format`.
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+pub fn get_file_type_icon_bytes(
+    file_type: FileType,
+    size: impl Into<IconSize>,
+    format: IconFormat,
+) -> Result<Vec<u8>, GetAppIconError> {
+    let image = get_file_type_icon(file_type, size)?;
+
+    encode::encode(&image, format).map_err(GetAppIconError::Encode)
+}
+
+/// Encodes an icon in `format`, e.g. after applying [`effects`] to the image of [`get_icon_image`].
+pub fn encode_icon(image: &RgbaImage, format: IconFormat) -> image::ImageResult<Vec<u8>> {
+    encode::encode(image, format)
+}
+
+/// Lists the installed applications, e.g. to build a launcher. Each [`Application::path`]
+/// can be passed to [`get_icon`].
+///
+/// Only implemented on Linux for now, where the desktop entries in `$XDG_DATA_DIRS/applications`
+/// are read and their names translated for the current locale (`$LC_MESSAGES`).
 #[cfg(target_os = "linux")]
-pub fn get_icon(app_path: &Path, save_path: &Path, size: f64) -> Result<(), GetAppIconError> {
-    unimplemented!();
+pub fn list_applications() -> Vec<Application> {
+    linux::list_applications()
 }
 
-/// Get app icon from app bundle. You specify the path to save the icon, and the desired icon size (like 16, 32, 48, 128, 256, 512)
-/// Saves the icon in PNG format.
-#[cfg(target_os = "macos")]
-pub fn get_icon(app_path: &Path, save_path: &Path, size: f64) -> Result<(), GetAppIconError> {
-    macos::request::get_icon(app_path, save_path, size)?;
+/// Get app icon from app bundle encoded as PNG bytes, e.g. to send it to the webview.
+pub fn get_icon_bytes(
+    app_path: &Path,
+    size: impl Into<IconSize>,
+) -> Result<Vec<u8>, GetAppIconError> {
+    get_icon_bytes_with_format(app_path, size, IconFormat::Png)
+}
+
+/// Like [`get_icon`], but saves the icon in the given format instead of PNG.
+pub fn get_icon_with_format(
+    app_path: &Path,
+    save_path: &Path,
+    size: impl Into<IconSize>,
+    format: IconFormat,
+) -> Result<(), GetAppIconError> {
+    #[cfg(target_os = "macos")]
+    macos::request::get_icon(app_path, save_path, size.into(), format)?;
+    #[cfg(target_os = "windows")]
+    windows::get_icon(app_path, save_path, size.into(), format)?;
+    #[cfg(target_os = "linux")]
+    linux::get_icon(app_path, save_path, size.into(), format)?;
+
     Ok(())
 }
+
+/// Get app icon from app bundle as an RGBA image, without writing anything to disk.
+pub fn get_icon_image(
+    app_path: &Path,
+    size: impl Into<IconSize>,
+) -> Result<RgbaImage, GetAppIconError> {
+    #[cfg(target_os = "macos")]
+    let image = macos::request::get_icon_image(app_path, size.into())?;
+    #[cfg(target_os = "windows")]
+    let image = windows::get_icon_image(app_path, size.into())?;
+    #[cfg(target_os = "linux")]
+    let image = linux::get_icon_image(app_path, size.into())?;
+
+    Ok(image)
+}
+
+/// Like [`get_icon_bytes`], but encodes the icon in the given format instead of PNG.
+pub fn get_icon_bytes_with_format(
+    app_path: &Path,
+    size: impl Into<IconSize>,
+    format: IconFormat,
+) -> Result<Vec<u8>, GetAppIconError> {
+    #[cfg(target_os = "macos")]
+    let bytes = macos::request::get_icon_bytes(app_path, size.into(), format)?;
+    #[cfg(target_os = "windows")]
+    let bytes = windows::get_icon_bytes(app_path, size.into(), format)?;
+    #[cfg(target_os = "linux")]
+    let bytes = linux::get_icon_bytes(app_path, size.into(), format)?;
+
+    Ok(bytes)
+}
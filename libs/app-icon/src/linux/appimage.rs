@@ -0,0 +1,133 @@
+//! Reading the icon of an AppImage straight from the squashfs image appended
+//! to its runtime, without mounting it.
+
+use std::{
+    fs::File,
+    io::{self, Read},
+    path::Path,
+};
+
+use thiserror::Error;
+
+use super::{
+    desktop_entry::DesktopEntry,
+    squashfs::{Squashfs, SquashfsError},
+};
+
+const ELF_MAGIC: &[u8] = b"\x7fELF";
+/// The AppImage type 2 magic, stored in the padding of the ELF identification.
+const APPIMAGE_MAGIC: &[u8] = b"AI\x02";
+
+#[derive(Error, Debug)]
+pub enum AppImageError {
+    #[error("failed to read the AppImage")]
+    ReadError(#[source] io::Error),
+    #[error("file is not a type 2 AppImage")]
+    NotAnAppImage,
+    #[error("failed to read the AppImage's squashfs image: {0}")]
+    SquashfsError(#[from] SquashfsError),
+    #[error("AppImage has no '.DirIcon' or desktop entry icon")]
+    NoIcon,
+}
+
+/// Whether `path` looks like an AppImage: its name ends with `.AppImage`,
+/// or it starts with the AppImage ELF header.
+pub fn is_appimage(path: &Path) -> bool {
+    if !path.is_file() {
+        return false;
+    }
+
+    if path
+        .extension()
+        .map_or(false, |ext| ext.eq_ignore_ascii_case("appimage"))
+    {
+        return true;
+    }
+
+    let mut header = [0; 16];
+    File::open(path)
+        .and_then(|mut file| file.read_exact(&mut header))
+        .map_or(false, |_| {
+            header.starts_with(ELF_MAGIC) && &header[8..11] == APPIMAGE_MAGIC
+        })
+}
+
+/// The offset of the squashfs image, which starts right after the runtime's
+/// ELF section headers.
+fn squashfs_offset(file: &mut File) -> Result<u64, AppImageError> {
+    let mut header = Vec::with_capacity(64);
+    file.take(64)
+        .read_to_end(&mut header)
+        .map_err(AppImageError::ReadError)?;
+
+    if header.len() < 64 || !header.starts_with(ELF_MAGIC) || &header[8..11] != APPIMAGE_MAGIC {
+        return Err(AppImageError::NotAnAppImage);
+    }
+
+    let little_endian = header[5] == 1;
+    let u16_at = |offset: usize| {
+        let bytes = [header[offset], header[offset + 1]];
+        u64::from(if little_endian {
+            u16::from_le_bytes(bytes)
+        } else {
+            u16::from_be_bytes(bytes)
+        })
+    };
+    let u32_at = |offset: usize| {
+        let bytes = header[offset..offset + 4].try_into().unwrap();
+        u64::from(if little_endian {
+            u32::from_le_bytes(bytes)
+        } else {
+            u32::from_be_bytes(bytes)
+        })
+    };
+    let u64_at = |offset: usize| {
+        let bytes = header[offset..offset + 8].try_into().unwrap();
+        if little_endian {
+            u64::from_le_bytes(bytes)
+        } else {
+            u64::from_be_bytes(bytes)
+        }
+    };
+
+    // e_shoff + e_shentsize * e_shnum, for 32 and 64-bit ELF files
+    match header[4] {
+        1 => Ok(u32_at(0x20) + u16_at(0x2e) * u16_at(0x30)),
+        2 => u64_at(0x28)
+            .checked_add(u16_at(0x3a) * u16_at(0x3c))
+            .ok_or(AppImageError::NotAnAppImage),
+        _ => Err(AppImageError::NotAnAppImage),
+    }
+}
+
+/// The icon file of the AppImage at `path`: the `.DirIcon` at the root of
+/// the image, or else the icon its root desktop entry names. It is a PNG or
+/// an SVG file.
+pub fn read_icon(path: &Path) -> Result<Vec<u8>, AppImageError> {
+    let mut file = File::open(path).map_err(AppImageError::ReadError)?;
+    let offset = squashfs_offset(&mut file)?;
+    let squashfs = Squashfs::open(file, offset)?;
+
+    match squashfs.read_file(".DirIcon") {
+        Ok(data) => return Ok(data),
+        Err(SquashfsError::NotFound) => {}
+        Err(error) => return Err(error.into()),
+    }
+
+    let mut entries = squashfs.root_entries()?;
+    entries.sort();
+
+    let desktop_entry = entries
+        .iter()
+        .find(|name| name.ends_with(".desktop"))
+        .ok_or(AppImageError::NoIcon)?;
+    let desktop_entry = DesktopEntry::parse(&String::from_utf8_lossy(
+        &squashfs.read_file(desktop_entry)?,
+    ));
+    let icon = desktop_entry.icon().ok_or(AppImageError::NoIcon)?;
+
+    ["png", "svg"]
+        .iter()
+        .find_map(|ext| squashfs.read_file(&format!("{icon}.{ext}")).ok())
+        .ok_or(AppImageError::NoIcon)
+}
@@ -0,0 +1,204 @@
+use std::{
+    collections::HashSet,
+    env, fs,
+    os::unix::fs::PermissionsExt,
+    path::{Path, PathBuf},
+};
+
+use super::{desktop_entry::DesktopEntry, xdg::all_data_dirs};
+use crate::Application;
+
+/// The `applications` directories desktop entries are installed in, the most
+/// important first, including the ones of Flatpak and Snap.
+pub fn application_dirs() -> Vec<PathBuf> {
+    all_data_dirs()
+        .into_iter()
+        .map(|dir| dir.join("applications"))
+        .collect()
+}
+
+fn collect_desktop_entries(root: &Path, dir: &Path, entries: &mut Vec<(String, PathBuf)>) {
+    let Ok(read_dir) = fs::read_dir(dir) else {
+        return;
+    };
+
+    // symlinked directories aren't followed, as they may loop
+    let mut paths: Vec<(PathBuf, bool)> = read_dir
+        .flatten()
+        .map(|entry| {
+            let is_dir = entry.file_type().map_or(false, |kind| kind.is_dir());
+            (entry.path(), is_dir)
+        })
+        .collect();
+    paths.sort();
+
+    for (path, is_dir) in paths {
+        if is_dir {
+            collect_desktop_entries(root, &path, entries);
+        } else if path.extension().map_or(false, |ext| ext == "desktop") {
+            // the desktop file id is the path below the applications directory, with '/' as '-'
+            let Ok(relative) = path.strip_prefix(root) else {
+                continue;
+            };
+            let id = relative
+                .components()
+                .map(|component| component.as_os_str().to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("-");
+
+            entries.push((id, path));
+        }
+    }
+}
+
+/// Every desktop entry in `dirs` with its desktop file id. An id found in an
+/// earlier directory hides the entries with the same id in the later ones.
+pub fn desktop_entries(dirs: &[PathBuf]) -> Vec<(String, PathBuf)> {
+    let mut seen = HashSet::new();
+    let mut entries = vec![];
+
+    for dir in dirs {
+        let mut dir_entries = vec![];
+        collect_desktop_entries(dir, dir, &mut dir_entries);
+
+        entries.extend(
+            dir_entries
+                .into_iter()
+                .filter(|(id, _)| seen.insert(id.clone())),
+        );
+    }
+
+    entries
+}
+
+/// Finds the desktop entry of the application identified by `query`, which is
+/// matched in turn against:
+///
+/// 1. the desktop file id, with or without `.desktop` (`org.gnome.Nautilus.desktop`)
+/// 2. `StartupWMClass`, ignoring case (`Navigator`)
+/// 3. the file name of the program in `Exec` (`firefox`)
+/// 4. the last part of a reverse DNS desktop file id, ignoring case (`nautilus`)
+pub fn find_application(dirs: &[PathBuf], query: &str) -> Option<PathBuf> {
+    if query.is_empty() {
+        return None;
+    }
+
+    let entries = desktop_entries(dirs);
+
+    let desktop_id = if query.ends_with(".desktop") {
+        query.to_string()
+    } else {
+        format!("{query}.desktop")
+    };
+
+    if let Some((_, path)) = entries.iter().find(|(id, _)| *id == desktop_id) {
+        return Some(path.clone());
+    }
+
+    let parsed: Vec<(&String, &PathBuf, DesktopEntry)> = entries
+        .iter()
+        .filter_map(|(id, path)| Some((id, path, DesktopEntry::from_path(path).ok()?)))
+        .collect();
+
+    let find = |matches: &dyn Fn(&String, &DesktopEntry) -> bool| {
+        parsed
+            .iter()
+            .find(|(id, _, entry)| matches(id, entry))
+            .map(|(_, path, _)| (*path).clone())
+    };
+
+    find(&|_, entry| {
+        entry
+            .startup_wm_class()
+            .map_or(false, |class| class.eq_ignore_ascii_case(query))
+    })
+    .or_else(|| {
+        find(&|_, entry| {
+            entry.exec_program().map_or(false, |program| {
+                Path::new(program)
+                    .file_name()
+                    .map_or(false, |name| name == query)
+            })
+        })
+    })
+    .or_else(|| {
+        find(&|id, _| {
+            id.trim_end_matches(".desktop")
+                .rsplit('.')
+                .next()
+                .map_or(false, |name| name.eq_ignore_ascii_case(query))
+        })
+    })
+}
+
+/// Finds the desktop entry whose `Exec` program is `program`, e.g. the one an
+/// AppImage integration tool installed for an AppImage.
+pub fn find_application_by_program(dirs: &[PathBuf], program: &Path) -> Option<PathBuf> {
+    desktop_entries(dirs).into_iter().find_map(|(_, path)| {
+        let entry = DesktopEntry::from_path(&path).ok()?;
+        (Path::new(entry.exec_program()?) == program).then_some(path)
+    })
+}
+
+fn is_executable(path: &Path) -> bool {
+    fs::metadata(path).map_or(false, |metadata| {
+        metadata.is_file() && metadata.permissions().mode() & 0o111 != 0
+    })
+}
+
+/// Whether the `TryExec` program is installed, either as a path or in `PATH`.
+fn is_program_installed(program: &str) -> bool {
+    if program.contains('/') {
+        return is_executable(Path::new(program));
+    }
+
+    env::var_os("PATH")
+        .iter()
+        .flat_map(env::split_paths)
+        .any(|dir| is_executable(&dir.join(program)))
+}
+
+/// The applications described by the desktop entries in `dirs`, with their
+/// names translated for the first of `locales` that has a translation.
+///
+/// Entries that aren't of the `Application` type, have no name, or whose
+/// `TryExec` program isn't installed are left out. Entries in earlier
+/// directories override the ones with the same desktop file id in later ones.
+pub fn list_applications(dirs: &[PathBuf], locales: &[String]) -> Vec<Application> {
+    desktop_entries(dirs)
+        .into_iter()
+        .filter_map(|(id, path)| {
+            let entry = DesktopEntry::from_path(&path).ok()?;
+
+            if entry
+                .get("Type")
+                .map_or(false, |kind| kind != "Application")
+            {
+                return None;
+            }
+
+            if let Some(try_exec) = entry.get("TryExec").filter(|program| !program.is_empty()) {
+                if !is_program_installed(try_exec) {
+                    return None;
+                }
+            }
+
+            let name = entry.get("Name").filter(|name| !name.is_empty())?;
+
+            Some(Application {
+                localized_name: entry
+                    .get_localized("Name", locales)
+                    .unwrap_or(name)
+                    .to_string(),
+                name: name.to_string(),
+                executable: entry.exec_program().map(String::from),
+                categories: entry.get_list("Categories"),
+                no_display: entry.get_bool("NoDisplay"),
+                hidden: entry.get_bool("Hidden"),
+                icon: entry.icon().map(String::from),
+                id,
+                path,
+            })
+        })
+        .collect()
+}
@@ -0,0 +1,124 @@
+use std::{env, fs, io, path::Path};
+
+use super::ini::{self, Group};
+
+const DESKTOP_ENTRY_GROUP: &str = "Desktop Entry";
+
+/// The `[Desktop Entry]` group of a `.desktop` file.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct DesktopEntry {
+    group: Group,
+}
+
+impl DesktopEntry {
+    pub fn from_path(path: &Path) -> io::Result<Self> {
+        Ok(Self::parse(&fs::read_to_string(path)?))
+    }
+
+    pub fn parse(contents: &str) -> Self {
+        let group = ini::parse(contents)
+            .into_iter()
+            .find(|group| group.name() == DESKTOP_ENTRY_GROUP)
+            .unwrap_or_default();
+
+        Self { group }
+    }
+
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.group.get(key)
+    }
+
+    /// The value of the translated `key[locale]` for the first of `locales`
+    /// that has one, or else the untranslated value.
+    pub fn get_localized(&self, key: &str, locales: &[String]) -> Option<&str> {
+        locales
+            .iter()
+            .find_map(|locale| self.get(&format!("{key}[{locale}]")))
+            .or_else(|| self.get(key))
+    }
+
+    pub fn get_bool(&self, key: &str) -> bool {
+        self.get(key) == Some("true")
+    }
+
+    /// The values of a `;` separated list key.
+    pub fn get_list(&self, key: &str) -> Vec<String> {
+        self.get(key)
+            .map(|value| {
+                value
+                    .split(';')
+                    .filter(|item| !item.is_empty())
+                    .map(String::from)
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// The value of the `Icon` key, either an icon name or an absolute path.
+    pub fn icon(&self) -> Option<&str> {
+        self.get("Icon").filter(|icon| !icon.is_empty())
+    }
+
+    /// The `StartupWMClass` key, the window class the app's windows are created with.
+    pub fn startup_wm_class(&self) -> Option<&str> {
+        self.get("StartupWMClass").filter(|class| !class.is_empty())
+    }
+
+    /// The program run by the `Exec` key, skipping an `env VAR=value` prefix.
+    pub fn exec_program(&self) -> Option<&str> {
+        let mut args = self.get("Exec")?.split_whitespace();
+        let mut program = args.next()?;
+
+        if program == "env" {
+            program = args.find(|arg| !arg.contains('='))?;
+        }
+
+        Some(program.trim_matches('"'))
+    }
+}
+
+/// The locales translated keys are looked up for, most specific first, as the
+/// spec orders them: `lang_COUNTRY@MODIFIER`, `lang_COUNTRY`, `lang@MODIFIER`
+/// then `lang`. The encoding of `locale` is ignored.
+pub fn locale_variants(locale: &str) -> Vec<String> {
+    let (locale, modifier) = match locale.split_once('@') {
+        Some((locale, modifier)) => (locale, Some(modifier)),
+        None => (locale, None),
+    };
+    let locale = locale.split('.').next().unwrap_or_default();
+
+    if locale.is_empty() || locale == "C" || locale == "POSIX" {
+        return vec![];
+    }
+
+    let (lang, country) = match locale.split_once('_') {
+        Some((lang, country)) => (lang, Some(country)),
+        None => (locale, None),
+    };
+
+    let mut variants = vec![];
+
+    if let (Some(country), Some(modifier)) = (country, modifier) {
+        variants.push(format!("{lang}_{country}@{modifier}"));
+    }
+    if let Some(country) = country {
+        variants.push(format!("{lang}_{country}"));
+    }
+    if let Some(modifier) = modifier {
+        variants.push(format!("{lang}@{modifier}"));
+    }
+    variants.push(lang.to_string());
+
+    variants
+}
+
+/// The locale variants of the user's messages locale, from `$LC_ALL`,
+/// `$LC_MESSAGES` then `$LANG`.
+pub fn current_locales() -> Vec<String> {
+    ["LC_ALL", "LC_MESSAGES", "LANG"]
+        .iter()
+        .filter_map(|var| env::var(var).ok())
+        .find(|locale| !locale.is_empty())
+        .map(|locale| locale_variants(&locale))
+        .unwrap_or_default()
+}
@@ -0,0 +1,396 @@
+use std::{
+    collections::HashSet,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::IconAppearance;
+
+use super::{
+    ini::{self, Group},
+    xdg::{all_data_dirs, home_dir, xdg_dir},
+};
+
+const FALLBACK_THEME: &str = "hicolor";
+
+/// The suffixes of the variants of a theme for other appearances, e.g.
+/// `Adwaita-dark`, `Papirus-Dark` or `breeze-high-contrast`.
+const VARIANT_SUFFIXES: &[(&str, IconAppearance)] = &[
+    ("-dark", IconAppearance::Dark),
+    ("-Dark", IconAppearance::Dark),
+    ("-high-contrast", IconAppearance::HighContrast),
+    ("-HighContrast", IconAppearance::HighContrast),
+];
+
+/// The theme most high contrast setups use, whatever the regular theme.
+const HIGH_CONTRAST_THEME: &str = "HighContrast";
+
+const ICON_EXTENSIONS: &[&str] = &["png", "svg"];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum DirectoryType {
+    Fixed,
+    Scalable,
+    Threshold,
+}
+
+/// The file names `icon_name` may have. Symbolic icons are also rendered
+/// to PNG by GTK as `<name>.symbolic.png`.
+fn file_names(icon_name: &str) -> Vec<String> {
+    let mut file_names: Vec<String> = ICON_EXTENSIONS
+        .iter()
+        .map(|ext| format!("{icon_name}.{ext}"))
+        .collect();
+
+    if let Some(name) = icon_name.strip_suffix("-symbolic") {
+        file_names.push(format!("{name}.symbolic.png"));
+    }
+
+    file_names
+}
+
+/// A sub directory of an icon theme, as described by its `index.theme` group.
+#[derive(Debug, Clone)]
+struct ThemeDirectory {
+    path: String,
+    size: u32,
+    scale: u32,
+    min_size: u32,
+    max_size: u32,
+    threshold: u32,
+    kind: DirectoryType,
+}
+
+impl ThemeDirectory {
+    fn from_group(group: &Group) -> Option<Self> {
+        let number = |key: &str| group.get(key).and_then(|value| value.parse::<u32>().ok());
+
+        let size = number("Size")?;
+
+        let kind = match group.get("Type") {
+            Some("Fixed") => DirectoryType::Fixed,
+            Some("Scalable") => DirectoryType::Scalable,
+            _ => DirectoryType::Threshold,
+        };
+
+        Some(Self {
+            path: group.name().to_string(),
+            size,
+            scale: number("Scale").unwrap_or(1),
+            min_size: number("MinSize").unwrap_or(size),
+            max_size: number("MaxSize").unwrap_or(size),
+            threshold: number("Threshold").unwrap_or(2),
+            kind,
+        })
+    }
+
+    fn matches_size(&self, size: u32, scale: u32) -> bool {
+        if self.scale != scale {
+            return false;
+        }
+
+        match self.kind {
+            DirectoryType::Fixed => self.size == size,
+            DirectoryType::Scalable => (self.min_size..=self.max_size).contains(&size),
+            DirectoryType::Threshold => (self.size.saturating_sub(self.threshold)
+                ..=self.size + self.threshold)
+                .contains(&size),
+        }
+    }
+
+    fn size_distance(&self, size: u32, scale: u32) -> u32 {
+        let scaled = size * scale;
+
+        let (min, max) = match self.kind {
+            DirectoryType::Fixed => (self.size, self.size),
+            DirectoryType::Scalable => (self.min_size, self.max_size),
+            DirectoryType::Threshold => (
+                self.size.saturating_sub(self.threshold),
+                self.size + self.threshold,
+            ),
+        };
+
+        if scaled < min * self.scale {
+            min * self.scale - scaled
+        } else {
+            scaled.saturating_sub(max * self.scale)
+        }
+    }
+}
+
+/// An icon theme, merged from every base directory that contains it.
+#[derive(Debug)]
+struct Theme {
+    roots: Vec<PathBuf>,
+    directories: Vec<ThemeDirectory>,
+    inherits: Vec<String>,
+}
+
+impl Theme {
+    fn load(base_dirs: &[PathBuf], name: &str) -> Option<Self> {
+        let roots: Vec<PathBuf> = base_dirs
+            .iter()
+            .map(|base_dir| base_dir.join(name))
+            .filter(|root| root.is_dir())
+            .collect();
+
+        // the first index.theme found in the base directories wins
+        let groups = roots
+            .iter()
+            .find_map(|root| fs::read_to_string(root.join("index.theme")).ok())
+            .map(|contents| ini::parse(&contents))?;
+
+        let main = groups.iter().find(|group| group.name() == "Icon Theme")?;
+
+        let list = |key: &str| -> Vec<String> {
+            main.get(key)
+                .map(|value| {
+                    value
+                        .split(',')
+                        .map(str::trim)
+                        .filter(|item| !item.is_empty())
+                        .map(String::from)
+                        .collect()
+                })
+                .unwrap_or_default()
+        };
+
+        let mut directory_names = list("Directories");
+        directory_names.extend(list("ScaledDirectories"));
+
+        let directories = directory_names
+            .iter()
+            .filter_map(|name| groups.iter().find(|group| group.name() == name))
+            .filter_map(ThemeDirectory::from_group)
+            .collect();
+
+        Some(Self {
+            roots,
+            directories,
+            inherits: list("Inherits"),
+        })
+    }
+
+    fn candidates<'a>(
+        &'a self,
+        directory: &'a ThemeDirectory,
+        icon_name: &'a str,
+    ) -> impl Iterator<Item = PathBuf> + 'a {
+        self.roots.iter().flat_map(move |root| {
+            file_names(icon_name)
+                .into_iter()
+                .map(move |file_name| root.join(&directory.path).join(file_name))
+        })
+    }
+
+    fn lookup(&self, icon_name: &str, size: u32, scale: u32) -> Option<PathBuf> {
+        let exact = self
+            .directories
+            .iter()
+            .filter(|directory| directory.matches_size(size, scale))
+            .find_map(|directory| {
+                self.candidates(directory, icon_name)
+                    .find(|candidate| candidate.is_file())
+            });
+
+        if exact.is_some() {
+            return exact;
+        }
+
+        let mut closest: Option<(u32, PathBuf)> = None;
+
+        for directory in &self.directories {
+            let distance = directory.size_distance(size, scale);
+
+            if closest
+                .as_ref()
+                .map_or(false, |(minimal, _)| distance >= *minimal)
+            {
+                continue;
+            }
+
+            if let Some(candidate) = self
+                .candidates(directory, icon_name)
+                .find(|candidate| candidate.is_file())
+            {
+                closest = Some((distance, candidate));
+            }
+        }
+
+        closest.map(|(_, path)| path)
+    }
+}
+
+/// Resolves icon names to files following the freedesktop icon theme specification.
+#[derive(Debug, Clone)]
+pub struct IconLookup {
+    base_dirs: Vec<PathBuf>,
+    theme: String,
+}
+
+impl IconLookup {
+    pub fn new(base_dirs: Vec<PathBuf>, theme: impl Into<String>) -> Self {
+        Self {
+            base_dirs,
+            theme: theme.into(),
+        }
+    }
+
+    /// The name of the theme the icons are looked up in first.
+    pub fn theme(&self) -> &str {
+        &self.theme
+    }
+
+    /// Also looks in `base_dir`, before the other base directories.
+    pub fn with_base_dir(mut self, base_dir: PathBuf) -> Self {
+        self.base_dirs.insert(0, base_dir);
+        self
+    }
+
+    /// Uses the XDG base directories and the icon theme configured for the
+    /// current user, falling back to `hicolor`.
+    pub fn from_env() -> Self {
+        Self::new(
+            default_base_dirs(),
+            current_theme_name().unwrap_or_else(|| FALLBACK_THEME.to_string()),
+        )
+    }
+
+    /// Finds the file for `icon_name` closest to `size` at `scale`, looking in
+    /// the current theme and its parents, then `hicolor`, then the unthemed
+    /// base directories (e.g. `/usr/share/pixmaps`).
+    pub fn find_icon(&self, icon_name: &str, size: u32, scale: u32) -> Option<PathBuf> {
+        let mut visited = HashSet::new();
+
+        self.find_in_theme(&self.theme, icon_name, size, scale, &mut visited)
+            .or_else(|| self.find_in_theme(FALLBACK_THEME, icon_name, size, scale, &mut visited))
+            .or_else(|| self.find_fallback(icon_name))
+    }
+
+    /// The themes holding the variant of the current theme for `appearance`,
+    /// e.g. `Papirus-Dark` for `Papirus`, or `Papirus` for `Papirus-Dark` and
+    /// a light appearance.
+    fn variant_themes(&self, appearance: IconAppearance) -> Vec<String> {
+        let base = VARIANT_SUFFIXES
+            .iter()
+            .find_map(|(suffix, _)| self.theme.strip_suffix(suffix))
+            .unwrap_or(&self.theme);
+
+        let mut themes: Vec<String> = VARIANT_SUFFIXES
+            .iter()
+            .filter(|(_, variant)| *variant == appearance)
+            .map(|(suffix, _)| format!("{base}{suffix}"))
+            .collect();
+
+        match appearance {
+            IconAppearance::Light => themes.push(base.to_string()),
+            IconAppearance::HighContrast => themes.push(HIGH_CONTRAST_THEME.to_string()),
+            IconAppearance::Dark | IconAppearance::Tinted => {}
+        }
+
+        themes
+    }
+
+    /// Like [`IconLookup::find_icon`], but prefers the icon of the variant of
+    /// the theme for `appearance`, and tells which appearance the file is for.
+    /// Icons found outside of a variant theme are reported as light ones.
+    pub fn find_icon_variant(
+        &self,
+        icon_name: &str,
+        size: u32,
+        scale: u32,
+        appearance: IconAppearance,
+    ) -> Option<(PathBuf, IconAppearance)> {
+        // only the variant theme's own icons are its variants, not the ones it inherits
+        let variant = self
+            .variant_themes(appearance)
+            .iter()
+            .find_map(|theme_name| {
+                Theme::load(&self.base_dirs, theme_name)?.lookup(icon_name, size, scale)
+            });
+
+        match variant {
+            Some(path) => Some((path, appearance)),
+            None => self
+                .find_icon(icon_name, size, scale)
+                .map(|path| (path, IconAppearance::Light)),
+        }
+    }
+
+    fn find_in_theme(
+        &self,
+        theme_name: &str,
+        icon_name: &str,
+        size: u32,
+        scale: u32,
+        visited: &mut HashSet<String>,
+    ) -> Option<PathBuf> {
+        if !visited.insert(theme_name.to_string()) {
+            return None;
+        }
+
+        let theme = Theme::load(&self.base_dirs, theme_name)?;
+
+        if let Some(path) = theme.lookup(icon_name, size, scale) {
+            return Some(path);
+        }
+
+        theme
+            .inherits
+            .iter()
+            .find_map(|parent| self.find_in_theme(parent, icon_name, size, scale, visited))
+    }
+
+    fn find_fallback(&self, icon_name: &str) -> Option<PathBuf> {
+        self.base_dirs.iter().find_map(|base_dir| {
+            file_names(icon_name)
+                .into_iter()
+                .map(|file_name| base_dir.join(file_name))
+                .find(|candidate| candidate.is_file())
+        })
+    }
+}
+
+fn default_base_dirs() -> Vec<PathBuf> {
+    let mut base_dirs = vec![];
+
+    if let Some(home) = home_dir() {
+        base_dirs.push(home.join(".icons"));
+    }
+
+    base_dirs.extend(all_data_dirs().into_iter().map(|dir| dir.join("icons")));
+    base_dirs.push(PathBuf::from("/usr/share/pixmaps"));
+
+    base_dirs
+}
+
+fn read_setting(path: &Path, group_name: &str, key: &str) -> Option<String> {
+    let contents = fs::read_to_string(path).ok()?;
+
+    ini::parse(&contents)
+        .iter()
+        .find(|group| group.name() == group_name)
+        .and_then(|group| group.get(key))
+        .filter(|value| !value.is_empty())
+        .map(String::from)
+}
+
+/// The icon theme chosen in the GTK or KDE settings of the current user.
+fn current_theme_name() -> Option<String> {
+    let config_home =
+        xdg_dir("XDG_CONFIG_HOME").or_else(|| home_dir().map(|home| home.join(".config")))?;
+
+    read_setting(
+        &config_home.join("gtk-4.0/settings.ini"),
+        "Settings",
+        "gtk-icon-theme-name",
+    )
+    .or_else(|| {
+        read_setting(
+            &config_home.join("gtk-3.0/settings.ini"),
+            "Settings",
+            "gtk-icon-theme-name",
+        )
+    })
+    .or_else(|| read_setting(&config_home.join("kdeglobals"), "Icons", "Theme"))
+}
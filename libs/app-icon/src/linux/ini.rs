@@ -0,0 +1,53 @@
+use std::collections::HashMap;
+
+/// A `[Group]` of `key=value` entries from a freedesktop ini-style file,
+/// such as a `.desktop` file or an icon theme's `index.theme`.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Group {
+    name: String,
+    entries: HashMap<String, String>,
+}
+
+impl Group {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.entries.get(key).map(String::as_str)
+    }
+}
+
+pub fn parse(contents: &str) -> Vec<Group> {
+    let mut groups: Vec<Group> = vec![];
+
+    for line in contents.lines() {
+        let line = line.trim();
+
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            groups.push(Group {
+                name: name.to_string(),
+                entries: HashMap::new(),
+            });
+            continue;
+        }
+
+        // keys before the first group header are not allowed by the spec
+        let Some(group) = groups.last_mut() else {
+            continue;
+        };
+
+        if let Some((key, value)) = line.split_once('=') {
+            group
+                .entries
+                .entry(key.trim().to_string())
+                .or_insert_with(|| value.trim().to_string());
+        }
+    }
+
+    groups
+}
@@ -0,0 +1,458 @@
+use std::{
+    cmp::Reverse,
+    collections::HashMap,
+    fs::{self, File},
+    io::Read,
+    path::{Path, PathBuf},
+};
+
+use super::xdg::{data_dirs, data_home};
+
+/// The most bytes of a file read to match the magic rules against.
+const MAX_SNIFF_LEN: usize = 64 * 1024;
+
+/// The bytes checked to tell text from binary files without a magic match.
+const TEXT_SNIFF_LEN: usize = 128;
+
+const DIRECTORY: &str = "inode/directory";
+const ZERO_SIZE: &str = "application/x-zerosize";
+const TEXT: &str = "text/plain";
+pub const BINARY: &str = "application/octet-stream";
+
+/// The `mime` directories shared-mime-info installs its database in, the most
+/// important first.
+pub fn mime_dirs() -> Vec<PathBuf> {
+    data_home()
+        .into_iter()
+        .chain(data_dirs())
+        .map(|dir| dir.join("mime"))
+        .collect()
+}
+
+#[derive(Debug, Clone)]
+struct Glob {
+    weight: u32,
+    mime_type: String,
+    pattern: String,
+    case_sensitive: bool,
+}
+
+/// A line of a magic section, which only counts when one of its children (the
+/// following lines indented one level deeper) matches too, if it has any.
+#[derive(Debug, Clone, Default)]
+struct MagicRule {
+    offset: usize,
+    range: usize,
+    value: Vec<u8>,
+    mask: Option<Vec<u8>>,
+    children: Vec<MagicRule>,
+}
+
+impl MagicRule {
+    fn extent(&self) -> usize {
+        let own = self.offset + self.range + self.value.len();
+
+        self.children
+            .iter()
+            .map(MagicRule::extent)
+            .fold(own, usize::max)
+    }
+
+    fn matches_at(&self, data: &[u8], start: usize) -> bool {
+        let Some(bytes) = data.get(start..start + self.value.len()) else {
+            return false;
+        };
+
+        match &self.mask {
+            Some(mask) => bytes
+                .iter()
+                .zip(&self.value)
+                .zip(mask)
+                .all(|((byte, value), mask)| byte & mask == value & mask),
+            None => bytes == self.value.as_slice(),
+        }
+    }
+
+    fn matches(&self, data: &[u8]) -> bool {
+        let matched = (self.offset..self.offset + self.range.max(1))
+            .any(|start| self.matches_at(data, start));
+
+        matched
+            && (self.children.is_empty() || self.children.iter().any(|child| child.matches(data)))
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Magic {
+    priority: u32,
+    mime_type: String,
+    rules: Vec<MagicRule>,
+}
+
+/// Parses a decimal number at the start of `data`, returning it and the rest.
+fn parse_number(data: &[u8]) -> Option<(usize, &[u8])> {
+    let len = data.iter().take_while(|byte| byte.is_ascii_digit()).count();
+
+    if len == 0 {
+        return None;
+    }
+
+    let number = std::str::from_utf8(&data[..len]).ok()?.parse().ok()?;
+    Some((number, &data[len..]))
+}
+
+/// Swaps the bytes of each `word_size` word, as multi-byte values of the
+/// magic file are stored big endian.
+fn to_host_order(bytes: &mut [u8], word_size: usize) {
+    if cfg!(target_endian = "little") && word_size > 1 {
+        for word in bytes.chunks_exact_mut(word_size) {
+            word.reverse();
+        }
+    }
+}
+
+/// Parses a `[indent]>offset=<len><value>[&mask][~word_size][+range]\n` line.
+fn parse_magic_rule(data: &[u8]) -> Option<(usize, MagicRule, &[u8])> {
+    let (indent, data) = match parse_number(data) {
+        Some((indent, rest)) => (indent, rest),
+        None => (0, data),
+    };
+
+    let data = data.strip_prefix(b">")?;
+    let (offset, data) = parse_number(data)?;
+    let data = data.strip_prefix(b"=")?;
+
+    let len = usize::from(u16::from_be_bytes([*data.first()?, *data.get(1)?]));
+    let mut value = data.get(2..2 + len)?.to_vec();
+    let mut data = &data[2 + len..];
+
+    let mut mask = None;
+    if let Some(rest) = data.strip_prefix(b"&") {
+        mask = Some(rest.get(..len)?.to_vec());
+        data = &rest[len..];
+    }
+
+    if let Some(rest) = data.strip_prefix(b"~") {
+        let (word_size, rest) = parse_number(rest)?;
+        to_host_order(&mut value, word_size);
+        if let Some(mask) = &mut mask {
+            to_host_order(mask, word_size);
+        }
+        data = rest;
+    }
+
+    let mut range = 1;
+    if let Some(rest) = data.strip_prefix(b"+") {
+        let (range_len, rest) = parse_number(rest)?;
+        range = range_len;
+        data = rest;
+    }
+
+    let data = data.strip_prefix(b"\n")?;
+
+    Some((
+        indent,
+        MagicRule {
+            offset,
+            range,
+            value,
+            mask,
+            children: vec![],
+        },
+        data,
+    ))
+}
+
+/// Adds `rule` at `indent` under the last rule of each shallower level.
+fn insert_rule(rules: &mut Vec<MagicRule>, indent: usize, rule: MagicRule) {
+    match (indent, rules.last_mut()) {
+        (0, _) | (_, None) => rules.push(rule),
+        (_, Some(parent)) => insert_rule(&mut parent.children, indent - 1, rule),
+    }
+}
+
+/// Parses the binary `magic` file of shared-mime-info.
+fn parse_magic(data: &[u8]) -> Vec<Magic> {
+    let Some(mut data) = data.strip_prefix(b"MIME-Magic\0\n") else {
+        return vec![];
+    };
+
+    let mut sections = vec![];
+
+    while let Some(rest) = data.strip_prefix(b"[") {
+        let Some(header_end) = rest.iter().position(|byte| *byte == b'\n') else {
+            break;
+        };
+        let header = String::from_utf8_lossy(&rest[..header_end]);
+        data = &rest[header_end + 1..];
+
+        let Some((priority, mime_type)) = header
+            .strip_suffix(']')
+            .and_then(|header| header.split_once(':'))
+        else {
+            break;
+        };
+
+        let mut magic = Magic {
+            priority: priority.parse().unwrap_or(50),
+            mime_type: mime_type.to_string(),
+            rules: vec![],
+        };
+
+        while !data.is_empty() && !data.starts_with(b"[") {
+            let Some((indent, rule, rest)) = parse_magic_rule(data) else {
+                // an unknown extension to the format, skip to the next line
+                match data.iter().position(|byte| *byte == b'\n') {
+                    Some(end) => data = &data[end + 1..],
+                    None => data = &[],
+                }
+                continue;
+            };
+
+            insert_rule(&mut magic.rules, indent, rule);
+            data = rest;
+        }
+
+        sections.push(magic);
+    }
+
+    sections
+}
+
+/// Matches `name` against a glob pattern with `*`, `?` and `[...]` wildcards.
+fn glob_matches(pattern: &[char], name: &[char]) -> bool {
+    match pattern.split_first() {
+        None => name.is_empty(),
+        Some(('*', rest)) => (0..=name.len()).any(|skip| glob_matches(rest, &name[skip..])),
+        Some(('?', rest)) => !name.is_empty() && glob_matches(rest, &name[1..]),
+        Some(('[', rest)) => {
+            let Some(end) = rest.iter().position(|c| *c == ']') else {
+                return name.first() == Some(&'[') && glob_matches(rest, &name[1..]);
+            };
+            let Some(c) = name.first() else {
+                return false;
+            };
+
+            let (negated, class) = match rest[..end].split_first() {
+                Some(('!', class)) => (true, class),
+                _ => (false, &rest[..end]),
+            };
+
+            let mut in_class = false;
+            let mut i = 0;
+            while i < class.len() {
+                if i + 2 < class.len() && class[i + 1] == '-' {
+                    in_class |= (class[i]..=class[i + 2]).contains(c);
+                    i += 3;
+                } else {
+                    in_class |= class[i] == *c;
+                    i += 1;
+                }
+            }
+
+            in_class != negated && glob_matches(&rest[end + 1..], &name[1..])
+        }
+        Some((c, rest)) => name.first() == Some(c) && glob_matches(rest, &name[1..]),
+    }
+}
+
+/// Reads `type<separator>value` lines, the first occurrence of a type winning.
+fn read_pairs(path: &Path, separator: char, pairs: &mut HashMap<String, String>) {
+    let Ok(contents) = fs::read_to_string(path) else {
+        return;
+    };
+
+    for line in contents.lines() {
+        if line.starts_with('#') {
+            continue;
+        }
+
+        if let Some((key, value)) = line.split_once(separator) {
+            pairs
+                .entry(key.to_string())
+                .or_insert_with(|| value.to_string());
+        }
+    }
+}
+
+/// The parts of the shared-mime-info database needed to tell the type of a file
+/// and find its icon.
+#[derive(Debug, Clone, Default)]
+pub struct MimeDatabase {
+    globs: Vec<Glob>,
+    magic: Vec<Magic>,
+    aliases: HashMap<String, String>,
+    icons: HashMap<String, String>,
+    generic_icons: HashMap<String, String>,
+}
+
+impl MimeDatabase {
+    /// Loads the database from `dirs`, the most important first.
+    pub fn load(dirs: &[PathBuf]) -> Self {
+        let mut database = Self::default();
+
+        for dir in dirs {
+            if let Ok(globs) = fs::read_to_string(dir.join("globs2")) {
+                database.globs.extend(globs.lines().filter_map(|line| {
+                    if line.starts_with('#') {
+                        return None;
+                    }
+
+                    let mut fields = line.split(':');
+                    let weight = fields.next()?.parse().ok()?;
+                    let mime_type = fields.next()?.to_string();
+                    let pattern = fields.next()?.to_string();
+                    let case_sensitive = fields
+                        .next()
+                        .map_or(false, |flags| flags.split(',').any(|flag| flag == "cs"));
+
+                    Some(Glob {
+                        weight,
+                        mime_type,
+                        pattern,
+                        case_sensitive,
+                    })
+                }));
+            }
+
+            if let Ok(magic) = fs::read(dir.join("magic")) {
+                database.magic.extend(parse_magic(&magic));
+            }
+
+            read_pairs(&dir.join("aliases"), ' ', &mut database.aliases);
+            read_pairs(&dir.join("icons"), ':', &mut database.icons);
+            read_pairs(&dir.join("generic-icons"), ':', &mut database.generic_icons);
+        }
+
+        database.magic.sort_by_key(|magic| Reverse(magic.priority));
+
+        database
+    }
+
+    pub fn unalias<'a>(&'a self, mime_type: &'a str) -> &'a str {
+        self.aliases
+            .get(mime_type)
+            .map_or(mime_type, String::as_str)
+    }
+
+    /// The types whose globs match `file_name` with the highest weight, longest
+    /// pattern first.
+    pub fn types_for_file_name(&self, file_name: &str) -> Vec<&str> {
+        let name: Vec<char> = file_name.chars().collect();
+        let lower_name: Vec<char> = file_name.to_lowercase().chars().collect();
+
+        let mut matches: Vec<&Glob> = self
+            .globs
+            .iter()
+            .filter(|glob| {
+                if glob.case_sensitive {
+                    glob_matches(&glob.pattern.chars().collect::<Vec<_>>(), &name)
+                } else {
+                    glob_matches(
+                        &glob.pattern.to_lowercase().chars().collect::<Vec<_>>(),
+                        &lower_name,
+                    )
+                }
+            })
+            .collect();
+
+        let Some(weight) = matches.iter().map(|glob| glob.weight).max() else {
+            return vec![];
+        };
+
+        matches.retain(|glob| glob.weight == weight);
+        matches.sort_by_key(|glob| Reverse(glob.pattern.len()));
+
+        let longest = matches[0].pattern.len();
+        let mut types: Vec<&str> = vec![];
+        for glob in matches.iter().filter(|glob| glob.pattern.len() == longest) {
+            if !types.contains(&glob.mime_type.as_str()) {
+                types.push(&glob.mime_type);
+            }
+        }
+
+        types
+    }
+
+    /// The type of the highest priority magic section matching `data`.
+    pub fn type_for_data(&self, data: &[u8]) -> Option<&str> {
+        self.magic
+            .iter()
+            .find(|magic| magic.rules.iter().any(|rule| rule.matches(data)))
+            .map(|magic| magic.mime_type.as_str())
+    }
+
+    fn sniff_len(&self) -> usize {
+        self.magic
+            .iter()
+            .flat_map(|magic| &magic.rules)
+            .map(MagicRule::extent)
+            .fold(TEXT_SNIFF_LEN, usize::max)
+            .min(MAX_SNIFF_LEN)
+    }
+
+    /// The type of the file at `path`, from its name and else its content, as
+    /// the shared-mime-info spec recommends.
+    pub fn type_for_path(&self, path: &Path) -> String {
+        if path.is_dir() {
+            return DIRECTORY.to_string();
+        }
+
+        let glob_types = path
+            .file_name()
+            .map(|name| self.types_for_file_name(&name.to_string_lossy()))
+            .unwrap_or_default();
+
+        if glob_types.len() == 1 {
+            return self.unalias(glob_types[0]).to_string();
+        }
+
+        let mut data = Vec::new();
+        if let Ok(file) = File::open(path) {
+            let _ = file.take(self.sniff_len() as u64).read_to_end(&mut data);
+        }
+
+        if let Some(mime_type) = self.type_for_data(&data) {
+            // the content decides between conflicting globs
+            if glob_types.is_empty() || glob_types.contains(&mime_type) {
+                return self.unalias(mime_type).to_string();
+            }
+        }
+
+        if let Some(mime_type) = glob_types.first() {
+            return self.unalias(mime_type).to_string();
+        }
+
+        if data.is_empty() {
+            ZERO_SIZE.to_string()
+        } else if data.iter().take(TEXT_SNIFF_LEN).any(|byte| *byte == 0) {
+            BINARY.to_string()
+        } else {
+            TEXT.to_string()
+        }
+    }
+
+    /// The icon names for `mime_type`, the most specific first: the `icons`
+    /// file, the type itself with `/` as `-`, then the `generic-icons` file
+    /// and the generic icon of its media type.
+    pub fn icon_names(&self, mime_type: &str) -> Vec<String> {
+        let mime_type = self.unalias(mime_type);
+        let mut names = vec![];
+
+        if let Some(icon) = self.icons.get(mime_type) {
+            names.push(icon.clone());
+        }
+
+        names.push(mime_type.replace('/', "-"));
+
+        if let Some(icon) = self.generic_icons.get(mime_type) {
+            names.push(icon.clone());
+        }
+
+        if let Some((media, _)) = mime_type.split_once('/') {
+            names.push(format!("{media}-x-generic"));
+        }
+
+        names
+    }
+}
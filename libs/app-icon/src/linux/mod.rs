@@ -0,0 +1,447 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex, PoisonError},
+};
+
+use image::{Rgba, RgbaImage};
+use tauri_utils::config::Color;
+
+use crate::{
+    bundle, encode, pe, resample, svg, Application, FileType, GetAppIconError, IconAppearance,
+    IconFormat, IconOptions, IconSize, IconVariant,
+};
+
+pub(crate) use appimage::AppImageError;
+use desktop_entry::DesktopEntry;
+use icon_theme::IconLookup;
+use mime::MimeDatabase;
+pub(crate) use squashfs::SquashfsError;
+
+mod appimage;
+mod applications;
+mod desktop_entry;
+mod icon_theme;
+mod ini;
+mod mime;
+mod squashfs;
+mod tests;
+mod xdg;
+
+fn check_app_path(app_path: &Path) -> Result<(), GetAppIconError> {
+    if !app_path.exists() {
+        return Err(GetAppIconError::not_found(app_path));
+    }
+
+    if bundle::is_app_bundle(app_path)
+        || pe::is_pe_path(app_path)
+        || appimage::is_appimage(app_path)
+    {
+        return Ok(());
+    }
+
+    find_desktop_entry(app_path).map(|_| ())
+}
+
+/// The icon theme configured for the current user, which the icons of apps
+/// change with.
+pub(crate) fn icon_theme_name() -> String {
+    IconLookup::from_env().theme().to_string()
+}
+
+pub(crate) fn is_app_path(app_path: &Path) -> bool {
+    check_app_path(app_path).is_ok()
+}
+
+/// Where application directories keep their desktop entry: at the root of an
+/// AppDir, in `meta/gui` for a snap (`/snap/<name>` or one of its revisions)
+/// and in the `export` directory of a Flatpak app (`/var/lib/flatpak/app/<id>`).
+const DESKTOP_ENTRY_DIRS: &[&str] = &[
+    "",
+    "meta/gui",
+    "current/meta/gui",
+    "export/share/applications",
+    "current/active/export/share/applications",
+];
+
+fn is_desktop_entry(path: &Path) -> bool {
+    path.is_file() && path.extension().map_or(false, |ext| ext == "desktop")
+}
+
+/// Finds the `.desktop` file describing the application at `app_path`, which
+/// is either the desktop entry itself or an application directory (an AppDir,
+/// a snap or a Flatpak app) containing one.
+fn find_desktop_entry(app_path: &Path) -> Result<PathBuf, GetAppIconError> {
+    if is_desktop_entry(app_path) {
+        return Ok(app_path.to_path_buf());
+    }
+
+    if !app_path.is_dir() {
+        return Err(GetAppIconError::NotAnApplication(app_path.to_path_buf()));
+    }
+
+    DESKTOP_ENTRY_DIRS
+        .iter()
+        .find_map(|dir| {
+            let mut entries: Vec<PathBuf> = fs::read_dir(app_path.join(dir))
+                .ok()?
+                .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+                .filter(|path| is_desktop_entry(path))
+                .collect();
+
+            entries.sort();
+            entries.into_iter().next()
+        })
+        .ok_or_else(|| GetAppIconError::NotAnApplication(app_path.to_path_buf()))
+}
+
+/// The root of the snap a desktop entry belongs to, which its `Icon` refers
+/// to as `${SNAP}`: the entry is either in the snap's `meta/gui`, or exported
+/// by snapd as `<snap>_<app>.desktop`.
+fn snap_root(desktop_entry_path: &Path) -> Option<PathBuf> {
+    let dir = desktop_entry_path.parent()?;
+
+    if dir.ends_with("meta/gui") {
+        return Some(dir.parent()?.parent()?.to_path_buf());
+    }
+
+    let (name, _) = desktop_entry_path.file_stem()?.to_str()?.split_once('_')?;
+
+    Some(Path::new("/snap").join(name).join("current"))
+}
+
+/// The lookup for the icons of a desktop entry, which also searches the
+/// entry's own directory (the root of an AppDir, a snap's `meta/gui`) and the
+/// `icons` directory next to its `applications` directory (a Flatpak export).
+fn desktop_entry_lookup(lookup: &IconLookup, desktop_entry_path: &Path) -> IconLookup {
+    let mut lookup = lookup.clone();
+
+    if let Some(dir) = desktop_entry_path.parent() {
+        if dir.ends_with("share/applications") {
+            if let Some(share) = dir.parent() {
+                lookup = lookup.with_base_dir(share.join("icons"));
+            }
+        }
+
+        lookup = lookup.with_base_dir(dir.to_path_buf());
+    }
+
+    lookup
+}
+
+fn find_app_path_in(dirs: &[PathBuf], id: &str) -> Result<PathBuf, GetAppIconError> {
+    applications::find_application(dirs, id)
+        .ok_or_else(|| GetAppIconError::NotFound(id.to_string()))
+}
+
+/// Finds the desktop entry of the application with the desktop file id, window
+/// class or executable name `id` in `$XDG_DATA_DIRS/applications`.
+pub fn find_app_path(id: &str) -> Result<PathBuf, GetAppIconError> {
+    find_app_path_in(&applications::application_dirs(), id)
+}
+
+/// The applications installed in `$XDG_DATA_DIRS/applications`, with their
+/// names translated for the current locale.
+pub fn list_applications() -> Vec<Application> {
+    applications::list_applications(
+        &applications::application_dirs(),
+        &desktop_entry::current_locales(),
+    )
+}
+
+fn resolve_icon(
+    lookup: &IconLookup,
+    icon: &str,
+    icon_size: &IconSize,
+    options: &IconOptions,
+) -> Option<(PathBuf, IconAppearance)> {
+    let path = Path::new(icon);
+
+    if path.is_absolute() {
+        return path
+            .is_file()
+            .then(|| (path.to_path_buf(), IconAppearance::Light));
+    }
+
+    // some entries name the icon with its extension, which the theme lookup doesn't expect
+    let name = icon
+        .strip_suffix(".png")
+        .or_else(|| icon.strip_suffix(".svg"))
+        .or_else(|| icon.strip_suffix(".xpm"))
+        .unwrap_or(icon);
+
+    find_themed_icon(lookup, name, icon_size, options)
+}
+
+/// Looks `name` up in the icon theme, trying its `-symbolic` variant first
+/// when the icon is to be drawn in a symbolic color or tinted, and else the
+/// variant of the theme for the appearance.
+fn find_themed_icon(
+    lookup: &IconLookup,
+    name: &str,
+    icon_size: &IconSize,
+    options: &IconOptions,
+) -> Option<(PathBuf, IconAppearance)> {
+    let size = icon_size.point_size();
+    let scale = icon_size.scale.ceil().max(1.0) as u32;
+
+    let symbolic =
+        options.symbolic_color.is_some() || options.appearance == Some(IconAppearance::Tinted);
+
+    if symbolic && !name.ends_with("-symbolic") {
+        if let Some(path) = lookup.find_icon(&format!("{name}-symbolic"), size, scale) {
+            return Some((path, IconAppearance::Tinted));
+        }
+    }
+
+    match options.appearance {
+        Some(appearance) => lookup.find_icon_variant(name, size, scale, appearance),
+        // the configured theme as is, even when it's a dark or high contrast one
+        None => lookup
+            .find_icon(name, size, scale)
+            .map(|path| (path, IconAppearance::Light)),
+    }
+}
+
+fn get_icon_image_with(
+    lookup: &IconLookup,
+    app_path: &Path,
+    icon_size: IconSize,
+) -> Result<RgbaImage, GetAppIconError> {
+    get_icon_variant_with(lookup, app_path, icon_size, &IconOptions::default())
+        .map(|variant| variant.image)
+}
+
+fn get_icon_variant_with(
+    lookup: &IconLookup,
+    app_path: &Path,
+    icon_size: IconSize,
+    options: &IconOptions,
+) -> Result<IconVariant, GetAppIconError> {
+    if !app_path.exists() {
+        return Err(GetAppIconError::not_found(app_path));
+    }
+
+    // macOS bundles, e.g. on a mounted volume, carry their own .icns
+    if bundle::is_app_bundle(app_path) {
+        return bundle::get_icon_variant(
+            app_path,
+            icon_size,
+            options.appearance.unwrap_or_default(),
+        )
+        .map_err(|error| GetAppIconError::bundle(app_path, error));
+    }
+
+    // Windows executables and libraries carry their icons as PE resources
+    if pe::is_pe_path(app_path) {
+        return pe::get_icon_image(app_path, icon_size)
+            .map(IconVariant::light)
+            .map_err(|error| GetAppIconError::pe(app_path, error));
+    }
+
+    if appimage::is_appimage(app_path) {
+        return get_appimage_icon(lookup, app_path, icon_size, options);
+    }
+
+    let desktop_entry_path = find_desktop_entry(app_path)?;
+
+    let desktop_entry = DesktopEntry::from_path(&desktop_entry_path)
+        .map_err(|error| GetAppIconError::io(&desktop_entry_path, error))?;
+
+    let icon = desktop_entry
+        .icon()
+        .ok_or_else(|| GetAppIconError::no_icon(app_path))?;
+
+    let icon = match snap_root(&desktop_entry_path) {
+        Some(root) if icon.contains("${SNAP}") => icon.replace("${SNAP}", &root.to_string_lossy()),
+        _ => icon.to_string(),
+    };
+
+    let lookup = desktop_entry_lookup(lookup, &desktop_entry_path);
+    let (icon_path, appearance) = resolve_icon(&lookup, &icon, &icon_size, options)
+        .ok_or_else(|| GetAppIconError::no_icon(app_path))?;
+
+    Ok(IconVariant {
+        image: load_icon(&icon_path, &icon_size, options)?,
+        appearance,
+    })
+}
+
+/// Reads the icon out of the AppImage, or when its image can't be read
+/// (e.g. it is compressed with lz4), uses the desktop entry an AppImage
+/// integration tool installed for it.
+fn get_appimage_icon(
+    lookup: &IconLookup,
+    app_path: &Path,
+    icon_size: IconSize,
+    options: &IconOptions,
+) -> Result<IconVariant, GetAppIconError> {
+    let error = match appimage::read_icon(app_path) {
+        Ok(data) => return decode_icon(&data, None, &icon_size).map(IconVariant::light),
+        Err(error) => error,
+    };
+
+    let desktop_entry_path = fs::canonicalize(app_path).ok().and_then(|app_path| {
+        applications::find_application_by_program(&applications::application_dirs(), &app_path)
+    });
+
+    match desktop_entry_path {
+        Some(desktop_entry_path) => {
+            get_icon_variant_with(lookup, &desktop_entry_path, icon_size, options)
+        }
+        None => Err(GetAppIconError::appimage(app_path, error)),
+    }
+}
+
+fn is_symbolic(icon_path: &Path) -> bool {
+    icon_path.file_stem().map_or(false, |stem| {
+        let stem = stem.to_string_lossy();
+        stem.ends_with("-symbolic") || stem.ends_with(".symbolic")
+    })
+}
+
+/// Paints the icon in `color`, keeping the shape given by its alpha, like GTK
+/// draws symbolic icons in the foreground color.
+fn recolor(image: &mut RgbaImage, color: Color) {
+    let Color(red, green, blue, alpha) = color;
+
+    for pixel in image.pixels_mut() {
+        let coverage = (u16::from(pixel[3]) * u16::from(alpha) + 127) / 255;
+        *pixel = Rgba([red, green, blue, coverage as u8]);
+    }
+}
+
+/// Decodes a PNG (or other raster) icon, or renders an SVG one straight at
+/// the size it's needed at, recoloring it with `symbolic_color` if any.
+fn decode_icon(
+    data: &[u8],
+    symbolic_color: Option<Color>,
+    icon_size: &IconSize,
+) -> Result<RgbaImage, GetAppIconError> {
+    let image = match image::load_from_memory(data) {
+        Ok(image) => image.into_rgba8(),
+        Err(error) if image::guess_format(data).is_ok() => {
+            return Err(GetAppIconError::image(error))
+        }
+        Err(_) => svg::render(data, icon_size.source_size()).map_err(GetAppIconError::svg)?,
+    };
+
+    let mut image = resample::resample(image, icon_size);
+
+    if let Some(color) = symbolic_color {
+        recolor(&mut image, color);
+    }
+
+    Ok(image)
+}
+
+fn load_icon(
+    icon_path: &Path,
+    icon_size: &IconSize,
+    options: &IconOptions,
+) -> Result<RgbaImage, GetAppIconError> {
+    let data = fs::read(icon_path).map_err(|error| GetAppIconError::io(icon_path, error))?;
+    let symbolic_color = options.symbolic_color.filter(|_| is_symbolic(icon_path));
+
+    decode_icon(&data, symbolic_color, icon_size)
+}
+
+pub fn get_icon_image(app_path: &Path, icon_size: IconSize) -> Result<RgbaImage, GetAppIconError> {
+    get_icon_image_with(&IconLookup::from_env(), app_path, icon_size)
+}
+
+/// The icon of the app, from the variant of the icon theme for the
+/// appearance of `options` when there is one.
+pub fn get_icon_variant(
+    app_path: &Path,
+    icon_size: IconSize,
+    options: &IconOptions,
+) -> Result<IconVariant, GetAppIconError> {
+    get_icon_variant_with(&IconLookup::from_env(), app_path, icon_size, options)
+}
+
+fn get_file_type_icon_with(
+    lookup: &IconLookup,
+    database: &MimeDatabase,
+    file_type: FileType,
+    icon_size: IconSize,
+) -> Result<RgbaImage, GetAppIconError> {
+    let mime_type = match file_type {
+        FileType::Path(path) => {
+            if !path.exists() {
+                return Err(GetAppIconError::not_found(path));
+            }
+
+            database.type_for_path(path)
+        }
+        FileType::Extension(extension) => {
+            let file_name = format!("file.{}", extension.trim_start_matches('.'));
+
+            database
+                .types_for_file_name(&file_name)
+                .first()
+                .map_or(mime::BINARY, |mime_type| database.unalias(mime_type))
+                .to_string()
+        }
+        FileType::MimeType(mime_type) => database.unalias(mime_type).to_string(),
+    };
+
+    let (icon_path, _) = database
+        .icon_names(&mime_type)
+        .iter()
+        .find_map(|name| find_themed_icon(lookup, name, &icon_size, &IconOptions::default()))
+        .ok_or(GetAppIconError::NoIcon(mime_type))?;
+
+    load_icon(&icon_path, &icon_size, &IconOptions::default())
+}
+
+/// shared-mime-info, parsed for the first file type icon asked for, as it
+/// only changes when packages are installed.
+static MIME_DATABASE: Mutex<Option<Arc<MimeDatabase>>> = Mutex::new(None);
+
+fn mime_database() -> Arc<MimeDatabase> {
+    let mut database = MIME_DATABASE.lock().unwrap_or_else(PoisonError::into_inner);
+
+    database
+        .get_or_insert_with(|| Arc::new(MimeDatabase::load(&mime::mime_dirs())))
+        .clone()
+}
+
+/// The icon of a file type, whose MIME type is told by shared-mime-info and
+/// whose icon is looked up in the icon theme.
+pub fn get_file_type_icon(
+    file_type: FileType,
+    icon_size: IconSize,
+) -> Result<RgbaImage, GetAppIconError> {
+    get_file_type_icon_with(
+        &IconLookup::from_env(),
+        &mime_database(),
+        file_type,
+        icon_size,
+    )
+}
+
+pub fn get_icon_bytes(
+    app_path: &Path,
+    icon_size: IconSize,
+    format: IconFormat,
+) -> Result<Vec<u8>, GetAppIconError> {
+    let image = get_icon_image(app_path, icon_size)?;
+
+    encode::encode(&image, format).map_err(GetAppIconError::Encode)
+}
+
+pub fn get_icon(
+    app_path: &Path,
+    save_path: &Path,
+    icon_size: IconSize,
+    format: IconFormat,
+) -> Result<(), GetAppIconError> {
+    check_app_path(app_path)?;
+
+    crate::check_save_path(save_path)?;
+
+    let bytes = get_icon_bytes(app_path, icon_size, format)?;
+
+    fs::write(save_path, bytes).map_err(|error| GetAppIconError::io(save_path, error))
+}
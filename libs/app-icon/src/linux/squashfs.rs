@@ -0,0 +1,557 @@
+//! A read-only squashfs 4.0 reader, enough to pull single files out of an
+//! AppImage without mounting it.
+
+use std::{
+    fs::File,
+    io::{self, Read, Seek, SeekFrom, Write},
+};
+
+use thiserror::Error;
+
+const MAGIC: &[u8] = b"hsqs";
+const SUPERBLOCK_SIZE: usize = 96;
+
+/// The sizes the data blocks can have, as powers of two.
+const MIN_BLOCK_SIZE: u32 = 4 * 1024;
+const MAX_BLOCK_SIZE: u32 = 1024 * 1024;
+
+const METADATA_SIZE: usize = 8192;
+const METADATA_UNCOMPRESSED: u16 = 0x8000;
+const DATA_UNCOMPRESSED: u32 = 1 << 24;
+const NO_FRAGMENT: u32 = 0xffff_ffff;
+const FRAGMENT_ENTRY_SIZE: usize = 16;
+
+const COMPRESSION_GZIP: u16 = 1;
+const COMPRESSION_XZ: u16 = 4;
+const COMPRESSION_ZSTD: u16 = 6;
+
+/// Files larger than this aren't icons, and aren't read.
+const MAX_FILE_SIZE: u64 = 16 * 1024 * 1024;
+const MAX_SYMLINK_DEPTH: usize = 8;
+
+#[derive(Error, Debug)]
+pub enum SquashfsError {
+    #[error("failed to read the image")]
+    ReadError(#[source] io::Error),
+    #[error("data is not a squashfs image")]
+    InvalidSuperblock,
+    #[error("unsupported squashfs compression {0}, only gzip, xz and zstd are supported")]
+    UnsupportedCompression(u16),
+    #[error("squashfs image is corrupted")]
+    Corrupted,
+    #[error("file not found in the squashfs image")]
+    NotFound,
+}
+
+fn u16_at(bytes: &[u8], offset: usize) -> Result<u16, SquashfsError> {
+    Ok(u16::from_le_bytes(
+        bytes
+            .get(offset..offset + 2)
+            .ok_or(SquashfsError::Corrupted)?
+            .try_into()
+            .unwrap(),
+    ))
+}
+
+fn u32_at(bytes: &[u8], offset: usize) -> Result<u32, SquashfsError> {
+    Ok(u32::from_le_bytes(
+        bytes
+            .get(offset..offset + 4)
+            .ok_or(SquashfsError::Corrupted)?
+            .try_into()
+            .unwrap(),
+    ))
+}
+
+fn u64_at(bytes: &[u8], offset: usize) -> Result<u64, SquashfsError> {
+    Ok(u64::from_le_bytes(
+        bytes
+            .get(offset..offset + 8)
+            .ok_or(SquashfsError::Corrupted)?
+            .try_into()
+            .unwrap(),
+    ))
+}
+
+/// Collects decompressed data, failing past `max_size` bytes rather than
+/// inflating a corrupted block without end.
+struct LimitedWriter {
+    bytes: Vec<u8>,
+    max_size: usize,
+}
+
+impl Write for LimitedWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.bytes.len() + buf.len() > self.max_size {
+            return Err(io::ErrorKind::InvalidData.into());
+        }
+
+        self.bytes.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Superblock {
+    block_size: u32,
+    root_inode: u64,
+    inode_table: u64,
+    directory_table: u64,
+    fragment_table: u64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Inode {
+    Directory {
+        block: u32,
+        offset: u16,
+        size: u32,
+    },
+    File {
+        blocks_start: u64,
+        size: u64,
+        fragment: u32,
+        fragment_offset: u32,
+        block_sizes: Vec<u32>,
+    },
+    Symlink(String),
+    Other,
+}
+
+/// Reads a metadata table (inodes, directories, fragments), which is stored
+/// as a sequence of blocks of up to 8 KiB, each compressed on its own.
+struct MetadataReader<'a> {
+    squashfs: &'a Squashfs,
+    next_block: u64,
+    data: Vec<u8>,
+    position: usize,
+}
+
+impl<'a> MetadataReader<'a> {
+    /// Starts at `offset` in the uncompressed block at `block`, relative to
+    /// the start of the image.
+    fn new(squashfs: &'a Squashfs, block: u64, offset: usize) -> Result<Self, SquashfsError> {
+        let mut reader = Self {
+            squashfs,
+            next_block: block,
+            data: vec![],
+            position: 0,
+        };
+        reader.load_block()?;
+        reader.position = offset;
+
+        Ok(reader)
+    }
+
+    fn load_block(&mut self) -> Result<(), SquashfsError> {
+        let header = self.squashfs.read_at(self.next_block, 2)?;
+        let header = u16::from_le_bytes([header[0], header[1]]);
+        let size = usize::from(header & !METADATA_UNCOMPRESSED);
+
+        let raw = self
+            .squashfs
+            .read_at(self.next_block.saturating_add(2), size)?;
+        self.data = if header & METADATA_UNCOMPRESSED != 0 {
+            raw
+        } else {
+            self.squashfs.decompress(&raw, METADATA_SIZE)?
+        };
+        self.next_block = self.next_block.saturating_add(2 + size as u64);
+        self.position = 0;
+
+        Ok(())
+    }
+
+    fn read(&mut self, mut len: usize) -> Result<Vec<u8>, SquashfsError> {
+        let mut bytes = Vec::with_capacity(len);
+
+        while len > 0 {
+            if self.position >= self.data.len() {
+                self.load_block()?;
+                if self.data.is_empty() {
+                    return Err(SquashfsError::Corrupted);
+                }
+            }
+
+            let available = (self.data.len() - self.position).min(len);
+            bytes.extend_from_slice(&self.data[self.position..self.position + available]);
+            self.position += available;
+            len -= available;
+        }
+
+        Ok(bytes)
+    }
+}
+
+/// A squashfs image stored at `offset` in `file`.
+pub struct Squashfs {
+    file: File,
+    offset: u64,
+    superblock: Superblock,
+    compression: u16,
+}
+
+impl Squashfs {
+    pub fn open(file: File, offset: u64) -> Result<Self, SquashfsError> {
+        let mut squashfs = Self {
+            file,
+            offset,
+            superblock: Superblock {
+                block_size: 0,
+                root_inode: 0,
+                inode_table: 0,
+                directory_table: 0,
+                fragment_table: 0,
+            },
+            compression: 0,
+        };
+
+        let bytes = squashfs.read_at(0, SUPERBLOCK_SIZE)?;
+        if &bytes[0..4] != MAGIC || u16_at(&bytes, 28)? != 4 {
+            return Err(SquashfsError::InvalidSuperblock);
+        }
+
+        squashfs.compression = u16_at(&bytes, 20)?;
+        if ![COMPRESSION_GZIP, COMPRESSION_XZ, COMPRESSION_ZSTD].contains(&squashfs.compression) {
+            return Err(SquashfsError::UnsupportedCompression(squashfs.compression));
+        }
+
+        // the block size is also stored as its log2, which must agree
+        let block_size = u32_at(&bytes, 12)?;
+        if !block_size.is_power_of_two()
+            || !(MIN_BLOCK_SIZE..=MAX_BLOCK_SIZE).contains(&block_size)
+            || u32::from(u16_at(&bytes, 22)?) != block_size.trailing_zeros()
+        {
+            return Err(SquashfsError::InvalidSuperblock);
+        }
+
+        squashfs.superblock = Superblock {
+            block_size,
+            root_inode: u64_at(&bytes, 32)?,
+            inode_table: u64_at(&bytes, 64)?,
+            directory_table: u64_at(&bytes, 72)?,
+            fragment_table: u64_at(&bytes, 80)?,
+        };
+
+        Ok(squashfs)
+    }
+
+    fn read_at(&self, position: u64, len: usize) -> Result<Vec<u8>, SquashfsError> {
+        let mut file = &self.file;
+        let mut bytes = vec![0; len];
+
+        let start = self
+            .offset
+            .checked_add(position)
+            .ok_or(SquashfsError::Corrupted)?;
+
+        file.seek(SeekFrom::Start(start))
+            .and_then(|_| file.read_exact(&mut bytes))
+            .map_err(|error| match error.kind() {
+                // a table or block past the end of the image
+                io::ErrorKind::UnexpectedEof => SquashfsError::Corrupted,
+                _ => SquashfsError::ReadError(error),
+            })?;
+
+        Ok(bytes)
+    }
+
+    fn decompress(&self, data: &[u8], max_size: usize) -> Result<Vec<u8>, SquashfsError> {
+        if self.compression == COMPRESSION_GZIP {
+            return miniz_oxide::inflate::decompress_to_vec_zlib_with_limit(data, max_size)
+                .map_err(|_| SquashfsError::Corrupted);
+        }
+
+        let mut output = LimitedWriter {
+            bytes: Vec::new(),
+            max_size,
+        };
+
+        let decompressed = if self.compression == COMPRESSION_XZ {
+            lzma_rs::xz_decompress(&mut &data[..], &mut output).is_ok()
+        } else {
+            ruzstd::decoding::StreamingDecoder::new(data).map_or(false, |mut decoder| {
+                io::copy(&mut decoder, &mut output).is_ok()
+            })
+        };
+
+        decompressed
+            .then_some(output.bytes)
+            .ok_or(SquashfsError::Corrupted)
+    }
+
+    /// Reads the inode an inode reference points to: the upper bits are the
+    /// offset of its metadata block in the inode table, the lower 16 bits its
+    /// offset in that block.
+    fn inode(&self, reference: u64) -> Result<Inode, SquashfsError> {
+        let mut reader = MetadataReader::new(
+            self,
+            // offsets past the end of the image fail to be read
+            self.superblock.inode_table.saturating_add(reference >> 16),
+            (reference & 0xffff) as usize,
+        )?;
+
+        let header = reader.read(16)?;
+        let kind = u16_at(&header, 0)?;
+
+        let inode = match kind {
+            // basic directory
+            1 => {
+                let bytes = reader.read(16)?;
+                Inode::Directory {
+                    block: u32_at(&bytes, 0)?,
+                    size: u32::from(u16_at(&bytes, 8)?),
+                    offset: u16_at(&bytes, 10)?,
+                }
+            }
+            // extended directory
+            8 => {
+                let bytes = reader.read(24)?;
+                Inode::Directory {
+                    size: u32_at(&bytes, 4)?,
+                    block: u32_at(&bytes, 8)?,
+                    offset: u16_at(&bytes, 18)?,
+                }
+            }
+            // basic and extended files
+            2 | 9 => {
+                let (blocks_start, fragment, fragment_offset, size) = if kind == 2 {
+                    let bytes = reader.read(16)?;
+                    (
+                        u64::from(u32_at(&bytes, 0)?),
+                        u32_at(&bytes, 4)?,
+                        u32_at(&bytes, 8)?,
+                        u64::from(u32_at(&bytes, 12)?),
+                    )
+                } else {
+                    let bytes = reader.read(40)?;
+                    (
+                        u64_at(&bytes, 0)?,
+                        u32_at(&bytes, 28)?,
+                        u32_at(&bytes, 32)?,
+                        u64_at(&bytes, 8)?,
+                    )
+                };
+
+                if size > MAX_FILE_SIZE {
+                    return Err(SquashfsError::Corrupted);
+                }
+
+                let block_size = u64::from(self.superblock.block_size);
+                let count = if fragment == NO_FRAGMENT {
+                    (size + block_size - 1) / block_size
+                } else {
+                    size / block_size
+                };
+
+                let bytes = reader.read(count as usize * 4)?;
+                let block_sizes = bytes
+                    .chunks_exact(4)
+                    .map(|chunk| u32::from_le_bytes(chunk.try_into().unwrap()))
+                    .collect();
+
+                Inode::File {
+                    blocks_start,
+                    size,
+                    fragment,
+                    fragment_offset,
+                    block_sizes,
+                }
+            }
+            // basic and extended symlinks
+            3 | 10 => {
+                let bytes = reader.read(8)?;
+                let target_size = u32_at(&bytes, 4)? as usize;
+                if target_size > 4096 {
+                    return Err(SquashfsError::Corrupted);
+                }
+
+                let target = reader.read(target_size)?;
+                Inode::Symlink(String::from_utf8_lossy(&target).into_owned())
+            }
+            _ => Inode::Other,
+        };
+
+        Ok(inode)
+    }
+
+    /// The names and inode references of the entries of a directory.
+    fn list(&self, directory: &Inode) -> Result<Vec<(String, u64)>, SquashfsError> {
+        let Inode::Directory {
+            block,
+            offset,
+            size,
+        } = *directory
+        else {
+            return Err(SquashfsError::NotFound);
+        };
+
+        // the size counts the implicit '.' and '..' entries
+        let mut remaining = (size as usize).saturating_sub(3);
+        let mut reader = MetadataReader::new(
+            self,
+            self.superblock
+                .directory_table
+                .saturating_add(u64::from(block)),
+            usize::from(offset),
+        )?;
+        let mut entries = vec![];
+
+        while remaining >= 12 {
+            let header = reader.read(12)?;
+            let count = u32_at(&header, 0)? as usize + 1;
+            let start = u64::from(u32_at(&header, 4)?);
+            remaining -= 12;
+
+            for _ in 0..count {
+                let entry = reader.read(8)?;
+                let name_size = usize::from(u16_at(&entry, 6)?) + 1;
+                let name = reader.read(name_size)?;
+                remaining = remaining.saturating_sub(8 + name_size);
+
+                entries.push((
+                    String::from_utf8_lossy(&name).into_owned(),
+                    (start << 16) | u64::from(u16_at(&entry, 0)?),
+                ));
+            }
+        }
+
+        Ok(entries)
+    }
+
+    /// The names of the entries of the root directory.
+    pub fn root_entries(&self) -> Result<Vec<String>, SquashfsError> {
+        let root = self.inode(self.superblock.root_inode)?;
+
+        Ok(self
+            .list(&root)?
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect())
+    }
+
+    /// Resolves `path`, relative to the root, following symlinks.
+    fn lookup(&self, path: &str, depth: usize) -> Result<Inode, SquashfsError> {
+        if depth > MAX_SYMLINK_DEPTH {
+            return Err(SquashfsError::NotFound);
+        }
+
+        let mut parents: Vec<Inode> = vec![];
+        let mut inode = self.inode(self.superblock.root_inode)?;
+        let components: Vec<&str> = path
+            .split('/')
+            .filter(|component| !component.is_empty() && *component != ".")
+            .collect();
+
+        for (index, component) in components.iter().enumerate() {
+            if *component == ".." {
+                inode = parents.pop().unwrap_or(inode);
+                continue;
+            }
+
+            let reference = self
+                .list(&inode)?
+                .into_iter()
+                .find(|(name, _)| name == component)
+                .map(|(_, reference)| reference)
+                .ok_or(SquashfsError::NotFound)?;
+            let child = self.inode(reference)?;
+
+            if let Inode::Symlink(target) = &child {
+                // targets are relative to the link's directory, or to the image root
+                let parent_path = components[..index].join("/");
+                let rest = components[index + 1..].join("/");
+                let target = if target.starts_with('/') {
+                    format!("{target}/{rest}")
+                } else {
+                    format!("{parent_path}/{target}/{rest}")
+                };
+
+                return self.lookup(&target, depth + 1);
+            }
+
+            parents.push(std::mem::replace(&mut inode, child));
+        }
+
+        Ok(inode)
+    }
+
+    fn fragment(&self, index: u32) -> Result<Vec<u8>, SquashfsError> {
+        let index = index as usize;
+        let entries_per_block = METADATA_SIZE / FRAGMENT_ENTRY_SIZE;
+
+        let pointer = self.read_at(
+            self.superblock
+                .fragment_table
+                .saturating_add((index / entries_per_block) as u64 * 8),
+            8,
+        )?;
+        let mut reader = MetadataReader::new(
+            self,
+            u64_at(&pointer, 0)?,
+            (index % entries_per_block) * FRAGMENT_ENTRY_SIZE,
+        )?;
+        let entry = reader.read(FRAGMENT_ENTRY_SIZE)?;
+
+        self.data_block(u64_at(&entry, 0)?, u32_at(&entry, 8)?)
+    }
+
+    fn data_block(&self, position: u64, size: u32) -> Result<Vec<u8>, SquashfsError> {
+        let block_size = self.superblock.block_size as usize;
+        let stored = (size & !DATA_UNCOMPRESSED) as usize;
+
+        if stored == 0 {
+            // a sparse block
+            return Ok(vec![0; block_size]);
+        }
+
+        let raw = self.read_at(position, stored)?;
+        if size & DATA_UNCOMPRESSED != 0 {
+            Ok(raw)
+        } else {
+            self.decompress(&raw, block_size)
+        }
+    }
+
+    /// Reads the file at `path`, relative to the root of the image.
+    pub fn read_file(&self, path: &str) -> Result<Vec<u8>, SquashfsError> {
+        let Inode::File {
+            blocks_start,
+            size,
+            fragment,
+            fragment_offset,
+            block_sizes,
+        } = self.lookup(path, 0)?
+        else {
+            return Err(SquashfsError::NotFound);
+        };
+
+        let mut data = Vec::with_capacity(size as usize);
+        let mut position = blocks_start;
+
+        for block_size in block_sizes {
+            data.extend(self.data_block(position, block_size)?);
+            position = position.saturating_add(u64::from(block_size & !DATA_UNCOMPRESSED));
+        }
+
+        if fragment != NO_FRAGMENT {
+            let block = self.fragment(fragment)?;
+            let start = fragment_offset as usize;
+            let len = (size % u64::from(self.superblock.block_size)) as usize;
+
+            data.extend_from_slice(
+                block
+                    .get(start..start + len)
+                    .ok_or(SquashfsError::Corrupted)?,
+            );
+        }
+
+        data.truncate(size as usize);
+
+        Ok(data)
+    }
+}
@@ -0,0 +1,1114 @@
+#![cfg(test)]
+use std::{
+    fs,
+    os::unix::fs::PermissionsExt,
+    path::{Path, PathBuf},
+};
+
+use image::{Rgba, RgbaImage};
+use tauri_utils::config::Color;
+
+use crate::{
+    fixture_dir, Application, FileType, GetAppIconError, IconAppearance, IconFormat, IconOptions,
+    IconSize,
+};
+
+use super::{
+    appimage, applications::list_applications, desktop_entry::locale_variants, find_app_path_in,
+    get_file_type_icon_with, get_icon, get_icon_bytes, get_icon_image_with, get_icon_variant_with,
+    icon_theme::IconLookup, mime::MimeDatabase, squashfs,
+};
+
+fn write_file(path: &Path, contents: &str) {
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    fs::write(path, contents).unwrap();
+}
+
+fn write_png(path: &Path, size: u32) {
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    RgbaImage::new(size, size).save(path).unwrap();
+}
+
+fn write_hicolor(base_dir: &Path) {
+    write_file(
+        &base_dir.join("hicolor/index.theme"),
+        "[Icon Theme]\n\
+         Name=Hicolor\n\
+         Directories=16x16/apps,48x48/apps,256x256/apps\n\
+         \n\
+         [16x16/apps]\n\
+         Size=16\n\
+         Type=Threshold\n\
+         \n\
+         [48x48/apps]\n\
+         Size=48\n\
+         Type=Threshold\n\
+         \n\
+         [256x256/apps]\n\
+         Size=256\n\
+         Type=Threshold\n",
+    );
+}
+
+#[test]
+fn app_path_does_not_exist() {
+    let app_path = Path::new("/foo/bar.desktop");
+    let save_path = Path::new("/tmp/bar.png");
+    assert!(matches!(
+        get_icon(app_path, save_path, IconSize::square(32.0), IconFormat::Png).unwrap_err(),
+        GetAppIconError::NotFound(_)
+    ));
+}
+
+#[test]
+fn app_path_is_not_desktop_entry() {
+    let dir = fixture_dir("not-desktop-entry");
+    let app_path = dir.join("foo.txt");
+    write_file(&app_path, "");
+    assert!(matches!(
+        get_icon(
+            &app_path,
+            &dir.join("foo.png"),
+            IconSize::square(32.0),
+            IconFormat::Png
+        )
+        .unwrap_err(),
+        GetAppIconError::NotAnApplication(_)
+    ));
+    assert!(matches!(
+        get_icon(
+            &dir,
+            &dir.join("foo.png"),
+            IconSize::square(32.0),
+            IconFormat::Png
+        )
+        .unwrap_err(),
+        GetAppIconError::NotAnApplication(_)
+    ));
+}
+
+#[test]
+fn save_path_parent_does_not_exist() {
+    let dir = fixture_dir("save-path-parent");
+    let app_path = dir.join("foo.desktop");
+    write_file(&app_path, "[Desktop Entry]\nIcon=foo\n");
+    assert!(matches!(
+        get_icon(
+            &app_path,
+            Path::new("/foo/foo.png"),
+            IconSize::square(32.0),
+            IconFormat::Png
+        )
+        .unwrap_err(),
+        GetAppIconError::Io { path, .. } if path == Path::new("/foo")
+    ));
+}
+
+#[test]
+fn desktop_entry_without_icon() {
+    let dir = fixture_dir("without-icon");
+    let app_path = dir.join("foo.desktop");
+    write_file(
+        &app_path,
+        "[Desktop Entry]\nName=Foo\n[Desktop Action new]\nIcon=foo\n",
+    );
+    assert!(matches!(
+        get_icon(
+            &app_path,
+            &dir.join("foo.png"),
+            IconSize::square(32.0),
+            IconFormat::Png
+        )
+        .unwrap_err(),
+        GetAppIconError::NoIcon(_)
+    ));
+}
+
+#[test]
+fn icon_not_found() {
+    let dir = fixture_dir("icon-not-found");
+    let app_path = dir.join("foo.desktop");
+    write_file(&app_path, "[Desktop Entry]\nIcon=foo\n");
+    let lookup = IconLookup::new(vec![dir.join("icons")], "hicolor");
+    assert!(matches!(
+        get_icon_image_with(&lookup, &app_path, IconSize::square(32.0)).unwrap_err(),
+        GetAppIconError::NoIcon(_)
+    ));
+}
+
+#[test]
+fn it_picks_the_closest_size() {
+    let dir = fixture_dir("closest-size");
+    let base_dir = dir.join("icons");
+    write_hicolor(&base_dir);
+    write_png(&base_dir.join("hicolor/16x16/apps/foo.png"), 16);
+    write_png(&base_dir.join("hicolor/48x48/apps/foo.png"), 48);
+    write_png(&base_dir.join("hicolor/256x256/apps/foo.png"), 256);
+
+    let lookup = IconLookup::new(vec![base_dir.clone()], "hicolor");
+    assert_eq!(
+        lookup.find_icon("foo", 48, 1),
+        Some(base_dir.join("hicolor/48x48/apps/foo.png"))
+    );
+    assert_eq!(
+        lookup.find_icon("foo", 17, 1),
+        Some(base_dir.join("hicolor/16x16/apps/foo.png"))
+    );
+    assert_eq!(
+        lookup.find_icon("foo", 200, 1),
+        Some(base_dir.join("hicolor/256x256/apps/foo.png"))
+    );
+}
+
+#[test]
+fn it_follows_theme_inheritance() {
+    let dir = fixture_dir("inheritance");
+    let base_dir = dir.join("icons");
+    write_hicolor(&base_dir);
+    write_file(
+        &base_dir.join("Child/index.theme"),
+        "[Icon Theme]\nName=Child\nInherits=Parent\nDirectories=48x48/apps\n\n[48x48/apps]\nSize=48\n",
+    );
+    write_file(
+        &base_dir.join("Parent/index.theme"),
+        "[Icon Theme]\nName=Parent\nInherits=Child\nDirectories=apps/48\n\n[apps/48]\nSize=48\nType=Fixed\n",
+    );
+    write_png(&base_dir.join("Parent/apps/48/foo.png"), 48);
+    write_png(&base_dir.join("hicolor/48x48/apps/foo.png"), 48);
+    write_png(&base_dir.join("hicolor/48x48/apps/bar.png"), 48);
+    write_png(&dir.join("pixmaps/baz.png"), 32);
+
+    let lookup = IconLookup::new(vec![base_dir.clone(), dir.join("pixmaps")], "Child");
+    assert_eq!(
+        lookup.find_icon("foo", 48, 1),
+        Some(base_dir.join("Parent/apps/48/foo.png"))
+    );
+    assert_eq!(
+        lookup.find_icon("bar", 48, 1),
+        Some(base_dir.join("hicolor/48x48/apps/bar.png"))
+    );
+    assert_eq!(
+        lookup.find_icon("baz", 48, 1),
+        Some(dir.join("pixmaps/baz.png"))
+    );
+}
+
+#[test]
+fn it_prefers_matching_scale() {
+    let dir = fixture_dir("scale");
+    let base_dir = dir.join("icons");
+    write_file(
+        &base_dir.join("hicolor/index.theme"),
+        "[Icon Theme]\nName=Hicolor\nDirectories=32x32/apps\nScaledDirectories=32x32@2/apps\n\n\
+         [32x32/apps]\nSize=32\n\n[32x32@2/apps]\nSize=32\nScale=2\n",
+    );
+    write_png(&base_dir.join("hicolor/32x32/apps/foo.png"), 32);
+    write_png(&base_dir.join("hicolor/32x32@2/apps/foo.png"), 64);
+
+    let lookup = IconLookup::new(vec![base_dir.clone()], "hicolor");
+    assert_eq!(
+        lookup.find_icon("foo", 32, 2),
+        Some(base_dir.join("hicolor/32x32@2/apps/foo.png"))
+    );
+    assert_eq!(
+        lookup.find_icon("foo", 32, 1),
+        Some(base_dir.join("hicolor/32x32/apps/foo.png"))
+    );
+}
+
+#[test]
+fn it_works() {
+    let dir = fixture_dir("it-works");
+    let base_dir = dir.join("icons");
+    write_hicolor(&base_dir);
+    write_png(&base_dir.join("hicolor/48x48/apps/foo.png"), 48);
+    write_file(
+        &dir.join("apps/foo.desktop"),
+        "[Desktop Entry]\nType=Application\nName=Foo\nIcon=foo\n",
+    );
+
+    let lookup = IconLookup::new(vec![base_dir], "hicolor");
+    let image = get_icon_image_with(&lookup, &dir.join("apps"), IconSize::square(32.0)).unwrap();
+    assert_eq!(image.dimensions(), (32, 32));
+}
+
+#[test]
+fn it_honors_scale_and_aspect_ratio() {
+    let dir = fixture_dir("scale-and-aspect-ratio");
+    let base_dir = dir.join("icons");
+    write_hicolor(&base_dir);
+    write_png(&base_dir.join("hicolor/256x256/apps/foo.png"), 256);
+    write_file(
+        &dir.join("apps/foo.desktop"),
+        "[Desktop Entry]\nType=Application\nName=Foo\nIcon=foo\n",
+    );
+
+    let lookup = IconLookup::new(vec![base_dir], "hicolor");
+    let size = IconSize::new(48.0, 32.0).with_scale(2.0);
+    let image = get_icon_image_with(&lookup, &dir.join("apps"), size).unwrap();
+    assert_eq!(image.dimensions(), (96, 64));
+}
+
+#[test]
+fn it_works_with_absolute_icon_path() {
+    let dir = fixture_dir("absolute-icon");
+    let icon_path = dir.join("foo.png");
+    write_png(&icon_path, 64);
+    let app_path = dir.join("foo.desktop");
+    write_file(
+        &app_path,
+        &format!("[Desktop Entry]\nIcon={}\n", icon_path.display()),
+    );
+
+    let save_path = dir.join("saved.png");
+    assert!(get_icon(
+        &app_path,
+        &save_path,
+        IconSize::square(16.0),
+        IconFormat::Png
+    )
+    .is_ok());
+    assert_eq!(image::open(&save_path).unwrap().width(), 16);
+}
+
+#[test]
+fn it_returns_png_bytes() {
+    let dir = fixture_dir("png-bytes");
+    let icon_path = dir.join("foo.png");
+    write_png(&icon_path, 64);
+    let app_path = dir.join("foo.desktop");
+    write_file(
+        &app_path,
+        &format!("[Desktop Entry]\nIcon={}\n", icon_path.display()),
+    );
+
+    let bytes = get_icon_bytes(&app_path, IconSize::square(24.0), IconFormat::Png).unwrap();
+    let image = image::load_from_memory_with_format(&bytes, image::ImageFormat::Png).unwrap();
+    assert_eq!((image.width(), image.height()), (24, 24));
+}
+
+#[test]
+fn it_works_with_app_bundle() {
+    let dir = fixture_dir("app-bundle");
+    let app_path = dir.join("Foo.app");
+    write_file(
+        &app_path.join("Contents/Info.plist"),
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<plist version="1.0">
+<dict>
+    <key>CFBundleIconFile</key>
+    <string>AppIcon</string>
+</dict>
+</plist>"#,
+    );
+    fs::create_dir_all(app_path.join("Contents/Resources")).unwrap();
+    fs::write(
+        app_path.join("Contents/Resources/AppIcon.icns"),
+        crate::icns::write(&[RgbaImage::new(64, 64)]).unwrap(),
+    )
+    .unwrap();
+
+    let save_path = dir.join("Foo.png");
+    assert!(get_icon(
+        &app_path,
+        &save_path,
+        IconSize::square(32.0),
+        IconFormat::Png
+    )
+    .is_ok());
+    assert_eq!(image::open(&save_path).unwrap().width(), 32);
+}
+
+#[test]
+fn it_rejects_exe_files_that_are_not_pe() {
+    let dir = fixture_dir("windows-executable");
+    let app_path = dir.join("Foo.exe");
+    write_file(&app_path, "not a PE file");
+
+    assert!(matches!(
+        get_icon_bytes(&app_path, IconSize::square(32.0), IconFormat::Png),
+        Err(GetAppIconError::NotAnApplication(path)) if path == app_path
+    ));
+}
+
+fn write_applications(dir: &Path) -> Vec<PathBuf> {
+    let user_dir = dir.join("home/applications");
+    let system_dir = dir.join("usr/applications");
+
+    write_file(
+        &system_dir.join("org.gnome.Nautilus.desktop"),
+        "[Desktop Entry]\nName=Files\nExec=nautilus --new-window %U\n",
+    );
+    write_file(
+        &system_dir.join("org.mozilla.firefox.desktop"),
+        "[Desktop Entry]\nName=Firefox\nExec=env MOZ_ENABLE_WAYLAND=1 /usr/lib/firefox/firefox %u\n\
+         StartupWMClass=Navigator\n",
+    );
+    write_file(
+        &system_dir.join("kde/org.kde.dolphin.desktop"),
+        "[Desktop Entry]\nName=Dolphin\nExec=dolphin %u\n",
+    );
+    // the user's entry hides the system one with the same desktop file id
+    write_file(
+        &user_dir.join("kde-org.kde.dolphin.desktop"),
+        "[Desktop Entry]\nName=Dolphin\nExec=dolphin --new-window %u\n",
+    );
+
+    vec![user_dir, system_dir]
+}
+
+#[test]
+fn it_finds_apps_by_desktop_id() {
+    let dir = fixture_dir("find-by-desktop-id");
+    let dirs = write_applications(&dir);
+
+    assert_eq!(
+        find_app_path_in(&dirs, "org.gnome.Nautilus.desktop").unwrap(),
+        (dir.join("usr/applications/org.gnome.Nautilus.desktop"))
+    );
+    assert_eq!(
+        find_app_path_in(&dirs, "org.gnome.Nautilus").unwrap(),
+        (dir.join("usr/applications/org.gnome.Nautilus.desktop"))
+    );
+    assert_eq!(
+        find_app_path_in(&dirs, "kde-org.kde.dolphin.desktop").unwrap(),
+        (dir.join("home/applications/kde-org.kde.dolphin.desktop"))
+    );
+}
+
+#[test]
+fn it_finds_apps_by_window_class_and_executable() {
+    let dir = fixture_dir("find-by-window-class");
+    let dirs = write_applications(&dir);
+    // searching every entry doesn't follow a symlink back to its directory
+    std::os::unix::fs::symlink(&dirs[1], dirs[1].join("loop")).unwrap();
+
+    assert_eq!(
+        find_app_path_in(&dirs, "navigator").unwrap(),
+        (dir.join("usr/applications/org.mozilla.firefox.desktop"))
+    );
+    assert_eq!(
+        find_app_path_in(&dirs, "firefox").unwrap(),
+        (dir.join("usr/applications/org.mozilla.firefox.desktop"))
+    );
+    assert_eq!(
+        find_app_path_in(&dirs, "Dolphin").unwrap(),
+        (dir.join("home/applications/kde-org.kde.dolphin.desktop"))
+    );
+    assert!(matches!(
+        find_app_path_in(&dirs, "gimp"),
+        Err(GetAppIconError::NotFound(id)) if id == "gimp"
+    ));
+}
+
+#[test]
+fn it_splits_locale_variants() {
+    assert_eq!(
+        locale_variants("de_DE.UTF-8@euro"),
+        vec!["de_DE@euro", "de_DE", "de@euro", "de"]
+    );
+    assert_eq!(locale_variants("pt_BR.UTF-8"), vec!["pt_BR", "pt"]);
+    assert!(locale_variants("C.UTF-8").is_empty());
+}
+
+#[test]
+fn it_lists_applications() {
+    let dir = fixture_dir("list-applications");
+    let user_dir = dir.join("home/applications");
+    let system_dir = dir.join("usr/applications");
+    let installed = dir.join("bin/editor");
+    write_file(&installed, "#!/bin/sh\n");
+    fs::set_permissions(&installed, fs::Permissions::from_mode(0o755)).unwrap();
+
+    write_file(
+        &system_dir.join("org.example.Editor.desktop"),
+        &format!(
+            "[Desktop Entry]\n\
+             Type=Application\n\
+             Name=Text Editor\n\
+             Name[de]=Texteditor\n\
+             Name[de_AT]=Texteditor (AT)\n\
+             Exec=editor %F\n\
+             TryExec={}\n\
+             Icon=org.example.Editor\n\
+             Categories=Utility;TextEditor;\n",
+            installed.display()
+        ),
+    );
+    write_file(
+        &system_dir.join("org.example.Missing.desktop"),
+        "[Desktop Entry]\nType=Application\nName=Missing\nTryExec=/does/not/exist\n",
+    );
+    write_file(
+        &system_dir.join("org.example.Link.desktop"),
+        "[Desktop Entry]\nType=Link\nName=Website\nURL=https://example.com\n",
+    );
+    write_file(
+        &system_dir.join("org.example.Handler.desktop"),
+        "[Desktop Entry]\nType=Application\nName=Handler\nExec=handler\nNoDisplay=true\n",
+    );
+    // the user removed the handler by overriding its entry
+    write_file(
+        &user_dir.join("org.example.Handler.desktop"),
+        "[Desktop Entry]\nType=Application\nName=Handler\nExec=handler\nHidden=true\n",
+    );
+
+    let applications = list_applications(
+        &[user_dir.clone(), system_dir.clone()],
+        &locale_variants("de_DE.UTF-8"),
+    );
+
+    assert_eq!(
+        applications,
+        vec![
+            Application {
+                id: "org.example.Handler.desktop".to_string(),
+                name: "Handler".to_string(),
+                localized_name: "Handler".to_string(),
+                executable: Some("handler".to_string()),
+                path: user_dir.join("org.example.Handler.desktop"),
+                hidden: true,
+                ..Default::default()
+            },
+            Application {
+                id: "org.example.Editor.desktop".to_string(),
+                name: "Text Editor".to_string(),
+                localized_name: "Texteditor".to_string(),
+                executable: Some("editor".to_string()),
+                path: system_dir.join("org.example.Editor.desktop"),
+                categories: vec!["Utility".to_string(), "TextEditor".to_string()],
+                icon: Some("org.example.Editor".to_string()),
+                ..Default::default()
+            },
+        ]
+    );
+}
+
+fn write_mime_database(dir: &Path) -> MimeDatabase {
+    write_file(
+        &dir.join("globs2"),
+        "# comment\n\
+         50:text/x-csrc:*.[ch]\n\
+         50:application/pdf:*.pdf\n",
+    );
+    let mut magic = b"MIME-Magic\0\n[50:application/pdf]\n>0=\0\x05%PDF-\n".to_vec();
+    magic.extend_from_slice(b"[40:text/x-csrc]\n>0=\0\x08#include\n");
+    fs::write(dir.join("magic"), magic).unwrap();
+    write_file(&dir.join("aliases"), "application/x-pdf application/pdf\n");
+    write_file(&dir.join("generic-icons"), "text/x-csrc:text-x-script\n");
+
+    MimeDatabase::load(&[dir.to_path_buf()])
+}
+
+#[test]
+fn it_detects_mime_types() {
+    let dir = fixture_dir("mime-types");
+    let database = write_mime_database(&dir.join("mime"));
+
+    assert_eq!(database.types_for_file_name("main.c"), vec!["text/x-csrc"]);
+    assert_eq!(database.types_for_file_name("main.H"), vec!["text/x-csrc"]);
+    assert_eq!(database.unalias("application/x-pdf"), "application/pdf");
+
+    let files = dir.join("files");
+    fs::create_dir_all(&files).unwrap();
+    fs::write(files.join("document"), "%PDF-1.7\n").unwrap();
+    fs::write(files.join("notes"), "hello\n").unwrap();
+    fs::write(files.join("empty"), "").unwrap();
+
+    assert_eq!(
+        database.type_for_path(&files.join("document")),
+        "application/pdf"
+    );
+    assert_eq!(database.type_for_path(&files.join("notes")), "text/plain");
+    assert_eq!(
+        database.type_for_path(&files.join("empty")),
+        "application/x-zerosize"
+    );
+    assert_eq!(database.type_for_path(&files), "inode/directory");
+}
+
+#[test]
+fn it_gets_file_type_icons() {
+    let dir = fixture_dir("file-type-icons");
+    let database = write_mime_database(&dir.join("mime"));
+
+    let base_dir = dir.join("icons");
+    write_file(
+        &base_dir.join("hicolor/index.theme"),
+        "[Icon Theme]\n\
+         Name=Hicolor\n\
+         Directories=48x48/mimetypes\n\
+         \n\
+         [48x48/mimetypes]\n\
+         Size=48\n\
+         Type=Threshold\n",
+    );
+    let icon_dir = base_dir.join("hicolor/48x48/mimetypes");
+    fs::create_dir_all(&icon_dir).unwrap();
+    for (name, color) in [
+        ("application-pdf", [255, 0, 0, 255]),
+        ("text-x-script", [0, 255, 0, 255]),
+        ("text-x-generic", [0, 0, 255, 255]),
+    ] {
+        RgbaImage::from_pixel(48, 48, Rgba(color))
+            .save(icon_dir.join(format!("{name}.png")))
+            .unwrap();
+    }
+    let lookup = IconLookup::new(vec![base_dir], "hicolor");
+
+    let document = dir.join("document");
+    fs::write(&document, "%PDF-1.7\n").unwrap();
+
+    let pixel = |file_type| {
+        let image =
+            get_file_type_icon_with(&lookup, &database, file_type, IconSize::square(32.0)).unwrap();
+        assert_eq!(image.dimensions(), (32, 32));
+        *image.get_pixel(16, 16)
+    };
+
+    assert_eq!(pixel(FileType::Extension("pdf")), Rgba([255, 0, 0, 255]));
+    assert_eq!(pixel(FileType::Extension(".PDF")), Rgba([255, 0, 0, 255]));
+    assert_eq!(
+        pixel(FileType::MimeType("application/x-pdf")),
+        Rgba([255, 0, 0, 255])
+    );
+    assert_eq!(pixel(FileType::Path(&document)), Rgba([255, 0, 0, 255]));
+    // the generic-icons file, then the generic icon of the media type
+    assert_eq!(pixel(FileType::Extension("c")), Rgba([0, 255, 0, 255]));
+    assert_eq!(
+        pixel(FileType::MimeType("text/markdown")),
+        Rgba([0, 0, 255, 255])
+    );
+
+    assert!(matches!(
+        get_file_type_icon_with(
+            &lookup,
+            &database,
+            FileType::MimeType("video/mp4"),
+            IconSize::square(32.0)
+        )
+        .unwrap_err(),
+        GetAppIconError::NoIcon(mime_type) if mime_type == "video/mp4"
+    ));
+    assert!(matches!(
+        get_file_type_icon_with(
+            &lookup,
+            &database,
+            FileType::Path(&dir.join("missing")),
+            IconSize::square(32.0)
+        )
+        .unwrap_err(),
+        GetAppIconError::NotFound(_)
+    ));
+}
+
+#[test]
+fn it_renders_scalable_and_symbolic_icons() {
+    let dir = fixture_dir("scalable-icons");
+    let base_dir = dir.join("icons");
+    write_file(
+        &base_dir.join("hicolor/index.theme"),
+        "[Icon Theme]\n\
+         Name=Hicolor\n\
+         Directories=scalable/apps,symbolic/apps\n\
+         \n\
+         [scalable/apps]\n\
+         Size=128\n\
+         MinSize=8\n\
+         MaxSize=512\n\
+         Type=Scalable\n\
+         \n\
+         [symbolic/apps]\n\
+         Size=16\n\
+         MinSize=8\n\
+         MaxSize=512\n\
+         Type=Scalable\n",
+    );
+    write_file(
+        &base_dir.join("hicolor/scalable/apps/foo.svg"),
+        r##"<svg viewBox="0 0 128 128"><rect width="128" height="128" fill="#3584e4"/></svg>"##,
+    );
+    write_file(
+        &base_dir.join("hicolor/symbolic/apps/foo-symbolic.svg"),
+        r##"<svg viewBox="0 0 16 16"><path d="M0 0h16v16H0z" fill="#2e3436" opacity=".5"/></svg>"##,
+    );
+    let app_path = dir.join("foo.desktop");
+    write_file(&app_path, "[Desktop Entry]\nIcon=foo\n");
+    let lookup = IconLookup::new(vec![base_dir], "hicolor");
+
+    let image = get_icon_image_with(&lookup, &app_path, IconSize::square(24.0)).unwrap();
+    assert_eq!(image.dimensions(), (24, 24));
+    assert_eq!(image.get_pixel(12, 12), &Rgba([0x35, 0x84, 0xe4, 255]));
+
+    let options = IconOptions::default().with_symbolic_color(Color(255, 255, 255, 255));
+    let size = IconSize::square(16.0).with_scale(2.0);
+    let image = get_icon_variant_with(&lookup, &app_path, size, &options)
+        .unwrap()
+        .image;
+    assert_eq!(image.dimensions(), (32, 32));
+    assert_eq!(image.get_pixel(16, 16), &Rgba([255, 255, 255, 128]));
+}
+
+enum SquashfsNode<'a> {
+    File(&'a [u8]),
+    Symlink(&'a str),
+}
+
+/// `data` compressed as squashfs compression `compression` does it.
+fn compress(compression: u16, data: &[u8]) -> Vec<u8> {
+    match compression {
+        1 => miniz_oxide::deflate::compress_to_vec_zlib(data, 6),
+        4 => {
+            let mut compressed = vec![];
+            lzma_rs::xz_compress(&mut &data[..], &mut compressed).unwrap();
+            compressed
+        }
+        6 => ruzstd::encoding::compress_to_vec(data, ruzstd::encoding::CompressionLevel::Fastest),
+        _ => unreachable!(),
+    }
+}
+
+/// A gzip squashfs image of `nodes`, in a type 2 AppImage whose runtime is
+/// just an ELF header. File tails are stored in an uncompressed fragment.
+fn write_appimage(path: &Path, nodes: &[(&str, SquashfsNode)]) {
+    write_appimage_with(path, 1, nodes);
+}
+
+/// Like `write_appimage`, with squashfs compression `compression`.
+fn write_appimage_with(path: &Path, compression: u16, nodes: &[(&str, SquashfsNode)]) {
+    use std::collections::BTreeMap;
+
+    const BLOCK_SIZE: usize = 4096;
+
+    #[derive(Default)]
+    struct Directory {
+        children: BTreeMap<String, Directory>,
+        nodes: Vec<(String, usize)>,
+    }
+
+    let mut root = Directory::default();
+    for (index, (path, _)) in nodes.iter().enumerate() {
+        let mut components: Vec<&str> = path.split('/').collect();
+        let name = components.pop().unwrap();
+        let directory = components.iter().fold(&mut root, |directory, component| {
+            directory.children.entry(component.to_string()).or_default()
+        });
+        directory.nodes.push((name.to_string(), index));
+    }
+
+    let mut data = vec![0; 96];
+    let mut fragment = vec![];
+    let mut inodes = vec![];
+    let mut directories = vec![];
+    let mut inode_number = 0;
+
+    fn header(inodes: &mut Vec<u8>, kind: u16, inode_number: &mut u32) {
+        *inode_number += 1;
+        inodes.extend(kind.to_le_bytes());
+        inodes.extend(
+            [0o755u16, 0, 0]
+                .iter()
+                .flat_map(|value| value.to_le_bytes()),
+        );
+        inodes.extend(0u32.to_le_bytes());
+        inodes.extend(inode_number.to_le_bytes());
+    }
+
+    // writes the inodes of the directory's entries, then its own, returning
+    // its inode reference
+    let mut write_directory = |directory: &Directory| -> u32 {
+        fn write(
+            directory: &Directory,
+            nodes: &[(&str, SquashfsNode)],
+            compression: u16,
+            state: &mut (
+                &mut Vec<u8>,
+                &mut Vec<u8>,
+                &mut Vec<u8>,
+                &mut Vec<u8>,
+                &mut u32,
+            ),
+        ) -> u32 {
+            let mut entries: Vec<(String, u32, u16)> = directory
+                .children
+                .iter()
+                .map(|(name, child)| (name.clone(), write(child, nodes, compression, state), 1))
+                .collect();
+
+            let (data, fragment, inodes, _, inode_number) = state;
+            for (name, index) in &directory.nodes {
+                let offset = inodes.len() as u32;
+                match nodes[*index].1 {
+                    SquashfsNode::File(contents) => {
+                        let blocks_start = data.len() as u32;
+                        let full = contents.len() / BLOCK_SIZE * BLOCK_SIZE;
+                        let block_sizes: Vec<u32> = contents[..full]
+                            .chunks(BLOCK_SIZE)
+                            .map(|block| {
+                                let compressed = compress(compression, block);
+                                data.extend(&compressed);
+                                compressed.len() as u32
+                            })
+                            .collect();
+                        let fragment_offset = fragment.len() as u32;
+                        fragment.extend(&contents[full..]);
+
+                        header(inodes, 2, inode_number);
+                        inodes.extend(blocks_start.to_le_bytes());
+                        inodes.extend(0u32.to_le_bytes());
+                        inodes.extend(fragment_offset.to_le_bytes());
+                        inodes.extend((contents.len() as u32).to_le_bytes());
+                        inodes.extend(block_sizes.iter().flat_map(|size| size.to_le_bytes()));
+                        entries.push((name.clone(), offset, 2));
+                    }
+                    SquashfsNode::Symlink(target) => {
+                        header(inodes, 3, inode_number);
+                        inodes.extend(1u32.to_le_bytes());
+                        inodes.extend((target.len() as u32).to_le_bytes());
+                        inodes.extend(target.as_bytes());
+                        entries.push((name.clone(), offset, 3));
+                    }
+                }
+            }
+            entries.sort();
+
+            let (_, _, inodes, directories, inode_number) = state;
+            let listing_offset = directories.len() as u16;
+            directories.extend((entries.len() as u32 - 1).to_le_bytes());
+            directories.extend([0u8; 4]);
+            directories.extend([0u8; 4]);
+            for (name, offset, kind) in &entries {
+                directories.extend((*offset as u16).to_le_bytes());
+                directories.extend(0u16.to_le_bytes());
+                directories.extend(kind.to_le_bytes());
+                directories.extend((name.len() as u16 - 1).to_le_bytes());
+                directories.extend(name.as_bytes());
+            }
+            let listing_size = directories.len() as u16 - listing_offset;
+
+            let offset = inodes.len() as u32;
+            header(inodes, 1, inode_number);
+            inodes.extend(0u32.to_le_bytes());
+            inodes.extend(2u32.to_le_bytes());
+            inodes.extend((listing_size + 3).to_le_bytes());
+            inodes.extend(listing_offset.to_le_bytes());
+            inodes.extend(0u32.to_le_bytes());
+
+            offset
+        }
+
+        write(
+            directory,
+            nodes,
+            compression,
+            &mut (
+                &mut data,
+                &mut fragment,
+                &mut inodes,
+                &mut directories,
+                &mut inode_number,
+            ),
+        )
+    };
+    let root_inode = write_directory(&root);
+
+    let fragment_start = data.len() as u64;
+    data.extend(&fragment);
+
+    let inode_table = data.len() as u64;
+    data.extend((inodes.len() as u16 | 0x8000).to_le_bytes());
+    data.extend(&inodes);
+
+    let directory_table = data.len() as u64;
+    let compressed = compress(compression, &directories);
+    data.extend((compressed.len() as u16).to_le_bytes());
+    data.extend(&compressed);
+
+    let fragment_entries = data.len() as u64;
+    data.extend((16u16 | 0x8000).to_le_bytes());
+    data.extend(fragment_start.to_le_bytes());
+    data.extend((fragment.len() as u32 | 1 << 24).to_le_bytes());
+    data.extend(0u32.to_le_bytes());
+
+    let fragment_table = data.len() as u64;
+    data.extend(fragment_entries.to_le_bytes());
+
+    let mut superblock = vec![];
+    superblock.extend(b"hsqs");
+    superblock.extend(inode_number.to_le_bytes());
+    superblock.extend(0u32.to_le_bytes());
+    superblock.extend((BLOCK_SIZE as u32).to_le_bytes());
+    superblock.extend(1u32.to_le_bytes());
+    superblock.extend(
+        [compression, 12, 0, 1, 4, 0]
+            .iter()
+            .flat_map(|value| value.to_le_bytes()),
+    );
+    superblock.extend(u64::from(root_inode).to_le_bytes());
+    superblock.extend((data.len() as u64).to_le_bytes());
+    superblock.extend([u64::MAX; 2].iter().flat_map(|value| value.to_le_bytes()));
+    superblock.extend(inode_table.to_le_bytes());
+    superblock.extend(directory_table.to_le_bytes());
+    superblock.extend(fragment_table.to_le_bytes());
+    superblock.extend(u64::MAX.to_le_bytes());
+    data[..96].copy_from_slice(&superblock);
+
+    // a 64-bit ELF header with the AppImage magic and no section headers,
+    // which puts the image right after it
+    let mut elf = vec![0; 64];
+    elf[..4].copy_from_slice(b"\x7fELF");
+    elf[4] = 2;
+    elf[5] = 1;
+    elf[8..11].copy_from_slice(b"AI\x02");
+    elf[0x28..0x30].copy_from_slice(&64u64.to_le_bytes());
+    elf.extend(data);
+
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    fs::write(path, elf).unwrap();
+}
+
+/// A PNG that doesn't compress well, so it spans several squashfs blocks.
+fn noisy_png(size: u32) -> Vec<u8> {
+    let mut seed = 0x2545_f491u32;
+    let image = RgbaImage::from_fn(size, size, |_, _| {
+        seed ^= seed << 13;
+        seed ^= seed >> 17;
+        seed ^= seed << 5;
+        Rgba(seed.to_le_bytes())
+    });
+
+    let mut bytes = vec![];
+    image
+        .write_to(
+            &mut std::io::Cursor::new(&mut bytes),
+            image::ImageFormat::Png,
+        )
+        .unwrap();
+    bytes
+}
+
+#[test]
+fn it_reads_appimage_icons() {
+    let dir = fixture_dir("appimage");
+    let icon = noisy_png(64);
+    assert!(icon.len() > 4096);
+
+    let app_path = dir.join("Foo-x86_64.AppImage");
+    write_appimage(
+        &app_path,
+        &[
+            (".DirIcon", SquashfsNode::Symlink("usr/share/icons/foo.png")),
+            (
+                "foo.desktop",
+                SquashfsNode::File(b"[Desktop Entry]\nIcon=foo\n"),
+            ),
+            ("usr/share/icons/foo.png", SquashfsNode::File(&icon)),
+        ],
+    );
+    assert_eq!(appimage::read_icon(&app_path).unwrap(), icon);
+
+    let lookup = IconLookup::new(vec![], "hicolor");
+    let image = get_icon_image_with(&lookup, &app_path, IconSize::square(32.0)).unwrap();
+    assert_eq!(image.dimensions(), (32, 32));
+
+    // without a '.DirIcon', the icon the desktop entry names is used
+    let app_path = dir.join("bar");
+    write_appimage(
+        &app_path,
+        &[
+            ("bar.desktop", SquashfsNode::File(b"[Desktop Entry]\nIcon=bar\n")),
+            (
+                "bar.svg",
+                SquashfsNode::File(
+                    br##"<svg viewBox="0 0 8 8"><rect width="8" height="8" fill="#e01b24"/></svg>"##,
+                ),
+            ),
+        ],
+    );
+    assert!(appimage::is_appimage(&app_path));
+
+    let image = get_icon_image_with(&lookup, &app_path, IconSize::square(16.0)).unwrap();
+    assert_eq!(image.get_pixel(8, 8), &Rgba([0xe0, 0x1b, 0x24, 255]));
+
+    let app_path = dir.join("Broken.AppImage");
+    write_file(&app_path, "not an AppImage");
+    assert!(matches!(
+        get_icon_image_with(&lookup, &app_path, IconSize::square(16.0)).unwrap_err(),
+        GetAppIconError::NotAnApplication(path) if path == app_path
+    ));
+}
+
+#[test]
+fn it_reads_xz_and_zstd_appimages() {
+    let dir = fixture_dir("appimage-compression");
+    let icon = noisy_png(64);
+
+    for (compression, name) in [(4, "xz"), (6, "zstd")] {
+        let app_path = dir.join(format!("Foo-{name}.AppImage"));
+        write_appimage_with(
+            &app_path,
+            compression,
+            &[
+                (
+                    "foo.desktop",
+                    SquashfsNode::File(b"[Desktop Entry]\nIcon=foo\n"),
+                ),
+                ("foo.png", SquashfsNode::File(&icon)),
+            ],
+        );
+
+        assert_eq!(appimage::read_icon(&app_path).unwrap(), icon);
+    }
+
+    // nor are other compressions, e.g. lz4
+    let app_path = dir.join("Foo-lz4.AppImage");
+    write_appimage(&app_path, &[(".DirIcon", SquashfsNode::File(b"icon"))]);
+    let mut appimage = fs::read(&app_path).unwrap();
+    appimage[84..86].copy_from_slice(&5u16.to_le_bytes());
+    fs::write(&app_path, appimage).unwrap();
+    assert!(matches!(
+        squashfs::Squashfs::open(fs::File::open(&app_path).unwrap(), 64).err(),
+        Some(squashfs::SquashfsError::UnsupportedCompression(5))
+    ));
+}
+
+#[test]
+fn it_rejects_invalid_squashfs_block_sizes() {
+    let dir = fixture_dir("squashfs-block-size");
+    let app_path = dir.join("Foo.AppImage");
+    write_appimage(&app_path, &[(".DirIcon", SquashfsNode::File(b"icon"))]);
+    let appimage = fs::read(&app_path).unwrap();
+
+    // the image starts after the 64 bytes of the ELF header, with the block
+    // size at 12 and its log2 at 22
+    let open = |block_size: u32, block_log: u16| {
+        let mut corrupted = appimage.clone();
+        corrupted[76..80].copy_from_slice(&block_size.to_le_bytes());
+        corrupted[86..88].copy_from_slice(&block_log.to_le_bytes());
+        fs::write(&app_path, corrupted).unwrap();
+
+        squashfs::Squashfs::open(fs::File::open(&app_path).unwrap(), 64).err()
+    };
+
+    assert!(open(4096, 12).is_none());
+    assert!(open(1 << 20, 20).is_none());
+    for (block_size, block_log) in [(0, 0), (1000, 10), (2048, 11), (1 << 21, 21), (4096, 13)] {
+        assert!(matches!(
+            open(block_size, block_log),
+            Some(squashfs::SquashfsError::InvalidSuperblock)
+        ));
+    }
+
+    // and reading a file of an image with a bad block size fails rather than
+    // dividing by 0
+    open(0, 0);
+    assert!(appimage::read_icon(&app_path).is_err());
+}
+
+#[test]
+fn it_finds_icons_of_flatpak_and_snap_apps() {
+    let dir = fixture_dir("sandboxed-apps");
+    let lookup = IconLookup::new(vec![], "hicolor");
+
+    // a Flatpak app directory exports its desktop entry and icons
+    let app_path = dir.join("flatpak/app/org.example.Foo");
+    let export = app_path.join("current/active/export/share");
+    write_hicolor(&export.join("icons"));
+    write_png(
+        &export.join("icons/hicolor/48x48/apps/org.example.Foo.png"),
+        48,
+    );
+    write_file(
+        &export.join("applications/org.example.Foo.desktop"),
+        "[Desktop Entry]\nName=Foo\nIcon=org.example.Foo\n",
+    );
+
+    let image = get_icon_image_with(&lookup, &app_path, IconSize::square(48.0)).unwrap();
+    assert_eq!(image.dimensions(), (48, 48));
+
+    // a snap refers to its icon relative to its root
+    let app_path = dir.join("snap/bar");
+    let root = app_path.join("current");
+    write_png(&root.join("meta/gui/icon.png"), 64);
+    write_file(
+        &root.join("meta/gui/bar.desktop"),
+        "[Desktop Entry]\nName=Bar\nIcon=${SNAP}/meta/gui/icon.png\n",
+    );
+
+    let image = get_icon_image_with(&lookup, &app_path, IconSize::square(32.0)).unwrap();
+    assert_eq!(image.dimensions(), (32, 32));
+}
+
+#[test]
+fn it_picks_appearance_variants() {
+    let dir = fixture_dir("appearance-variants");
+    let base_dir = dir.join("icons");
+    write_hicolor(&base_dir);
+    for theme in ["Papirus", "Papirus-Dark", "HighContrast"] {
+        write_file(
+            &base_dir.join(theme).join("index.theme"),
+            &format!(
+                "[Icon Theme]\nName={theme}\nInherits=hicolor\nDirectories=48x48/apps\n\n[48x48/apps]\nSize=48\n"
+            ),
+        );
+    }
+    write_png(&base_dir.join("Papirus/48x48/apps/foo.png"), 48);
+    // the dark icon is white, to tell it from the others
+    let dark_icon = base_dir.join("Papirus-Dark/48x48/apps/foo.png");
+    fs::create_dir_all(dark_icon.parent().unwrap()).unwrap();
+    RgbaImage::from_pixel(48, 48, Rgba([255, 255, 255, 255]))
+        .save(dark_icon)
+        .unwrap();
+    write_png(&base_dir.join("hicolor/48x48/apps/foo-symbolic.png"), 48);
+    write_png(&base_dir.join("hicolor/48x48/apps/bar.png"), 48);
+
+    let lookup = IconLookup::new(vec![base_dir.clone()], "Papirus");
+    assert_eq!(
+        lookup.find_icon_variant("foo", 48, 1, IconAppearance::Dark),
+        Some((
+            base_dir.join("Papirus-Dark/48x48/apps/foo.png"),
+            IconAppearance::Dark
+        ))
+    );
+    // the dark theme doesn't have it, and what it inherits isn't a dark variant
+    assert_eq!(
+        lookup.find_icon_variant("bar", 48, 1, IconAppearance::Dark),
+        Some((
+            base_dir.join("hicolor/48x48/apps/bar.png"),
+            IconAppearance::Light
+        ))
+    );
+    assert_eq!(
+        lookup.find_icon_variant("foo", 48, 1, IconAppearance::HighContrast),
+        Some((
+            base_dir.join("Papirus/48x48/apps/foo.png"),
+            IconAppearance::Light
+        ))
+    );
+
+    // the light variant of a dark theme, when asked for
+    let dark_lookup = IconLookup::new(vec![base_dir.clone()], "Papirus-Dark");
+    assert_eq!(
+        dark_lookup.find_icon_variant("foo", 48, 1, IconAppearance::Light),
+        Some((
+            base_dir.join("Papirus/48x48/apps/foo.png"),
+            IconAppearance::Light
+        ))
+    );
+
+    // but without an appearance the configured theme is used as is
+    let app_path = dir.join("foo.desktop");
+    write_file(&app_path, "[Desktop Entry]\nIcon=foo\n");
+    let image = get_icon_image_with(&dark_lookup, &app_path, IconSize::square(48.0)).unwrap();
+    assert_eq!(image.get_pixel(24, 24), &Rgba([255, 255, 255, 255]));
+    let options = IconOptions::default().with_appearance(IconAppearance::Light);
+    let variant =
+        get_icon_variant_with(&dark_lookup, &app_path, IconSize::square(48.0), &options).unwrap();
+    assert_eq!(variant.image.get_pixel(24, 24), &Rgba([0, 0, 0, 0]));
+
+    let options = IconOptions::default().with_appearance(IconAppearance::Tinted);
+    let variant =
+        get_icon_variant_with(&lookup, &app_path, IconSize::square(48.0), &options).unwrap();
+    assert_eq!(variant.appearance, IconAppearance::Tinted);
+    assert_eq!(variant.image.dimensions(), (48, 48));
+}
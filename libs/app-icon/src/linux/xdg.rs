@@ -0,0 +1,61 @@
+use std::{env, path::PathBuf};
+
+pub fn home_dir() -> Option<PathBuf> {
+    env::var_os("HOME")
+        .filter(|home| !home.is_empty())
+        .map(PathBuf::from)
+}
+
+pub fn xdg_dir(var: &str) -> Option<PathBuf> {
+    env::var_os(var)
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+}
+
+/// `$XDG_DATA_HOME`, `~/.local/share` by default.
+pub fn data_home() -> Option<PathBuf> {
+    xdg_dir("XDG_DATA_HOME").or_else(|| home_dir().map(|home| home.join(".local/share")))
+}
+
+/// `$XDG_DATA_DIRS`, `/usr/local/share:/usr/share` by default.
+pub fn data_dirs() -> Vec<PathBuf> {
+    let dirs = env::var("XDG_DATA_DIRS")
+        .ok()
+        .filter(|dirs| !dirs.is_empty())
+        .unwrap_or_else(|| "/usr/local/share:/usr/share".to_string());
+
+    dirs.split(':')
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .collect()
+}
+
+/// The directories Flatpak and Snap export the desktop entries and icons of
+/// sandboxed apps to. They're usually in `$XDG_DATA_DIRS`, but not when the
+/// app was started outside of a desktop session.
+pub fn sandbox_data_dirs() -> Vec<PathBuf> {
+    let mut dirs = vec![];
+
+    if let Some(data_home) = data_home() {
+        dirs.push(data_home.join("flatpak/exports/share"));
+    }
+
+    dirs.push(PathBuf::from("/var/lib/flatpak/exports/share"));
+    dirs.push(PathBuf::from("/var/lib/snapd/desktop"));
+
+    dirs
+}
+
+/// `$XDG_DATA_HOME` and `$XDG_DATA_DIRS`, followed by the sandbox export
+/// directories missing from them.
+pub fn all_data_dirs() -> Vec<PathBuf> {
+    let mut dirs: Vec<PathBuf> = data_home().into_iter().chain(data_dirs()).collect();
+
+    for dir in sandbox_data_dirs() {
+        if !dirs.contains(&dir) {
+            dirs.push(dir);
+        }
+    }
+
+    dirs
+}
@@ -1,111 +1,392 @@
-use std::{ffi::CString, path::Path};
+use std::{
+    ffi::{CStr, CString},
+    fs, io,
+    os::raw::c_char,
+    path::{Path, PathBuf},
+    ptr,
+    sync::{Mutex, PoisonError},
+};
 
 use cocoa::{
     base::{id, nil, NO, YES},
     foundation::{NSInteger, NSPoint, NSRect, NSSize},
 };
+use core_foundation::{
+    base::TCFType,
+    string::{CFString, CFStringRef},
+};
+use image::RgbaImage;
 use objc::{class, msg_send, rc::autoreleasepool, sel, sel_impl};
-use thiserror::Error;
 
-#[repr(u64)]
-#[derive(Clone, Copy, Debug, PartialEq)]
-enum NSBitmapImageFileType {
-    NSBitmapImageFileTypePNG = 4,
-}
+use crate::{
+    bundle, encode, resample, FileType, GetAppIconError, IconAppearance, IconFormat, IconOptions,
+    IconSize, IconVariant,
+};
 
 #[link(name = "AppKit", kind = "framework")]
 extern "C" {
     pub static NSDeviceRGBColorSpace: id;
+    static NSAppearanceNameAqua: id;
+    static NSAppearanceNameDarkAqua: id;
+    static NSAppearanceNameAccessibilityHighContrastAqua: id;
+}
+
+#[link(name = "CoreServices", kind = "framework")]
+extern "C" {
+    static kUTTagClassMIMEType: CFStringRef;
+    fn UTTypeCreatePreferredIdentifierForTag(
+        tag_class: CFStringRef,
+        tag: CFStringRef,
+        conforming_to_uti: CFStringRef,
+    ) -> CFStringRef;
+}
+
+const BITMAP_ERROR: &str = "failed to read the icon bitmap";
+
+/// `path` as a C string for `NSString`, which needs it in UTF-8.
+fn path_cstring(path: &Path) -> Result<CString, GetAppIconError> {
+    path.to_str()
+        .and_then(|path| CString::new(path).ok())
+        .ok_or_else(|| {
+            GetAppIconError::io(
+                path,
+                io::Error::new(io::ErrorKind::InvalidInput, "path is not valid UTF-8"),
+            )
+        })
 }
 
-#[derive(Error, Debug, PartialEq)]
-pub enum GetIconError {
-    #[error("app path does not exist")]
-    AppPathDoesNotExist,
-    #[error("app path does not have '.app' extension")]
-    AppPathDoesNotEndWithApp,
-    #[error("save path parent directory does not exist")]
-    SavePathParentDirDoesNotExist,
-    #[error("failed to convert {0} to &str")]
-    PathConversionError(&'static str),
-    #[error("Failed to create a CString from the path")]
-    CStringCreationError(#[from] std::ffi::NulError),
+/// `text` as a C string for `NSString`, where an interior NUL can't name anything.
+fn id_cstring(text: &str) -> Result<CString, GetAppIconError> {
+    CString::new(text).map_err(|_| GetAppIconError::NotFound(text.to_string()))
 }
 
-pub fn get_icon(app_path: &Path, save_path: &Path, icon_size: f64) -> Result<(), GetIconError> {
+fn check_app_path(app_path: &Path) -> Result<(), GetAppIconError> {
     if !app_path.exists() {
-        return Err(GetIconError::AppPathDoesNotExist);
+        return Err(GetAppIconError::not_found(app_path));
     }
 
     if !app_path.extension().map_or(false, |ext| ext == "app") {
-        return Err(GetIconError::AppPathDoesNotEndWithApp);
+        return Err(GetAppIconError::NotAnApplication(app_path.to_path_buf()));
+    }
+
+    Ok(())
+}
+
+pub(crate) fn is_app_path(app_path: &Path) -> bool {
+    check_app_path(app_path).is_ok()
+}
+
+/// Copies the pixels of an 8-bit RGBA `NSBitmapImageRep`, undoing the alpha premultiplication.
+unsafe fn bitmap_to_image(image_rep: id) -> Result<RgbaImage, GetAppIconError> {
+    let width: NSInteger = msg_send![image_rep, pixelsWide];
+    let height: NSInteger = msg_send![image_rep, pixelsHigh];
+    let bytes_per_row: NSInteger = msg_send![image_rep, bytesPerRow];
+    let data: *const u8 = msg_send![image_rep, bitmapData];
+
+    if data.is_null() {
+        return Err(GetAppIconError::decode(BITMAP_ERROR));
+    }
+
+    let width = usize::try_from(width).map_err(GetAppIconError::decode)?;
+    let height = usize::try_from(height).map_err(GetAppIconError::decode)?;
+    let bytes_per_row = usize::try_from(bytes_per_row).map_err(GetAppIconError::decode)?;
+
+    let mut buf = Vec::with_capacity(width * height * 4);
+
+    for row in 0..height {
+        let row = std::slice::from_raw_parts(data.add(row * bytes_per_row), width * 4);
+        buf.extend_from_slice(row);
     }
 
-    let parent = save_path
-        .parent()
-        .ok_or(GetIconError::SavePathParentDirDoesNotExist)?;
+    for pixel in buf.chunks_exact_mut(4) {
+        let alpha = u16::from(pixel[3]);
+        if alpha > 0 && alpha < 255 {
+            for channel in &mut pixel[..3] {
+                *channel = ((u16::from(*channel) * 255 + alpha / 2) / alpha).min(255) as u8;
+            }
+        }
+    }
+
+    RgbaImage::from_raw(width as u32, height as u32, buf)
+        .ok_or_else(|| GetAppIconError::decode(BITMAP_ERROR))
+}
+
+/// Draws `nsimage` into an `icon_size`×`icon_size` bitmap, letting AppKit pick
+/// the representation that fits the pixel size best.
+unsafe fn render(nsimage: id, icon_size: f64) -> Result<RgbaImage, GetAppIconError> {
+    let () = msg_send![nsimage, setSize: NSSize::new(icon_size, icon_size)];
+
+    let bits_per_sample: NSInteger = 8;
+    let samples_per_pixel: NSInteger = 4;
+    let zero: NSInteger = 0;
+
+    let bitmap_ref: id = msg_send![class!(NSBitmapImageRep), alloc];
+    let image_rep: id = msg_send![bitmap_ref, initWithBitmapDataPlanes:nil pixelsWide:icon_size as NSInteger pixelsHigh:icon_size as NSInteger bitsPerSample:bits_per_sample samplesPerPixel:samples_per_pixel hasAlpha:YES isPlanar:NO colorSpaceName:NSDeviceRGBColorSpace bytesPerRow:zero bitsPerPixel:zero];
+    let () = msg_send![image_rep, setSize: NSSize::new(icon_size, icon_size)];
+
+    let () = msg_send![class!(NSGraphicsContext), saveGraphicsState];
+    let context: id = msg_send![
+      class!(NSGraphicsContext),
+      graphicsContextWithBitmapImageRep: image_rep
+    ];
+    let () = msg_send![class!(NSGraphicsContext), setCurrentContext: context];
+    let () = msg_send![nsimage, drawInRect: NSRect {
+        origin: NSPoint {
+          x: 0.0,
+          y: 0.0,
+        },
+        size: NSSize {
+          width: icon_size,
+          height: icon_size,
+        },
+      } fromRect:NSRect {
+        origin: NSPoint {
+          x: 0.0,
+          y: 0.0,
+        },
+        size: NSSize {
+          width: 0.0,
+          height: 0.0,
+        },
+      } operation:cocoa::appkit::NSCompositingOperation::NSCompositeCopy fraction:1.0];
+
+    let () = msg_send![class!(NSGraphicsContext), restoreGraphicsState];
+    let image = bitmap_to_image(image_rep);
+    let () = msg_send![image_rep, autorelease];
 
-    if !parent.exists() {
-        return Err(GetIconError::SavePathParentDirDoesNotExist);
+    image
+}
+
+/// Like `render`, but draws `nsimage` for `appearance`, which picks the
+/// matching variant of images from asset catalogs and `.icns` files. There
+/// is no `NSAppearance` for tinted icons, which are drawn light.
+unsafe fn render_for(
+    nsimage: id,
+    icon_size: f64,
+    appearance: IconAppearance,
+) -> Result<RgbaImage, GetAppIconError> {
+    let name = match appearance {
+        IconAppearance::Dark => NSAppearanceNameDarkAqua,
+        IconAppearance::HighContrast => NSAppearanceNameAccessibilityHighContrastAqua,
+        IconAppearance::Light | IconAppearance::Tinted => NSAppearanceNameAqua,
+    };
+    let nsappearance: id = msg_send![class!(NSAppearance), appearanceNamed: name];
+
+    let previous: id = msg_send![class!(NSAppearance), currentAppearance];
+    let () = msg_send![class!(NSAppearance), setCurrentAppearance: nsappearance];
+    let image = render(nsimage, icon_size);
+    let () = msg_send![class!(NSAppearance), setCurrentAppearance: previous];
+
+    image
+}
+
+/// The generic application icon rendered at each size it was compared at,
+/// which saves rendering it again for every icon.
+static GENERIC_ICONS: Mutex<Vec<(u32, RgbaImage)>> = Mutex::new(Vec::new());
+
+/// Whether `image`, rendered at `size`, is the generic application icon.
+unsafe fn is_generic_icon(
+    nsworkspace: id,
+    image: &RgbaImage,
+    size: u32,
+) -> Result<bool, GetAppIconError> {
+    let cached = GENERIC_ICONS
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .iter()
+        .find(|(generic_size, _)| *generic_size == size)
+        .map(|(_, generic)| generic == image);
+
+    if let Some(is_generic) = cached {
+        return Ok(is_generic);
     }
 
+    let application_bundle_type = id_cstring("com.apple.application-bundle")?;
+    let nsstring_application_bundle_type: id = msg_send![
+        class!(NSString),
+        stringWithUTF8String: application_bundle_type.as_ptr()
+    ];
+    let generic_nsimage: id =
+        msg_send![nsworkspace, iconForFileType: nsstring_application_bundle_type];
+    let generic = render_for(generic_nsimage, f64::from(size), IconAppearance::Light)?;
+    let is_generic = generic == *image;
+
+    GENERIC_ICONS
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .push((size, generic));
+
+    Ok(is_generic)
+}
+
+pub fn get_icon_image(app_path: &Path, icon_size: IconSize) -> Result<RgbaImage, GetAppIconError> {
+    get_icon_variant(app_path, icon_size, &IconOptions::default()).map(|variant| variant.image)
+}
+
+/// The icon of the app drawn for the appearance of `options`, or for the
+/// current appearance without one. The variant counts as used when it draws
+/// differently from the light icon.
+pub fn get_icon_variant(
+    app_path: &Path,
+    icon_size: IconSize,
+    options: &IconOptions,
+) -> Result<IconVariant, GetAppIconError> {
+    check_app_path(app_path)?;
+
+    let appearance = options.appearance.unwrap_or_default();
+    let source_size = f64::from(icon_size.source_size());
+
     autoreleasepool(|| unsafe {
-        let app_path = app_path
-            .to_str()
-            .ok_or(GetIconError::PathConversionError("app_path"))
-            .map(CString::new)?
-            .map_err(GetIconError::CStringCreationError)?;
-        let save_path = save_path
-            .to_str()
-            .ok_or(GetIconError::PathConversionError("save_path"))
-            .map(CString::new)?
-            .map_err(GetIconError::CStringCreationError)?;
-        let nsstring_app_path: id = msg_send![class!(NSString), stringWithCString: app_path];
-        let nsstring_save_path: id = msg_send![class!(NSString), stringWithCString: save_path];
+        let app_path_cstring = path_cstring(app_path)?;
+        let nsstring_app_path: id = msg_send![
+            class!(NSString),
+            stringWithUTF8String: app_path_cstring.as_ptr()
+        ];
 
         let nsworkspace: id = msg_send![class!(NSWorkspace), sharedWorkspace];
         let nsimage: id = msg_send![nsworkspace, iconForFile: nsstring_app_path];
-        let () = msg_send![nsimage, setSize: NSSize::new(icon_size, icon_size)];
+        let image = match options.appearance {
+            Some(_) => render_for(nsimage, source_size, IconAppearance::Light)?,
+            // as the app draws, e.g. dark in dark mode
+            None => render(nsimage, source_size)?,
+        };
 
-        let bits_per_sample: NSInteger = 8;
-        let samples_per_pixel: NSInteger = 4;
-        let zero: NSInteger = 0;
+        // NSWorkspace hands out the generic application icon when it can't make sense of
+        // the bundle's icon, whose .icns may still be readable on its own
+        if is_generic_icon(nsworkspace, &image, icon_size.source_size()).unwrap_or(false) {
+            if let Ok(variant) = bundle::get_icon_variant(app_path, icon_size, appearance) {
+                return Ok(variant);
+            }
+        }
 
-        let bitmap_ref: id = msg_send![class!(NSBitmapImageRep), alloc];
-        let image_rep: id = msg_send![bitmap_ref, initWithBitmapDataPlanes:nil pixelsWide:icon_size as NSInteger pixelsHigh:icon_size as NSInteger bitsPerSample:bits_per_sample samplesPerPixel:samples_per_pixel hasAlpha:YES isPlanar:NO colorSpaceName:NSDeviceRGBColorSpace bytesPerRow:zero bitsPerPixel:zero];
-        let () = msg_send![image_rep, setSize: NSSize::new(icon_size, icon_size)];
+        let variant = match options.appearance {
+            None | Some(IconAppearance::Light) => None,
+            Some(IconAppearance::Dark | IconAppearance::HighContrast) => {
+                Some(render_for(nsimage, source_size, appearance)?)
+                    .filter(|variant_image| *variant_image != image)
+                    .map(|variant_image| IconVariant {
+                        image: resample::resample(variant_image, &icon_size),
+                        appearance,
+                    })
+            }
+            // the template of the bundle's .icns, as AppKit has no tinted drawing
+            Some(IconAppearance::Tinted) => {
+                bundle::get_icon_variant(app_path, icon_size, IconAppearance::Tinted)
+                    .ok()
+                    .filter(|variant| variant.appearance == IconAppearance::Tinted)
+            }
+        };
 
-        let () = msg_send![class!(NSGraphicsContext), saveGraphicsState];
-        let context: id = msg_send![
-          class!(NSGraphicsContext),
-          graphicsContextWithBitmapImageRep: image_rep
+        Ok(variant.unwrap_or_else(|| IconVariant::light(resample::resample(image, &icon_size))))
+    })
+}
+
+/// Asks Launch Services for the bundle of the application with the bundle id
+/// `app_id` (`com.apple.Safari`), or else with the name `app_id` (`Safari`).
+pub fn find_app_path(app_id: &str) -> Result<PathBuf, GetAppIconError> {
+    autoreleasepool(|| unsafe {
+        let app_id_cstring = id_cstring(app_id)?;
+        let nsstring_app_id: id = msg_send![
+            class!(NSString),
+            stringWithUTF8String: app_id_cstring.as_ptr()
         ];
-        let () = msg_send![class!(NSGraphicsContext), setCurrentContext: context];
-        let () = msg_send![nsimage, drawInRect: NSRect {
-            origin: NSPoint {
-              x: 0.0,
-              y: 0.0,
-            },
-            size: NSSize {
-              width: icon_size,
-              height: icon_size,
-            },
-          } fromRect:NSRect {
-            origin: NSPoint {
-              x: 0.0,
-              y: 0.0,
-            },
-            size: NSSize {
-              width: 0.0,
-              height: 0.0,
-            },
-          } operation:cocoa::appkit::NSCompositingOperation::NSCompositeCopy fraction:1.0];
-
-        let () = msg_send![class!(NSGraphicsContext), restoreGraphicsState];
-        let png_data: id = msg_send![image_rep, representationUsingType:NSBitmapImageFileType::NSBitmapImageFileTypePNG properties:nil];
-        let () = msg_send![png_data, writeToFile:nsstring_save_path atomically:YES];
-        let () = msg_send![image_rep, autorelease];
-
-        Ok(())
+
+        let nsworkspace: id = msg_send![class!(NSWorkspace), sharedWorkspace];
+        let url: id =
+            msg_send![nsworkspace, URLForApplicationWithBundleIdentifier: nsstring_app_id];
+        let nsstring_path: id = if url != nil {
+            msg_send![url, path]
+        } else {
+            msg_send![nsworkspace, fullPathForApplication: nsstring_app_id]
+        };
+
+        if nsstring_path == nil {
+            return Err(GetAppIconError::NotFound(app_id.to_string()));
+        }
+
+        let path: *const c_char = msg_send![nsstring_path, UTF8String];
+        if path.is_null() {
+            return Err(GetAppIconError::NotFound(app_id.to_string()));
+        }
+
+        Ok(PathBuf::from(
+            CStr::from_ptr(path).to_string_lossy().into_owned(),
+        ))
     })
 }
+
+/// The icon Finder shows for a file, or for the files of an extension or MIME type.
+pub fn get_file_type_icon(
+    file_type: FileType,
+    icon_size: IconSize,
+) -> Result<RgbaImage, GetAppIconError> {
+    autoreleasepool(|| unsafe {
+        let nsworkspace: id = msg_send![class!(NSWorkspace), sharedWorkspace];
+
+        let nsimage: id = match file_type {
+            FileType::Path(path) => {
+                if !path.exists() {
+                    return Err(GetAppIconError::not_found(path));
+                }
+
+                let path_cstring = path_cstring(path)?;
+                let nsstring_path: id =
+                    msg_send![class!(NSString), stringWithUTF8String: path_cstring.as_ptr()];
+
+                msg_send![nsworkspace, iconForFile: nsstring_path]
+            }
+            FileType::Extension(extension) => {
+                let extension_cstring = id_cstring(extension.trim_start_matches('.'))?;
+                let nsstring_extension: id = msg_send![
+                    class!(NSString),
+                    stringWithUTF8String: extension_cstring.as_ptr()
+                ];
+
+                msg_send![nsworkspace, iconForFileType: nsstring_extension]
+            }
+            FileType::MimeType(mime_type) => {
+                // iconForFileType: takes a UTI, like the extension
+                let uti = UTTypeCreatePreferredIdentifierForTag(
+                    kUTTagClassMIMEType,
+                    CFString::new(mime_type).as_concrete_TypeRef(),
+                    ptr::null(),
+                );
+                if uti.is_null() {
+                    return Err(GetAppIconError::NotFound(mime_type.to_string()));
+                }
+                let uti = CFString::wrap_under_create_rule(uti);
+
+                msg_send![nsworkspace, iconForFileType: uti.as_concrete_TypeRef() as id]
+            }
+        };
+
+        let image = render(nsimage, f64::from(icon_size.source_size()))?;
+
+        Ok(resample::resample(image, &icon_size))
+    })
+}
+
+pub fn get_icon_bytes(
+    app_path: &Path,
+    icon_size: IconSize,
+    format: IconFormat,
+) -> Result<Vec<u8>, GetAppIconError> {
+    let image = get_icon_image(app_path, icon_size)?;
+
+    encode::encode(&image, format).map_err(GetAppIconError::Encode)
+}
+
+pub fn get_icon(
+    app_path: &Path,
+    save_path: &Path,
+    icon_size: IconSize,
+    format: IconFormat,
+) -> Result<(), GetAppIconError> {
+    check_app_path(app_path)?;
+
+    crate::check_save_path(save_path)?;
+
+    let bytes = get_icon_bytes(app_path, icon_size, format)?;
+
+    fs::write(save_path, bytes).map_err(|error| GetAppIconError::io(save_path, error))
+}
@@ -1,40 +1,108 @@
 #![cfg(test)]
-use super::request::{get_icon, GetIconError};
+use super::request::{
+    find_app_path, get_file_type_icon, get_icon, get_icon_bytes, get_icon_image, get_icon_variant,
+};
+use crate::{FileType, GetAppIconError, IconAppearance, IconFormat, IconOptions, IconSize};
 use std::path::Path;
 
 #[test]
 fn app_path_does_not_exist() {
     let app_path = Path::new("/foo/bar");
     let save_path = Path::new("/tmp");
-    assert_eq!(
-        get_icon(app_path, save_path, 32.0).unwrap_err(),
-        GetIconError::AppPathDoesNotExist
-    );
+    assert!(matches!(
+        get_icon(app_path, save_path, IconSize::square(32.0), IconFormat::Png).unwrap_err(),
+        GetAppIconError::NotFound(path) if path == "/foo/bar"
+    ));
 }
 
 #[test]
 fn app_path_without_app_extension() {
     let app_path = Path::new("/System/Applications");
     let save_path = Path::new("/tmp");
-    assert_eq!(
-        get_icon(app_path, save_path, 32.0).unwrap_err(),
-        GetIconError::AppPathDoesNotEndWithApp
-    );
+    assert!(matches!(
+        get_icon(app_path, save_path, IconSize::square(32.0), IconFormat::Png).unwrap_err(),
+        GetAppIconError::NotAnApplication(path) if path == app_path
+    ));
 }
 
 #[test]
 fn save_path_parent_does_not_exist() {
     let app_path = Path::new("/System/Applications/Notes.app");
     let save_path = Path::new("/foo/Notes.png");
-    assert_eq!(
-        get_icon(app_path, save_path, 32.0).unwrap_err(),
-        GetIconError::SavePathParentDirDoesNotExist
-    );
+    assert!(matches!(
+        get_icon(app_path, save_path, IconSize::square(32.0), IconFormat::Png).unwrap_err(),
+        GetAppIconError::Io { path, .. } if path == Path::new("/foo")
+    ));
 }
 
 #[test]
 fn it_works() {
     let app_path = Path::new("/System/Applications/Notes.app");
     let save_path = Path::new("/tmp/Notes.png");
-    assert!(get_icon(app_path, save_path, 32.0).is_ok());
+    assert!(get_icon(app_path, save_path, IconSize::square(32.0), IconFormat::Png).is_ok());
+}
+
+#[test]
+fn it_returns_image() {
+    let app_path = Path::new("/System/Applications/Notes.app");
+    let image = get_icon_image(app_path, IconSize::square(32.0)).unwrap();
+    assert_eq!(image.dimensions(), (32, 32));
+}
+
+#[test]
+fn it_returns_png_bytes() {
+    let app_path = Path::new("/System/Applications/Notes.app");
+    let bytes = get_icon_bytes(app_path, IconSize::square(32.0), IconFormat::Png).unwrap();
+    assert!(image::load_from_memory_with_format(&bytes, image::ImageFormat::Png).is_ok());
+}
+
+#[test]
+fn it_finds_apps_by_bundle_id() {
+    assert_eq!(
+        find_app_path("com.apple.Notes").unwrap(),
+        Path::new("/System/Applications/Notes.app")
+    );
+}
+
+#[test]
+fn app_not_found() {
+    assert!(matches!(
+        find_app_path("com.example.DoesNotExist").unwrap_err(),
+        GetAppIconError::NotFound(id) if id == "com.example.DoesNotExist"
+    ));
+}
+
+#[test]
+fn it_returns_file_type_icons() {
+    for file_type in [
+        FileType::Path(Path::new("/System/Applications/Notes.app")),
+        FileType::Extension("pdf"),
+        FileType::MimeType("application/pdf"),
+    ] {
+        let image = get_file_type_icon(file_type, IconSize::square(32.0)).unwrap();
+        assert_eq!(image.dimensions(), (32, 32));
+    }
+}
+
+#[test]
+fn file_does_not_exist() {
+    assert!(matches!(
+        get_file_type_icon(FileType::Path(Path::new("/foo/bar")), IconSize::square(32.0))
+            .unwrap_err(),
+        GetAppIconError::NotFound(_)
+    ));
+}
+
+#[test]
+fn it_reports_the_appearance_variant() {
+    let app_path = Path::new("/System/Applications/Notes.app");
+
+    let variant =
+        get_icon_variant(app_path, IconSize::square(64.0), &IconOptions::default()).unwrap();
+    assert_eq!(variant.appearance, IconAppearance::Light);
+    assert_eq!(variant.image.dimensions(), (64, 64));
+
+    let options = IconOptions::default().with_appearance(IconAppearance::Dark);
+    let variant = get_icon_variant(app_path, IconSize::square(64.0), &options).unwrap();
+    assert_eq!(variant.image.dimensions(), (64, 64));
 }
@@ -0,0 +1,168 @@
+//! The colors of an icon, e.g. to tint window chrome or a toast to match the
+//! focused app.
+//!
+//! Colors are counted in buckets of 4 bits per channel, weighted by their
+//! opacity so transparent pixels and antialiased edges barely count. The
+//! swatches are picked from the buckets like Android's Palette does: by how
+//! close their saturation and lightness are to the swatch's, and how many
+//! pixels they cover.
+
+use std::collections::BTreeMap;
+
+use image::RgbaImage;
+use tauri_utils::config::Color;
+
+mod tests;
+
+/// Bits kept per channel to bucket the colors.
+const BUCKET_BITS: u8 = 4;
+
+const WEIGHT_SATURATION: f32 = 3.0;
+const WEIGHT_LIGHTNESS: f32 = 6.0;
+const WEIGHT_POPULATION: f32 = 1.0;
+
+/// The saturation and lightness a swatch aims for, and the ranges a color
+/// must be in to be the swatch.
+struct Target {
+    saturation: (f32, f32, f32),
+    lightness: (f32, f32, f32),
+}
+
+const VIBRANT: Target = Target {
+    saturation: (0.35, 1.0, 1.0),
+    lightness: (0.3, 0.5, 0.7),
+};
+
+const MUTED: Target = Target {
+    saturation: (0.0, 0.3, 0.4),
+    lightness: (0.3, 0.5, 0.7),
+};
+
+/// The colors of an icon, as `tauri::window::Color`s ready for `BorderConfig`
+/// or a toast. A color is `None` when no pixel fits it, e.g. there is no
+/// vibrant color in a grayscale icon and no color at all in a transparent one.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Palette {
+    /// The most common color.
+    pub dominant: Option<Color>,
+    /// A saturated color of medium lightness, for accents.
+    pub vibrant: Option<Color>,
+    /// A desaturated color of medium lightness, for backgrounds.
+    pub muted: Option<Color>,
+    /// The mean of all colors, ignoring transparent pixels.
+    pub average: Option<Color>,
+}
+
+/// A bucket of similar colors, with their sums weighted by opacity.
+#[derive(Debug, Default, Clone, Copy)]
+struct Bucket {
+    sum: [f32; 3],
+    weight: f32,
+}
+
+impl Bucket {
+    fn add(&mut self, rgb: [u8; 3], weight: f32) {
+        for (sum, channel) in self.sum.iter_mut().zip(rgb) {
+            *sum += f32::from(channel) * weight;
+        }
+        self.weight += weight;
+    }
+
+    fn mean(&self) -> [f32; 3] {
+        self.sum.map(|sum| sum / self.weight)
+    }
+}
+
+fn to_color(rgb: [f32; 3]) -> Color {
+    let [red, green, blue] = rgb.map(|channel| channel.round().clamp(0.0, 255.0) as u8);
+
+    Color(red, green, blue, 255)
+}
+
+/// The HSL saturation and lightness of `rgb`, in `0..=1`.
+fn saturation_lightness(rgb: [f32; 3]) -> (f32, f32) {
+    let [red, green, blue] = rgb.map(|channel| channel / 255.0);
+    let max = red.max(green).max(blue);
+    let min = red.min(green).min(blue);
+
+    let lightness = (max + min) / 2.0;
+    let saturation = if max == min {
+        0.0
+    } else {
+        (max - min) / (1.0 - (2.0 * lightness - 1.0).abs())
+    };
+
+    (saturation, lightness)
+}
+
+/// The bucket that best fits `target`, if any is in its ranges.
+fn find_swatch(buckets: &[Bucket], target: &Target) -> Option<Color> {
+    let max_weight = buckets
+        .iter()
+        .map(|bucket| bucket.weight)
+        .fold(0.0, f32::max);
+
+    let score = |bucket: &Bucket| {
+        let (saturation, lightness) = saturation_lightness(bucket.mean());
+
+        let (min_saturation, target_saturation, max_saturation) = target.saturation;
+        let (min_lightness, target_lightness, max_lightness) = target.lightness;
+
+        if !(min_saturation..=max_saturation).contains(&saturation)
+            || !(min_lightness..=max_lightness).contains(&lightness)
+        {
+            return None;
+        }
+
+        Some(
+            WEIGHT_SATURATION * (1.0 - (saturation - target_saturation).abs())
+                + WEIGHT_LIGHTNESS * (1.0 - (lightness - target_lightness).abs())
+                + WEIGHT_POPULATION * bucket.weight / max_weight,
+        )
+    };
+
+    buckets
+        .iter()
+        .filter_map(|bucket| Some((score(bucket)?, bucket)))
+        .max_by(|(a, _), (b, _)| a.total_cmp(b))
+        .map(|(_, bucket)| to_color(bucket.mean()))
+}
+
+/// Computes the palette of `image`.
+pub fn extract(image: &RgbaImage) -> Palette {
+    let shift = 8 - BUCKET_BITS;
+    let mut average = Bucket::default();
+    // ordered, so that ties between buckets always go the same way
+    let mut buckets: BTreeMap<[u8; 3], Bucket> = BTreeMap::new();
+
+    for pixel in image.pixels() {
+        let [red, green, blue, alpha] = pixel.0;
+        if alpha == 0 {
+            continue;
+        }
+
+        let weight = f32::from(alpha) / 255.0;
+        average.add([red, green, blue], weight);
+        buckets
+            .entry([red >> shift, green >> shift, blue >> shift])
+            .or_default()
+            .add([red, green, blue], weight);
+    }
+
+    if average.weight == 0.0 {
+        return Palette::default();
+    }
+
+    let buckets: Vec<Bucket> = buckets.into_values().collect();
+    let dominant = buckets
+        .iter()
+        .max_by(|a, b| a.weight.total_cmp(&b.weight))
+        .map(|bucket| to_color(bucket.mean()));
+
+    Palette {
+        dominant,
+        vibrant: find_swatch(&buckets, &VIBRANT),
+        muted: find_swatch(&buckets, &MUTED),
+        average: Some(to_color(average.mean())),
+    }
+}
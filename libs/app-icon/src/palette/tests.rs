@@ -0,0 +1,58 @@
+#![cfg(test)]
+use image::{Rgba, RgbaImage};
+use tauri_utils::config::Color;
+
+use super::{extract, Palette};
+
+#[test]
+fn it_ignores_transparent_pixels() {
+    assert_eq!(extract(&RgbaImage::new(16, 16)), Palette::default());
+
+    let mut image = RgbaImage::new(16, 16);
+    for x in 0..16 {
+        image.put_pixel(x, 0, Rgba([0, 0, 255, 255]));
+    }
+
+    let palette = extract(&image);
+    assert_eq!(palette.average, Some(Color(0, 0, 255, 255)));
+    assert_eq!(palette.dominant, Some(Color(0, 0, 255, 255)));
+}
+
+#[test]
+fn it_weights_colors_by_opacity() {
+    let mut image = RgbaImage::from_pixel(2, 1, Rgba([255, 255, 255, 255]));
+    image.put_pixel(1, 0, Rgba([0, 0, 0, 85]));
+
+    // three quarters white, one quarter black
+    assert_eq!(extract(&image).average, Some(Color(191, 191, 191, 255)));
+}
+
+#[test]
+fn it_finds_dominant_vibrant_and_muted_colors() {
+    // mostly a dark gray, with a saturated red and a grayish blue
+    let image = RgbaImage::from_fn(10, 10, |x, _| match x {
+        0..=5 => Rgba([40, 40, 40, 255]),
+        6 | 7 => Rgba([220, 30, 30, 255]),
+        _ => Rgba([100, 110, 140, 255]),
+    });
+
+    let palette = extract(&image);
+    assert_eq!(palette.dominant, Some(Color(40, 40, 40, 255)));
+    assert_eq!(palette.vibrant, Some(Color(220, 30, 30, 255)));
+    assert_eq!(palette.muted, Some(Color(100, 110, 140, 255)));
+
+    let grayscale = RgbaImage::from_pixel(4, 4, Rgba([128, 128, 128, 255]));
+    let palette = extract(&grayscale);
+    assert_eq!(palette.vibrant, None);
+    assert_eq!(palette.muted, Some(Color(128, 128, 128, 255)));
+}
+
+#[test]
+fn it_breaks_ties_between_colors_the_same_way() {
+    let mut image = RgbaImage::from_pixel(2, 1, Rgba([0, 0, 255, 255]));
+    image.put_pixel(1, 0, Rgba([255, 0, 0, 255]));
+
+    for _ in 0..8 {
+        assert_eq!(extract(&image).dominant, Some(Color(255, 0, 0, 255)));
+    }
+}
@@ -0,0 +1,342 @@
+//! Reading the icon resources of Windows PE files (`.exe`, `.dll`) without the
+//! Win32 API, so they can be extracted from any OS.
+
+use std::{fs, io, path::Path};
+
+use image::{ImageFormat, RgbaImage};
+use thiserror::Error;
+
+use crate::resample::{self, IconSize};
+
+mod tests;
+
+const RT_ICON: u32 = 3;
+const RT_GROUP_ICON: u32 = 14;
+
+const RESOURCE_DIRECTORY_INDEX: usize = 2;
+
+/// The levels of the resource tree: types, names, then languages.
+const RESOURCE_DIRECTORY_LEVELS: usize = 3;
+
+const PE_EXTENSIONS: &[&str] = &["exe", "dll", "cpl", "ocx", "scr"];
+
+#[derive(Error, Debug)]
+pub enum PeIconError {
+    #[error("failed to read the PE file")]
+    ReadError(#[source] io::Error),
+    #[error("data is not a PE file")]
+    InvalidHeader,
+    #[error("PE file has no icon resource")]
+    NoIcon,
+    #[error("failed to decode the icon resource")]
+    DecodeError,
+}
+
+fn u16_at(bytes: &[u8], offset: usize) -> Option<u16> {
+    Some(u16::from_le_bytes(
+        bytes.get(offset..offset.checked_add(2)?)?.try_into().ok()?,
+    ))
+}
+
+fn u32_at(bytes: &[u8], offset: usize) -> Option<u32> {
+    Some(u32::from_le_bytes(
+        bytes.get(offset..offset.checked_add(4)?)?.try_into().ok()?,
+    ))
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Section {
+    virtual_address: u32,
+    virtual_size: u32,
+    raw_offset: u32,
+    raw_size: u32,
+}
+
+/// The parts of a PE image needed to walk its resources.
+struct PeFile<'a> {
+    bytes: &'a [u8],
+    sections: Vec<Section>,
+    resource_rva: u32,
+}
+
+impl<'a> PeFile<'a> {
+    fn parse(bytes: &'a [u8]) -> Option<Self> {
+        if bytes.get(..2)? != b"MZ" {
+            return None;
+        }
+
+        let pe_offset = u32_at(bytes, 0x3c)? as usize;
+
+        if bytes.get(pe_offset..pe_offset + 4)? != b"PE\0\0" {
+            return None;
+        }
+
+        let coff = pe_offset + 4;
+        let section_count = u16_at(bytes, coff + 2)? as usize;
+        let optional_header_size = u16_at(bytes, coff + 16)? as usize;
+        let optional_header = coff + 20;
+
+        // PE32 and PE32+ only differ in where the data directories start
+        let (rva_count_offset, data_directories_offset) = match u16_at(bytes, optional_header)? {
+            0x10b => (92, 96),
+            0x20b => (108, 112),
+            _ => return None,
+        };
+
+        let rva_count = u32_at(bytes, optional_header + rva_count_offset)? as usize;
+
+        let resource_rva = if rva_count > RESOURCE_DIRECTORY_INDEX {
+            u32_at(
+                bytes,
+                optional_header + data_directories_offset + RESOURCE_DIRECTORY_INDEX * 8,
+            )?
+        } else {
+            0
+        };
+
+        let section_table = optional_header + optional_header_size;
+        let sections = (0..section_count)
+            .map(|index| {
+                let section = section_table + index * 40;
+                Some(Section {
+                    virtual_size: u32_at(bytes, section + 8)?,
+                    virtual_address: u32_at(bytes, section + 12)?,
+                    raw_size: u32_at(bytes, section + 16)?,
+                    raw_offset: u32_at(bytes, section + 20)?,
+                })
+            })
+            .collect::<Option<Vec<_>>>()?;
+
+        Some(Self {
+            bytes,
+            sections,
+            resource_rva,
+        })
+    }
+
+    fn rva_to_offset(&self, rva: u32) -> Option<usize> {
+        self.sections.iter().find_map(|section| {
+            let delta = rva.checked_sub(section.virtual_address)?;
+
+            (delta < section.virtual_size.max(section.raw_size))
+                .then(|| (delta as usize).checked_add(section.raw_offset as usize))
+                .flatten()
+        })
+    }
+
+    fn resource_root(&self) -> Option<usize> {
+        if self.resource_rva == 0 {
+            return None;
+        }
+
+        self.rva_to_offset(self.resource_rva)
+    }
+
+    /// The `(id or name offset, is directory, offset)` entries of the resource
+    /// directory at `directory`, with offsets relative to the resource root.
+    fn directory_entries(&self, root: usize, directory: usize) -> Vec<(u32, bool, usize)> {
+        let Some(header) = root
+            .checked_add(directory)
+            .filter(|header| *header < self.bytes.len())
+        else {
+            return vec![];
+        };
+
+        let count = || -> Option<usize> {
+            Some(
+                u16_at(self.bytes, header + 12)? as usize
+                    + u16_at(self.bytes, header + 14)? as usize,
+            )
+        };
+
+        (0..count().unwrap_or(0))
+            .map_while(|index| {
+                let entry = header + 16 + index * 8;
+                let name = u32_at(self.bytes, entry)?;
+                let offset = u32_at(self.bytes, entry + 4)?;
+
+                Some((
+                    name,
+                    offset & 0x8000_0000 != 0,
+                    (offset & 0x7fff_ffff) as usize,
+                ))
+            })
+            .collect()
+    }
+
+    /// Follows the first language of a resource down to its data, below the
+    /// `path` of directories that led to it. A directory pointing back at one
+    /// of them, or nesting deeper than the languages, makes it `None`.
+    fn resource_data(
+        &self,
+        root: usize,
+        mut offset: usize,
+        mut is_directory: bool,
+        mut path: Vec<usize>,
+    ) -> Option<&'a [u8]> {
+        while is_directory {
+            if path.len() >= RESOURCE_DIRECTORY_LEVELS || path.contains(&offset) {
+                return None;
+            }
+            path.push(offset);
+
+            let (_, next_is_directory, next_offset) =
+                *self.directory_entries(root, offset).first()?;
+            is_directory = next_is_directory;
+            offset = next_offset;
+        }
+
+        let data_entry = root.checked_add(offset)?;
+        let data_rva = u32_at(self.bytes, data_entry)?;
+        let size = u32_at(self.bytes, data_entry + 4)? as usize;
+        let start = self.rva_to_offset(data_rva)?;
+
+        self.bytes.get(start..start.checked_add(size)?)
+    }
+
+    /// The data of every resource of type `kind`, keyed by their ids (names are
+    /// keyed by their offset with the high bit set, as they are in the file).
+    fn resources(&self, kind: u32) -> Vec<(u32, &'a [u8])> {
+        let Some(root) = self.resource_root() else {
+            return vec![];
+        };
+
+        let Some((_, true, type_directory)) = self
+            .directory_entries(root, 0)
+            .into_iter()
+            .find(|(id, _, _)| *id == kind)
+        else {
+            return vec![];
+        };
+
+        // the type directory can't be the root it's listed in
+        if type_directory == 0 {
+            return vec![];
+        }
+
+        self.directory_entries(root, type_directory)
+            .into_iter()
+            .filter_map(|(id, is_directory, offset)| {
+                let path = vec![0, type_directory];
+
+                Some((id, self.resource_data(root, offset, is_directory, path)?))
+            })
+            .collect()
+    }
+}
+
+/// An entry of a `GRPICONDIR`, pointing to an `RT_ICON` resource by id.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct GroupIconEntry {
+    size: u32,
+    bit_count: u16,
+    icon_id: u32,
+}
+
+fn group_icon_entries(group: &[u8]) -> Vec<GroupIconEntry> {
+    let count = u16_at(group, 4).unwrap_or(0) as usize;
+
+    (0..count)
+        .map_while(|index| {
+            let entry = 6 + index * 14;
+            // 0 stands for 256
+            let dimension = |value: u8| if value == 0 { 256 } else { u32::from(value) };
+            let width = dimension(*group.get(entry)?);
+            let height = dimension(*group.get(entry + 1)?);
+
+            Some(GroupIconEntry {
+                size: width.max(height),
+                bit_count: u16_at(group, entry + 6)?,
+                icon_id: u32::from(u16_at(group, entry + 12)?),
+            })
+        })
+        .collect()
+}
+
+/// Wraps an `RT_ICON` resource, which is either a PNG or a DIB without a file
+/// header, into a single image `.ico` that the `image` crate can decode.
+fn decode_icon_resource(data: &[u8], size: u32) -> Option<RgbaImage> {
+    if data.starts_with(b"\x89PNG") {
+        return image::load_from_memory_with_format(data, ImageFormat::Png)
+            .ok()
+            .map(|image| image.into_rgba8());
+    }
+
+    let dimension = if size >= 256 { 0 } else { size as u8 };
+    let bit_count = u16_at(data, 14)?;
+
+    let mut ico = Vec::with_capacity(22 + data.len());
+    ico.extend_from_slice(&[0, 0, 1, 0, 1, 0]);
+    ico.extend_from_slice(&[dimension, dimension, 0, 0]);
+    ico.extend_from_slice(&1u16.to_le_bytes());
+    ico.extend_from_slice(&bit_count.to_le_bytes());
+    ico.extend_from_slice(&(data.len() as u32).to_le_bytes());
+    ico.extend_from_slice(&22u32.to_le_bytes());
+    ico.extend_from_slice(data);
+
+    image::load_from_memory_with_format(&ico, ImageFormat::Ico)
+        .ok()
+        .map(|image| image.into_rgba8())
+}
+
+/// Whether `path` has the extension of a PE file that may carry icon resources.
+pub fn is_pe_path(path: &Path) -> bool {
+    path.is_file()
+        && path.extension().map_or(false, |ext| {
+            PE_EXTENSIONS
+                .iter()
+                .any(|pe_ext| ext.eq_ignore_ascii_case(pe_ext))
+        })
+}
+
+/// Decodes the entry of the first icon group of a PE file closest to `size`
+/// pixels, preferring the smallest one that is at least as large, then the
+/// largest smaller one, and the highest bit depth among equal sizes. Both DIB
+/// and PNG compressed (typically 256px) entries are supported.
+pub fn decode(bytes: &[u8], size: u32) -> Result<RgbaImage, PeIconError> {
+    let pe = PeFile::parse(bytes).ok_or(PeIconError::InvalidHeader)?;
+
+    let (_, group) = pe
+        .resources(RT_GROUP_ICON)
+        .into_iter()
+        .next()
+        .ok_or(PeIconError::NoIcon)?;
+
+    let icons = pe.resources(RT_ICON);
+
+    let mut entries = group_icon_entries(group);
+
+    entries.sort_by_key(|entry| {
+        let distance = if entry.size >= size {
+            (0, entry.size)
+        } else {
+            (1, u32::MAX - entry.size)
+        };
+
+        (distance, u16::MAX - entry.bit_count)
+    });
+
+    if entries.is_empty() {
+        return Err(PeIconError::NoIcon);
+    }
+
+    entries
+        .iter()
+        .filter_map(|entry| {
+            icons
+                .iter()
+                .find(|(id, _)| *id == entry.icon_id)
+                .map(|(_, data)| (entry, *data))
+        })
+        .find_map(|(entry, data)| decode_icon_resource(data, entry.size))
+        .ok_or(PeIconError::DecodeError)
+}
+
+/// Decodes the PE file's icon closest to `size` and resamples it to exactly `size`.
+pub fn get_icon_image(path: &Path, size: IconSize) -> Result<RgbaImage, PeIconError> {
+    let bytes = fs::read(path).map_err(PeIconError::ReadError)?;
+
+    let image = decode(&bytes, size.source_size())?;
+
+    Ok(resample::resample(image, &size))
+}
@@ -0,0 +1,294 @@
+#![cfg(test)]
+use image::{Rgba, RgbaImage};
+
+use super::{decode, PeIconError, RT_GROUP_ICON, RT_ICON};
+use crate::encode;
+
+const SECTION_RVA: u32 = 0x1000;
+const SECTION_OFFSET: usize = 0x200;
+
+fn png_icon(size: u32, color: [u8; 4]) -> Vec<u8> {
+    encode::png(&RgbaImage::from_pixel(size, size, Rgba(color))).unwrap()
+}
+
+/// A 32-bit DIB as stored in `RT_ICON`: a `BITMAPINFOHEADER` with the height
+/// doubled, bottom-up BGRA rows, then the 1-bit AND mask.
+fn dib_icon(size: u32, color: [u8; 4]) -> Vec<u8> {
+    let mut dib = Vec::new();
+    dib.extend_from_slice(&40u32.to_le_bytes());
+    dib.extend_from_slice(&(size as i32).to_le_bytes());
+    dib.extend_from_slice(&(size as i32 * 2).to_le_bytes());
+    dib.extend_from_slice(&1u16.to_le_bytes());
+    dib.extend_from_slice(&32u16.to_le_bytes());
+    dib.extend_from_slice(&[0; 24]);
+
+    let [r, g, b, a] = color;
+    for _ in 0..size * size {
+        dib.extend_from_slice(&[b, g, r, a]);
+    }
+
+    let mask_row = (size as usize).div_ceil(32) * 4;
+    dib.extend(std::iter::repeat(0).take(mask_row * size as usize));
+    dib
+}
+
+fn group_icon(entries: &[(u32, u16, u16)]) -> Vec<u8> {
+    let mut group = vec![0, 0, 1, 0];
+    group.extend_from_slice(&(entries.len() as u16).to_le_bytes());
+
+    for (size, bit_count, id) in entries {
+        let dimension = if *size >= 256 { 0 } else { *size as u8 };
+        group.extend_from_slice(&[dimension, dimension, 0, 0]);
+        group.extend_from_slice(&1u16.to_le_bytes());
+        group.extend_from_slice(&bit_count.to_le_bytes());
+        group.extend_from_slice(&0u32.to_le_bytes());
+        group.extend_from_slice(&id.to_le_bytes());
+    }
+
+    group
+}
+
+fn directory(entries: &[(u32, u32)]) -> Vec<u8> {
+    let mut directory = vec![0; 12];
+    directory.extend_from_slice(&0u16.to_le_bytes());
+    directory.extend_from_slice(&(entries.len() as u16).to_le_bytes());
+
+    for (id, offset) in entries {
+        directory.extend_from_slice(&id.to_le_bytes());
+        directory.extend_from_slice(&offset.to_le_bytes());
+    }
+
+    directory
+}
+
+/// A `.rsrc` section holding `resources` as `(type, id, data)`, with a single
+/// language per resource.
+fn resource_section(resources: &[(u32, u16, Vec<u8>)]) -> Vec<u8> {
+    let mut types: Vec<u32> = resources.iter().map(|(kind, _, _)| *kind).collect();
+    types.sort();
+    types.dedup();
+
+    let dir_len = |count: usize| 16 + count * 8;
+
+    // lay out: root, type directories, language directories, data entries, data
+    let root_len = dir_len(types.len());
+    let mut type_offsets = vec![];
+    let mut offset = root_len;
+    for kind in &types {
+        type_offsets.push(offset);
+        offset += dir_len(resources.iter().filter(|(k, _, _)| k == kind).count());
+    }
+
+    let language_offset = offset;
+    let data_entry_offset = language_offset + resources.len() * dir_len(1);
+    let mut data_offset = data_entry_offset + resources.len() * 16;
+
+    let ordered: Vec<&(u32, u16, Vec<u8>)> = types
+        .iter()
+        .flat_map(|kind| resources.iter().filter(move |(k, _, _)| k == kind))
+        .collect();
+
+    let mut section = directory(
+        &types
+            .iter()
+            .zip(&type_offsets)
+            .map(|(kind, offset)| (*kind, *offset as u32 | 0x8000_0000))
+            .collect::<Vec<_>>(),
+    );
+
+    for kind in &types {
+        let entries: Vec<(u32, u32)> = ordered
+            .iter()
+            .enumerate()
+            .filter(|(_, (k, _, _))| k == kind)
+            .map(|(i, (_, id, _))| {
+                (
+                    u32::from(*id),
+                    (language_offset + i * dir_len(1)) as u32 | 0x8000_0000,
+                )
+            })
+            .collect();
+        section.extend(directory(&entries));
+    }
+
+    for i in 0..ordered.len() {
+        section.extend(directory(&[(0x409, (data_entry_offset + i * 16) as u32)]));
+    }
+
+    for (_, _, data) in &ordered {
+        section.extend_from_slice(&(SECTION_RVA + data_offset as u32).to_le_bytes());
+        section.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        section.extend_from_slice(&[0; 8]);
+        data_offset += data.len();
+    }
+
+    for (_, _, data) in &ordered {
+        section.extend_from_slice(data);
+    }
+
+    section
+}
+
+/// A minimal PE32+ image whose only section is `.rsrc`.
+fn pe_file(resources: &[(u32, u16, Vec<u8>)]) -> Vec<u8> {
+    let section = resource_section(resources);
+
+    let mut pe = vec![0; SECTION_OFFSET];
+    pe[..2].copy_from_slice(b"MZ");
+    pe[0x3c..0x40].copy_from_slice(&0x40u32.to_le_bytes());
+
+    pe[0x40..0x44].copy_from_slice(b"PE\0\0");
+    let coff = 0x44;
+    pe[coff..coff + 2].copy_from_slice(&0x8664u16.to_le_bytes());
+    pe[coff + 2..coff + 4].copy_from_slice(&1u16.to_le_bytes());
+    pe[coff + 16..coff + 18].copy_from_slice(&240u16.to_le_bytes());
+
+    let optional_header = coff + 20;
+    pe[optional_header..optional_header + 2].copy_from_slice(&0x20bu16.to_le_bytes());
+    pe[optional_header + 108..optional_header + 112].copy_from_slice(&16u32.to_le_bytes());
+    let resource_directory = optional_header + 112 + 2 * 8;
+    pe[resource_directory..resource_directory + 4].copy_from_slice(&SECTION_RVA.to_le_bytes());
+    pe[resource_directory + 4..resource_directory + 8]
+        .copy_from_slice(&(section.len() as u32).to_le_bytes());
+
+    let section_header = optional_header + 240;
+    pe[section_header..section_header + 5].copy_from_slice(b".rsrc");
+    pe[section_header + 8..section_header + 12]
+        .copy_from_slice(&(section.len() as u32).to_le_bytes());
+    pe[section_header + 12..section_header + 16].copy_from_slice(&SECTION_RVA.to_le_bytes());
+    pe[section_header + 16..section_header + 20]
+        .copy_from_slice(&(section.len() as u32).to_le_bytes());
+    pe[section_header + 20..section_header + 24]
+        .copy_from_slice(&(SECTION_OFFSET as u32).to_le_bytes());
+
+    pe.extend(section);
+    pe
+}
+
+fn sample_pe() -> Vec<u8> {
+    pe_file(&[
+        (RT_ICON, 1, dib_icon(16, [255, 0, 0, 255])),
+        (RT_ICON, 2, dib_icon(32, [0, 255, 0, 255])),
+        (RT_ICON, 3, png_icon(256, [0, 0, 255, 255])),
+        (
+            RT_GROUP_ICON,
+            1,
+            group_icon(&[(16, 32, 1), (32, 32, 2), (256, 32, 3)]),
+        ),
+    ])
+}
+
+#[test]
+fn it_rejects_invalid_header() {
+    assert!(matches!(
+        decode(b"not a PE", 16),
+        Err(PeIconError::InvalidHeader)
+    ));
+}
+
+#[test]
+fn it_rejects_pe_without_icon() {
+    let pe = pe_file(&[(RT_ICON, 1, dib_icon(16, [255, 0, 0, 255]))]);
+
+    assert!(matches!(decode(&pe, 16), Err(PeIconError::NoIcon)));
+}
+
+#[test]
+fn it_decodes_dib_entries() {
+    let image = decode(&sample_pe(), 16).unwrap();
+
+    assert_eq!(image.dimensions(), (16, 16));
+    assert_eq!(image.get_pixel(3, 3), &Rgba([255, 0, 0, 255]));
+}
+
+#[test]
+fn it_picks_closest_size() {
+    let pe = sample_pe();
+
+    assert_eq!(decode(&pe, 24).unwrap().dimensions(), (32, 32));
+    assert_eq!(decode(&pe, 48).unwrap().dimensions(), (256, 256));
+    assert_eq!(decode(&pe, 512).unwrap().dimensions(), (256, 256));
+}
+
+#[test]
+fn it_decodes_png_entries() {
+    let image = decode(&sample_pe(), 256).unwrap();
+
+    assert_eq!(image.get_pixel(0, 0), &Rgba([0, 0, 255, 255]));
+}
+
+#[test]
+fn it_prefers_higher_bit_depth() {
+    let pe = pe_file(&[
+        (RT_ICON, 1, dib_icon(32, [255, 0, 0, 255])),
+        (RT_ICON, 2, dib_icon(32, [0, 255, 0, 255])),
+        (RT_GROUP_ICON, 1, group_icon(&[(32, 8, 1), (32, 32, 2)])),
+    ]);
+
+    assert_eq!(
+        decode(&pe, 32).unwrap().get_pixel(0, 0),
+        &Rgba([0, 255, 0, 255])
+    );
+}
+
+/// Overwrites the 32-bit field at `offset` of a PE file.
+fn set_u32(pe: &mut [u8], offset: usize, value: u32) {
+    pe[offset..offset + 4].copy_from_slice(&value.to_le_bytes());
+}
+
+#[test]
+fn it_stops_at_cyclic_resource_directories() {
+    let icon = || pe_file(&[(RT_GROUP_ICON, 1, group_icon(&[(16, 32, 1)]))]);
+    // the root and the type directory are 24 bytes each, then comes the
+    // language directory, whose entry points at its data at 16 bytes in
+    let language_entry = SECTION_OFFSET + 48 + 16 + 4;
+    // the group is found, but not the icon it lists
+    assert!(matches!(decode(&icon(), 16), Err(PeIconError::DecodeError)));
+
+    // a language directory listing itself
+    let mut pe = icon();
+    set_u32(&mut pe, language_entry, 48 | 0x8000_0000);
+    assert!(matches!(decode(&pe, 16), Err(PeIconError::NoIcon)));
+
+    // a language directory listing the type directory above it
+    let mut pe = icon();
+    set_u32(&mut pe, language_entry, 24 | 0x8000_0000);
+    assert!(matches!(decode(&pe, 16), Err(PeIconError::NoIcon)));
+
+    // a type directory pointing back at the root
+    let mut pe = icon();
+    set_u32(&mut pe, SECTION_OFFSET + 16 + 4, 0x8000_0000);
+    assert!(matches!(decode(&pe, 16), Err(PeIconError::NoIcon)));
+}
+
+#[test]
+fn it_rejects_overflowing_section_offsets() {
+    let mut pe = sample_pe();
+    // the `PointerToRawData` of the `.rsrc` section header
+    set_u32(&mut pe, 0x58 + 240 + 20, 0xffff_fff0);
+
+    assert!(matches!(decode(&pe, 16), Err(PeIconError::NoIcon)));
+}
+
+#[test]
+fn it_survives_corrupted_files() {
+    let pe = pe_file(&[
+        (RT_ICON, 1, dib_icon(16, [255, 0, 0, 255])),
+        (RT_GROUP_ICON, 1, group_icon(&[(16, 32, 1)])),
+    ]);
+    // the headers, the resource directories and the data entries
+    let end = SECTION_OFFSET + 160;
+
+    for offset in 0..end {
+        for value in [0x00, 0x7f, 0x80, 0xff] {
+            let mut corrupted = pe.clone();
+            corrupted[offset] = value;
+
+            let _ = decode(&corrupted, 16);
+        }
+    }
+
+    for len in 0..end {
+        let _ = decode(&pe[..len], 16);
+    }
+}
@@ -0,0 +1,109 @@
+//! A Tauri plugin registering the `appicon://` URI scheme, which serves app
+//! icons to the webview through an [`IconProtocol`]:
+//!
+//! ```no_run
+//! tauri::Builder::default()
+//!     .plugin(app_icon::plugin::init())
+//! # ;
+//! ```
+//!
+//! ```html
+//! <img src="appicon://localhost/com.apple.Safari?size=64&scale=2">
+//! ```
+//!
+//! Windows and Android webviews reach custom schemes through
+//! `http://appicon.localhost/`, which `convertFileSrc(id, "appicon")` of
+//! `@tauri-apps/api/core` builds on every platform.
+
+use std::time::Duration;
+
+use tauri::{
+    plugin::{Builder as PluginBuilder, TauriPlugin},
+    Manager, Runtime,
+};
+
+use crate::{cache::IconCache, protocol::IconProtocol};
+
+const DEFAULT_SCHEME: &str = "appicon";
+
+/// Configures the plugin, see [`init`] for the defaults.
+#[derive(Debug, Clone)]
+pub struct Builder {
+    scheme: String,
+    cache: Option<IconCache>,
+    max_age: Option<Duration>,
+}
+
+impl Default for Builder {
+    fn default() -> Self {
+        Self {
+            scheme: DEFAULT_SCHEME.to_string(),
+            cache: None,
+            max_age: None,
+        }
+    }
+}
+
+impl Builder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Serves the icons on `scheme://` instead of `appicon://`.
+    pub fn with_scheme(mut self, scheme: impl Into<String>) -> Self {
+        self.scheme = scheme.into();
+        self
+    }
+
+    /// Keeps the icons in `cache` instead of the `app-icon` directory of the
+    /// app's cache directory.
+    pub fn with_cache(mut self, cache: IconCache) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    /// See [`IconProtocol::with_max_age`].
+    pub fn with_max_age(mut self, max_age: Duration) -> Self {
+        self.max_age = Some(max_age);
+        self
+    }
+
+    pub fn build<R: Runtime>(self) -> TauriPlugin<R> {
+        let Self {
+            scheme,
+            cache,
+            max_age,
+        } = self;
+
+        PluginBuilder::new("app-icon")
+            .setup(move |app, _api| {
+                let cache = match cache {
+                    Some(cache) => cache,
+                    None => IconCache::new(app.path().app_cache_dir()?.join("app-icon")),
+                };
+                let protocol = IconProtocol::new(cache);
+
+                app.manage(match max_age {
+                    Some(max_age) => protocol.with_max_age(max_age),
+                    None => protocol,
+                });
+
+                Ok(())
+            })
+            .register_asynchronous_uri_scheme_protocol(scheme, |ctx, request, responder| {
+                let protocol = ctx.app_handle().state::<IconProtocol>().inner().clone();
+
+                // extracting an icon blocks, and the handler runs on the main thread
+                tauri::async_runtime::spawn_blocking(move || {
+                    responder.respond(protocol.handle(&request));
+                });
+            })
+            .build()
+    }
+}
+
+/// The plugin serving icons on `appicon://`, cached in the app's cache
+/// directory and kept by the webview for an hour.
+pub fn init<R: Runtime>() -> TauriPlugin<R> {
+    Builder::new().build()
+}
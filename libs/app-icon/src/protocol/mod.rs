@@ -0,0 +1,308 @@
+//! Serving icons over a URI scheme, so the webview loads them with a plain
+//! `<img src="appicon://localhost/com.apple.Safari?size=64">` instead of
+//! base64 strings sent through commands. The `plugin` feature registers the
+//! handler with Tauri, see [`crate::plugin`].
+//!
+//! The path of the URI is the percent-encoded app id, or absolute app path,
+//! given to [`crate::find_app_path`]. Paths to anything but an application
+//! are answered like missing apps, and no `Access-Control-Allow-Origin` is
+//! sent, so pages can't use the scheme to look at other files on the disk.
+//! The query takes:
+//!
+//! - `size`, in points, 32 by default
+//! - `scale`, the HiDPI scale factor, 1 by default
+//! - `appearance`, `light`, `dark`, `high-contrast` or `tinted`
+//! - `format`, `png` (the default), `webp`, `jpeg`, `ico` or `icns`
+//!
+//! Other parameters are ignored, e.g. to bust the webview cache.
+
+use std::{path::Path, time::Duration};
+
+use http::{
+    header::{self, HeaderValue},
+    Method, Request, Response, StatusCode, Uri,
+};
+use thiserror::Error;
+
+use crate::{
+    cache::IconCache, find_app_path, is_app_path, GetAppIconError, IconAppearance, IconFormat,
+    IconOptions, IconSize,
+};
+
+mod tests;
+
+const DEFAULT_SIZE: f64 = 32.0;
+/// The largest icon served, which keeps a typo from rendering a huge image.
+const MAX_PIXELS: f64 = 2048.0;
+const JPEG_QUALITY: u8 = 90;
+/// How long the webview may use an icon without asking again, 1 hour by default.
+const DEFAULT_MAX_AGE: Duration = Duration::from_secs(60 * 60);
+
+#[derive(Error, Debug, PartialEq)]
+pub enum ParseIconUriError {
+    #[error("the URI has no app id")]
+    MissingAppId,
+    #[error("the URI is not percent-encoded correctly")]
+    InvalidEncoding,
+    #[error("invalid `{0}` parameter")]
+    InvalidParameter(&'static str),
+}
+
+/// The icon a URI asks for.
+#[derive(Debug, Clone, PartialEq)]
+pub struct IconQuery {
+    /// The app id or absolute path of the app.
+    pub app: String,
+    pub size: IconSize,
+    pub options: IconOptions,
+    pub format: IconFormat,
+}
+
+fn hex_value(byte: u8) -> Option<u8> {
+    match byte {
+        b'0'..=b'9' => Some(byte - b'0'),
+        b'a'..=b'f' => Some(byte - b'a' + 10),
+        b'A'..=b'F' => Some(byte - b'A' + 10),
+        _ => None,
+    }
+}
+
+/// Decodes the `%XX` escapes of `text`, and `+` as a space in query strings.
+fn percent_decode(text: &str, plus_as_space: bool) -> Result<String, ParseIconUriError> {
+    let mut bytes = Vec::with_capacity(text.len());
+    let mut iter = text.bytes();
+
+    while let Some(byte) = iter.next() {
+        match byte {
+            b'%' => {
+                let high = iter.next().and_then(hex_value);
+                let low = iter.next().and_then(hex_value);
+                let (Some(high), Some(low)) = (high, low) else {
+                    return Err(ParseIconUriError::InvalidEncoding);
+                };
+                bytes.push(high << 4 | low);
+            }
+            b'+' if plus_as_space => bytes.push(b' '),
+            byte => bytes.push(byte),
+        }
+    }
+
+    String::from_utf8(bytes).map_err(|_| ParseIconUriError::InvalidEncoding)
+}
+
+fn parse_number(value: &str, name: &'static str) -> Result<f64, ParseIconUriError> {
+    value
+        .parse::<f64>()
+        .ok()
+        .filter(|number| number.is_finite() && *number > 0.0)
+        .ok_or(ParseIconUriError::InvalidParameter(name))
+}
+
+fn parse_appearance(value: &str) -> Result<IconAppearance, ParseIconUriError> {
+    match value {
+        "light" => Ok(IconAppearance::Light),
+        "dark" => Ok(IconAppearance::Dark),
+        "high-contrast" => Ok(IconAppearance::HighContrast),
+        "tinted" => Ok(IconAppearance::Tinted),
+        _ => Err(ParseIconUriError::InvalidParameter("appearance")),
+    }
+}
+
+fn parse_format(value: &str) -> Result<IconFormat, ParseIconUriError> {
+    match value {
+        "png" => Ok(IconFormat::Png),
+        "webp" => Ok(IconFormat::WebP),
+        "jpeg" | "jpg" => Ok(IconFormat::Jpeg {
+            quality: JPEG_QUALITY,
+            background: [255, 255, 255],
+        }),
+        "ico" => Ok(IconFormat::Ico),
+        "icns" => Ok(IconFormat::Icns),
+        _ => Err(ParseIconUriError::InvalidParameter("format")),
+    }
+}
+
+impl IconQuery {
+    /// Reads the icon asked for by `uri`, e.g.
+    /// `appicon://localhost/com.apple.Safari?size=64&scale=2`. Only the path
+    /// and query are read, so `http://appicon.localhost/...` URIs, which
+    /// Windows and Android webviews use for custom schemes, work the same.
+    pub fn parse(uri: &Uri) -> Result<Self, ParseIconUriError> {
+        let app = percent_decode(uri.path().trim_start_matches('/'), false)?;
+
+        if app.is_empty() {
+            return Err(ParseIconUriError::MissingAppId);
+        }
+
+        let mut size = DEFAULT_SIZE;
+        let mut scale = 1.0;
+        let mut options = IconOptions::default();
+        let mut format = IconFormat::Png;
+
+        for pair in uri.query().unwrap_or_default().split('&') {
+            let (name, value) = pair.split_once('=').unwrap_or((pair, ""));
+            let value = percent_decode(value, true)?;
+
+            match name {
+                "size" => size = parse_number(&value, "size")?,
+                "scale" => scale = parse_number(&value, "scale")?,
+                "appearance" => options = options.with_appearance(parse_appearance(&value)?),
+                "format" => format = parse_format(&value)?,
+                _ => {}
+            }
+        }
+
+        if size * scale > MAX_PIXELS {
+            return Err(ParseIconUriError::InvalidParameter("size"));
+        }
+
+        Ok(Self {
+            app,
+            size: IconSize::square(size).with_scale(scale),
+            options,
+            format,
+        })
+    }
+}
+
+/// Answers requests of an icon URI scheme from an [`IconCache`], with the
+/// `Content-Type`, `Cache-Control` and `ETag` headers set so the webview
+/// keeps the icons and revalidates them once they expire.
+#[derive(Debug, Clone)]
+pub struct IconProtocol {
+    cache: IconCache,
+    max_age: Duration,
+}
+
+fn set_header(response: &mut Response<Vec<u8>>, name: header::HeaderName, value: &str) {
+    if let Ok(value) = HeaderValue::from_str(value) {
+        response.headers_mut().insert(name, value);
+    }
+}
+
+fn error_response(status: StatusCode, message: &str) -> Response<Vec<u8>> {
+    let mut response = Response::new(message.as_bytes().to_vec());
+    *response.status_mut() = status;
+    set_header(
+        &mut response,
+        header::CONTENT_TYPE,
+        "text/plain; charset=utf-8",
+    );
+    set_header(&mut response, header::CACHE_CONTROL, "no-store");
+
+    response
+}
+
+/// The one answer to an app that can't be found, isn't an application or has
+/// no icon.
+fn not_found_response(app: &str) -> Response<Vec<u8>> {
+    error_response(StatusCode::NOT_FOUND, &format!("no icon found for '{app}'"))
+}
+
+/// Answers a missing app or icon with a 404, and a failure to get the icon of
+/// an app with a 500.
+fn icon_error_response(app: &str, error: &GetAppIconError) -> Response<Vec<u8>> {
+    match error {
+        GetAppIconError::NotFound(_)
+        | GetAppIconError::NotAnApplication(_)
+        | GetAppIconError::NoIcon(_) => not_found_response(app),
+        _ => error_response(StatusCode::INTERNAL_SERVER_ERROR, &error.to_string()),
+    }
+}
+
+/// Whether the `If-None-Match` header of `request` lists `etag`.
+fn is_fresh<T>(request: &Request<T>, etag: &str) -> bool {
+    request
+        .headers()
+        .get_all(header::IF_NONE_MATCH)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(|tag| tag.trim().trim_start_matches("W/"))
+        .any(|tag| tag == "*" || tag == etag)
+}
+
+impl IconProtocol {
+    /// Answers with icons from `cache`, which the webview may keep for an hour.
+    pub fn new(cache: IconCache) -> Self {
+        Self {
+            cache,
+            max_age: DEFAULT_MAX_AGE,
+        }
+    }
+
+    /// How long the webview may use an icon before asking for it again, which
+    /// is how long an updated app may show its old icon.
+    pub fn with_max_age(mut self, max_age: Duration) -> Self {
+        self.max_age = max_age;
+        self
+    }
+
+    /// Answers `request`, extracting and caching the icon it asks for. This
+    /// blocks while the icon is extracted, so call it off the main thread.
+    pub fn handle<T>(&self, request: &Request<T>) -> Response<Vec<u8>> {
+        let head = request.method() == Method::HEAD;
+
+        if request.method() != Method::GET && !head {
+            let mut response = error_response(StatusCode::METHOD_NOT_ALLOWED, "");
+            set_header(&mut response, header::ALLOW, "GET, HEAD");
+            return response;
+        }
+
+        let query = match IconQuery::parse(request.uri()) {
+            Ok(query) => query,
+            Err(error) => return error_response(StatusCode::BAD_REQUEST, &error.to_string()),
+        };
+
+        // any page in the webview can make these requests, so only apps are
+        // looked up, and a path that is not one is as missing as a path that
+        // doesn't exist: nothing tells whether a file is on the disk
+        let app_path = match find_app_path(&query.app) {
+            Ok(app_path) if is_app_path(&app_path) => app_path,
+            _ => return not_found_response(&query.app),
+        };
+
+        // the name of the cache entry changes with the app, the size and the
+        // format, which makes it a strong validator of the icon, and without
+        // an entry the icon is served uncached
+        let (etag, bytes) =
+            match self
+                .cache
+                .lookup(&app_path, query.size, query.options, query.format)
+            {
+                Ok((path, bytes)) => (path.ok().and_then(|path| entry_tag(&path)), bytes),
+                Err(error) => return icon_error_response(&query.app, &error),
+            };
+
+        let fresh = etag
+            .as_deref()
+            .map_or(false, |etag| is_fresh(request, etag));
+        let length = bytes.len();
+        let mut response = Response::new(if head || fresh { Vec::new() } else { bytes });
+
+        if fresh {
+            *response.status_mut() = StatusCode::NOT_MODIFIED;
+        } else {
+            set_header(
+                &mut response,
+                header::CONTENT_TYPE,
+                query.format.mime_type(),
+            );
+            set_header(&mut response, header::CONTENT_LENGTH, &length.to_string());
+        }
+
+        let cache_control = format!("private, max-age={}", self.max_age.as_secs());
+        set_header(&mut response, header::CACHE_CONTROL, &cache_control);
+        if let Some(etag) = etag {
+            set_header(&mut response, header::ETAG, &etag);
+        }
+
+        response
+    }
+}
+
+fn entry_tag(path: &Path) -> Option<String> {
+    let stem = path.file_stem()?.to_str()?;
+
+    Some(format!("\"{stem}\""))
+}
@@ -0,0 +1,163 @@
+#![cfg(test)]
+use http::{header, Method, Request, StatusCode, Uri};
+
+use super::{IconProtocol, IconQuery, ParseIconUriError};
+use crate::{cache::IconCache, fixture_dir, IconAppearance, IconFormat, IconOptions, IconSize};
+
+fn parse(uri: &str) -> Result<IconQuery, ParseIconUriError> {
+    IconQuery::parse(&uri.parse::<Uri>().unwrap())
+}
+
+#[test]
+fn it_parses_icon_uris() {
+    assert_eq!(
+        parse("appicon://localhost/com.apple.Safari").unwrap(),
+        IconQuery {
+            app: "com.apple.Safari".to_string(),
+            size: IconSize::square(32.0),
+            options: IconOptions::default(),
+            format: IconFormat::Png,
+        }
+    );
+
+    let query = parse(
+        "http://appicon.localhost/%2FApplications%2FNotes%20App.app?size=64&scale=2&appearance=dark&format=webp&v=3",
+    )
+    .unwrap();
+    assert_eq!(query.app, "/Applications/Notes App.app");
+    assert_eq!(query.size, IconSize::square(64.0).with_scale(2.0));
+    assert_eq!(query.options.appearance, Some(IconAppearance::Dark));
+    assert_eq!(query.format, IconFormat::WebP);
+
+    assert_eq!(
+        parse("appicon://localhost/?size=64"),
+        Err(ParseIconUriError::MissingAppId)
+    );
+    assert_eq!(
+        parse("appicon://localhost/foo%2"),
+        Err(ParseIconUriError::InvalidEncoding)
+    );
+    for (uri, parameter) in [
+        ("appicon://localhost/foo?size=-1", "size"),
+        ("appicon://localhost/foo?size=4096", "size"),
+        ("appicon://localhost/foo?scale=abc", "scale"),
+        ("appicon://localhost/foo?appearance=blue", "appearance"),
+        ("appicon://localhost/foo?format=gif", "format"),
+    ] {
+        assert_eq!(
+            parse(uri),
+            Err(ParseIconUriError::InvalidParameter(parameter))
+        );
+    }
+}
+
+#[test]
+fn it_answers_bad_requests_with_errors() {
+    let protocol = IconProtocol::new(IconCache::new(fixture_dir("protocol-errors")));
+    let request =
+        |method: Method, uri: &str| Request::builder().method(method).uri(uri).body(()).unwrap();
+
+    let response = protocol.handle(&request(Method::POST, "appicon://localhost/foo"));
+    assert_eq!(response.status(), StatusCode::METHOD_NOT_ALLOWED);
+    assert_eq!(response.headers()[header::ALLOW], "GET, HEAD");
+
+    let response = protocol.handle(&request(Method::GET, "appicon://localhost/foo?size=0"));
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(response.body(), b"invalid `size` parameter");
+
+    let response = protocol.handle(&request(
+        Method::GET,
+        "appicon://localhost/%2Ffoo%2Fbar.desktop",
+    ));
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    assert_eq!(response.headers()[header::CACHE_CONTROL], "no-store");
+    assert_eq!(response.body(), b"no icon found for '/foo/bar.desktop'");
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn it_serves_cached_icons() {
+    use std::time::Duration;
+
+    let dir = fixture_dir("protocol");
+
+    let icon_path = dir.join("foo.png");
+    image::RgbaImage::new(64, 64).save(&icon_path).unwrap();
+    let app_path = dir.join("foo.desktop");
+    std::fs::write(
+        &app_path,
+        format!("[Desktop Entry]\nIcon={}\n", icon_path.display()),
+    )
+    .unwrap();
+
+    let protocol =
+        IconProtocol::new(IconCache::new(dir.join("cache"))).with_max_age(Duration::from_secs(60));
+    let uri = format!(
+        "appicon://localhost/{}?size=24&format=webp",
+        app_path.display().to_string().replace('/', "%2F")
+    );
+
+    let response = protocol.handle(&Request::get(&uri).body(()).unwrap());
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()[header::CONTENT_TYPE], "image/webp");
+    assert_eq!(
+        response.headers()[header::CACHE_CONTROL],
+        "private, max-age=60"
+    );
+    assert_eq!(
+        response.headers()[header::CONTENT_LENGTH],
+        response.body().len().to_string().as_str()
+    );
+    assert_eq!(
+        image::load_from_memory(response.body()).unwrap().width(),
+        24
+    );
+    assert!(!response
+        .headers()
+        .contains_key(header::ACCESS_CONTROL_ALLOW_ORIGIN));
+
+    let etag = response.headers()[header::ETAG].clone();
+    let response = protocol.handle(
+        &Request::get(&uri)
+            .header(header::IF_NONE_MATCH, etag.clone())
+            .body(())
+            .unwrap(),
+    );
+    assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+    assert!(response.body().is_empty());
+    assert_eq!(response.headers()[header::ETAG], etag);
+
+    let response = protocol.handle(&Request::head(&uri).body(()).unwrap());
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(response.body().is_empty());
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn it_answers_files_that_are_not_apps_like_missing_apps() {
+    let dir = fixture_dir("protocol-files");
+
+    let file_path = dir.join("notes.txt");
+    std::fs::write(&file_path, "secret").unwrap();
+
+    let protocol = IconProtocol::new(IconCache::new(dir.join("cache")));
+    let get = |path: &std::path::Path| {
+        let uri = format!(
+            "appicon://localhost/{}",
+            path.display().to_string().replace('/', "%2F")
+        );
+        protocol.handle(&Request::get(&uri).body(()).unwrap())
+    };
+
+    let existing = get(&file_path);
+    let missing = get(&dir.join("missing.txt"));
+    assert_eq!(existing.status(), StatusCode::NOT_FOUND);
+    assert_eq!(missing.status(), StatusCode::NOT_FOUND);
+    assert_eq!(
+        existing.body(),
+        format!("no icon found for '{}'", file_path.display()).as_bytes()
+    );
+
+    // the directory exists too, but holds no desktop entry
+    assert_eq!(get(&dir).status(), StatusCode::NOT_FOUND);
+}
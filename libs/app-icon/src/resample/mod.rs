@@ -0,0 +1,129 @@
+use image::{
+    imageops::{self, FilterType},
+    RgbaImage,
+};
+mod tests;
+
+/// The filter used to scale the source icon to the requested size.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum ResampleFilter {
+    /// Sharpest result, best for photographic and detailed icons.
+    #[default]
+    Lanczos,
+    /// Slightly softer than Lanczos, with less ringing around hard edges.
+    CatmullRom,
+    /// Keeps hard pixel edges, for pixel art icons.
+    Nearest,
+}
+
+impl From<ResampleFilter> for FilterType {
+    fn from(filter: ResampleFilter) -> Self {
+        match filter {
+            ResampleFilter::Lanczos => FilterType::Lanczos3,
+            ResampleFilter::CatmullRom => FilterType::CatmullRom,
+            ResampleFilter::Nearest => FilterType::Nearest,
+        }
+    }
+}
+
+/// The size of the icon to produce, in points, and the scale factor of the
+/// display it is for. `IconSize::from(32.0)` is a 32×32 icon at scale 1.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct IconSize {
+    pub width: f64,
+    pub height: f64,
+    pub scale: f64,
+    pub filter: ResampleFilter,
+}
+
+impl IconSize {
+    pub fn new(width: f64, height: f64) -> Self {
+        Self {
+            width,
+            height,
+            scale: 1.0,
+            filter: ResampleFilter::default(),
+        }
+    }
+
+    pub fn square(size: f64) -> Self {
+        Self::new(size, size)
+    }
+
+    /// The HiDPI scale factor, e.g. 2 for a 32pt icon rendered at 64px.
+    pub fn with_scale(mut self, scale: f64) -> Self {
+        self.scale = scale;
+        self
+    }
+
+    pub fn with_filter(mut self, filter: ResampleFilter) -> Self {
+        self.filter = filter;
+        self
+    }
+
+    /// The width of the produced image in pixels.
+    pub fn pixel_width(&self) -> u32 {
+        to_pixels(self.width * self.scale)
+    }
+
+    /// The height of the produced image in pixels.
+    pub fn pixel_height(&self) -> u32 {
+        to_pixels(self.height * self.scale)
+    }
+
+    /// The size of the square source representation to pick, before resampling.
+    pub(crate) fn source_size(&self) -> u32 {
+        self.pixel_width().max(self.pixel_height())
+    }
+
+    /// The size in points of the square source representation to pick, for
+    /// lookups that take the scale factor separately.
+    pub(crate) fn point_size(&self) -> u32 {
+        to_pixels(self.width.max(self.height))
+    }
+}
+
+impl From<f64> for IconSize {
+    fn from(size: f64) -> Self {
+        Self::square(size)
+    }
+}
+
+fn to_pixels(size: f64) -> u32 {
+    size.round().max(1.0) as u32
+}
+
+/// Scales `image` to the exact pixel size of `size`. The image keeps its
+/// aspect ratio and is centered on a transparent canvas when the requested
+/// width and height don't match it.
+pub(crate) fn resample(image: RgbaImage, size: &IconSize) -> RgbaImage {
+    let (width, height) = (size.pixel_width(), size.pixel_height());
+
+    if image.dimensions() == (width, height) {
+        return image;
+    }
+
+    let (source_width, source_height) = image.dimensions();
+    let ratio = f64::min(
+        f64::from(width) / f64::from(source_width),
+        f64::from(height) / f64::from(source_height),
+    );
+    let fit_width = to_pixels(f64::from(source_width) * ratio).min(width);
+    let fit_height = to_pixels(f64::from(source_height) * ratio).min(height);
+
+    let resized = imageops::resize(&image, fit_width, fit_height, size.filter.into());
+
+    if (fit_width, fit_height) == (width, height) {
+        return resized;
+    }
+
+    let mut canvas = RgbaImage::new(width, height);
+    imageops::overlay(
+        &mut canvas,
+        &resized,
+        i64::from((width - fit_width) / 2),
+        i64::from((height - fit_height) / 2),
+    );
+
+    canvas
+}
@@ -0,0 +1,50 @@
+#![cfg(test)]
+use image::{Rgba, RgbaImage};
+
+use super::{resample, IconSize, ResampleFilter};
+
+#[test]
+fn it_scales_to_the_exact_size() {
+    let image = RgbaImage::from_pixel(256, 256, Rgba([9, 9, 9, 255]));
+
+    assert_eq!(
+        resample(image, &IconSize::square(48.0)).dimensions(),
+        (48, 48)
+    );
+}
+
+#[test]
+fn it_applies_the_scale_factor() {
+    let size = IconSize::square(32.0).with_scale(2.0);
+    let image = RgbaImage::new(256, 256);
+
+    assert_eq!(size.source_size(), 64);
+    assert_eq!(size.point_size(), 32);
+    assert_eq!(resample(image, &size).dimensions(), (64, 64));
+}
+
+#[test]
+fn it_centers_the_icon_for_other_aspect_ratios() {
+    let image = RgbaImage::from_pixel(16, 16, Rgba([9, 9, 9, 255]));
+
+    let resampled = resample(image, &IconSize::new(64.0, 32.0));
+
+    assert_eq!(resampled.dimensions(), (64, 32));
+    assert_eq!(resampled.get_pixel(0, 16), &Rgba([0, 0, 0, 0]));
+    assert_eq!(resampled.get_pixel(32, 16), &Rgba([9, 9, 9, 255]));
+}
+
+#[test]
+fn it_keeps_pixel_art_sharp() {
+    let mut image = RgbaImage::from_pixel(2, 2, Rgba([0, 0, 0, 255]));
+    image.put_pixel(0, 0, Rgba([255, 255, 255, 255]));
+
+    let resampled = resample(
+        image,
+        &IconSize::square(8.0).with_filter(ResampleFilter::Nearest),
+    );
+
+    assert!(resampled
+        .pixels()
+        .all(|pixel| pixel.0 == [0, 0, 0, 255] || pixel.0 == [255, 255, 255, 255]));
+}
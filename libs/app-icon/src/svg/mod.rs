@@ -0,0 +1,61 @@
+//! Rendering the SVG icons of freedesktop icon themes with resvg, whose
+//! tiny-skia pixmaps also back the drawing of [`crate::effects`].
+//!
+//! Text is left out, as no fonts are loaded, and so are raster images
+//! embedded in the document.
+
+use image::RgbaImage;
+use resvg::tiny_skia;
+#[cfg(target_os = "linux")]
+use resvg::usvg;
+#[cfg(target_os = "linux")]
+use thiserror::Error;
+
+mod tests;
+
+#[cfg(target_os = "linux")]
+#[derive(Error, Debug, PartialEq)]
+pub enum SvgError {
+    #[error("failed to parse the SVG document")]
+    ParseError,
+    #[error("SVG document has no size")]
+    InvalidSize,
+}
+
+/// Copies the pixels of `pixmap`, undoing the alpha premultiplication of tiny-skia.
+pub(crate) fn pixmap_to_image(pixmap: &tiny_skia::Pixmap) -> RgbaImage {
+    let pixels = pixmap
+        .pixels()
+        .iter()
+        .flat_map(|pixel| {
+            let color = pixel.demultiply();
+            [color.red(), color.green(), color.blue(), color.alpha()]
+        })
+        .collect();
+
+    RgbaImage::from_raw(pixmap.width(), pixmap.height(), pixels)
+        .expect("a pixmap has 4 bytes per pixel")
+}
+
+/// Renders the SVG document `data` to fit in a `size`×`size` pixels image,
+/// keeping its aspect ratio.
+#[cfg(target_os = "linux")]
+pub fn render(data: &[u8], size: u32) -> Result<RgbaImage, SvgError> {
+    let tree =
+        usvg::Tree::from_data(data, &usvg::Options::default()).map_err(|error| match error {
+            usvg::Error::InvalidSize => SvgError::InvalidSize,
+            _ => SvgError::ParseError,
+        })?;
+
+    let (width, height) = (tree.size().width(), tree.size().height());
+    let scale = size.max(1) as f32 / width.max(height);
+    let pixel_width = (width * scale).round().max(1.0);
+    let pixel_height = (height * scale).round().max(1.0);
+
+    let mut pixmap = tiny_skia::Pixmap::new(pixel_width as u32, pixel_height as u32)
+        .ok_or(SvgError::InvalidSize)?;
+    let transform = tiny_skia::Transform::from_scale(pixel_width / width, pixel_height / height);
+    resvg::render(&tree, transform, &mut pixmap.as_mut());
+
+    Ok(pixmap_to_image(&pixmap))
+}
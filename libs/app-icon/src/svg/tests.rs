@@ -0,0 +1,125 @@
+#![cfg(all(test, target_os = "linux"))]
+use image::Rgba;
+
+use super::{render, SvgError};
+
+fn alpha_at(image: &image::RgbaImage, x: u32, y: u32) -> u8 {
+    image.get_pixel(x, y)[3]
+}
+
+#[test]
+fn it_fills_shapes_scaled_to_the_requested_size() {
+    let image = render(
+        br##"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 16 16">
+            <rect x="0" y="0" width="8.125" height="16" fill="#ff0000"/>
+            <circle cx="12" cy="8" r="2" style="fill:rgb(0, 0, 255)"/>
+        </svg>"##,
+        64,
+    )
+    .unwrap();
+
+    assert_eq!(image.dimensions(), (64, 64));
+    assert_eq!(image.get_pixel(10, 10), &Rgba([255, 0, 0, 255]));
+    assert_eq!(image.get_pixel(48, 32), &Rgba([0, 0, 255, 255]));
+    assert_eq!(alpha_at(&image, 48, 10), 0);
+    // the edge of the rectangle covers half a pixel
+    assert_eq!(image.get_pixel(32, 10), &Rgba([255, 0, 0, 128]));
+}
+
+#[test]
+fn it_keeps_the_aspect_ratio() {
+    let image = render(
+        br#"<svg width="32px" height="16px"><rect width="100%" height="100%"/></svg>"#,
+        48,
+    )
+    .unwrap();
+
+    assert_eq!(image.dimensions(), (48, 24));
+    assert_eq!(image.get_pixel(47, 23), &Rgba([0, 0, 0, 255]));
+}
+
+#[test]
+fn it_applies_transforms_and_fill_rules() {
+    let image = render(
+        br##"<svg viewBox="0 0 10 10">
+            <g transform="translate(10 0) scale(-1 1)" fill-rule="evenodd">
+                <path d="M0 0h6v10H0zM2 2h2v6H2z" fill="#0f0"/>
+            </g>
+        </svg>"##,
+        10,
+    )
+    .unwrap();
+
+    // mirrored to the right half, with a hole in the middle
+    assert_eq!(image.get_pixel(9, 0), &Rgba([0, 255, 0, 255]));
+    assert_eq!(alpha_at(&image, 6, 5), 0);
+    assert_eq!(alpha_at(&image, 0, 5), 0);
+}
+
+#[test]
+fn it_renders_gradients_and_references() {
+    let image = render(
+        br##"<svg xmlns:xlink="http://www.w3.org/1999/xlink" viewBox="0 0 100 10">
+            <defs>
+                <linearGradient id="base">
+                    <stop offset="0" stop-color="#000"/>
+                    <stop offset="100%" style="stop-color:#fff"/>
+                </linearGradient>
+                <linearGradient id="fade" xlink:href="#base" x1="0" x2="1"/>
+                <rect id="bar" width="100" height="10"/>
+            </defs>
+            <use xlink:href="#bar" fill="url(#fade)"/>
+        </svg>"##,
+        100,
+    )
+    .unwrap();
+
+    let left = image.get_pixel(0, 5);
+    let middle = image.get_pixel(50, 5);
+    let right = image.get_pixel(99, 5);
+    assert!(left[0] < 8 && right[0] > 247);
+    assert!((120..136).contains(&middle[0]), "middle {middle:?}");
+    assert_eq!(middle[3], 255);
+}
+
+#[test]
+fn it_strokes_paths() {
+    let image = render(
+        br#"<svg viewBox="0 0 20 20">
+            <path d="M2 10 H18" fill="none" stroke="currentColor" color="white"
+                stroke-width="4" stroke-linecap="round" opacity="0.5"/>
+        </svg>"#,
+        20,
+    )
+    .unwrap();
+
+    assert_eq!(image.get_pixel(10, 10), &Rgba([255, 255, 255, 128]));
+    assert_eq!(alpha_at(&image, 10, 5), 0);
+    // the round cap reaches past the end of the line
+    assert!(alpha_at(&image, 19, 10) > 0);
+}
+
+#[test]
+fn it_skips_hidden_and_filtered_elements() {
+    let image = render(
+        br#"<svg viewBox="0 0 4 4">
+            <rect width="4" height="4" style="display:none"/>
+            <rect width="4" height="4" visibility="hidden"/>
+            <rect width="4" height="4" filter="url(#blur)"/>
+        </svg>"#,
+        4,
+    )
+    .unwrap();
+
+    assert!(image.pixels().all(|pixel| pixel[3] == 0));
+}
+
+#[test]
+fn invalid_documents() {
+    assert_eq!(render(b"<svg>", 16).unwrap_err(), SvgError::ParseError);
+    assert_eq!(render(b"<html/>", 16).unwrap_err(), SvgError::ParseError);
+    assert_eq!(
+        render(br#"<svg width="0" height="0"/>"#, 16).unwrap_err(),
+        SvgError::InvalidSize
+    );
+}
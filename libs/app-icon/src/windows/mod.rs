@@ -1,9 +1,10 @@
 use std::num::TryFromIntError;
 use std::os::windows::ffi::OsStrExt;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::{env, fs};
 use std::{
     mem::{self, MaybeUninit},
-    ptr::addr_of_mut,
+    ptr::{self, addr_of_mut},
 };
 
 use image::RgbaImage;
@@ -12,34 +13,66 @@ use windows_sys::Win32::Graphics::Gdi::{
     DeleteObject, GetDC, GetDIBits, GetObjectW, ReleaseDC, BITMAP, BITMAPINFOHEADER, BI_RGB,
     DIB_RGB_COLORS,
 };
-use windows_sys::Win32::System::Com::CoUninitialize;
+use windows_sys::Win32::System::Com::{
+    CoInitializeEx, CoUninitialize, COINIT_APARTMENTTHREADED, COINIT_DISABLE_OLE1DDE,
+};
 use windows_sys::Win32::UI::Shell::ExtractIconExW;
 use windows_sys::Win32::UI::WindowsAndMessaging::{DestroyIcon, GetIconInfo, HICON};
 
+use crate::{
+    bundle, encode, pe, resample, GetAppIconError, IconFormat, IconOptions, IconSize, IconVariant,
+};
+
 mod tests;
 
-#[derive(Error, Debug, PartialEq)]
-pub enum GetIconError {
-    #[error("app path does not exist")]
-    AppPathDoesNotExist,
-    #[error("save path parent directory does not exist")]
-    SavePathParentDirDoesNotExist,
-    #[error("failed to extract icon")]
-    IconExtractionError,
+/// Why an extracted `HICON` couldn't be read into an image.
+#[derive(Error, Debug)]
+enum IconBitmapError {
     #[error("failed to get icon info")]
     IconInfoError,
     #[error("failed to convert icon info")]
     IconInfoConversionError,
-    #[error("failed to save image")]
-    ImageSaveError,
+    #[error("failed to read icon bitmap")]
+    ImageConversionError,
     #[error("Failed to convert one of the bitmap data to valid integer: {0}")]
     BitmapConversionError(#[from] TryFromIntError),
 }
 
-unsafe fn icon_to_image(icon: HICON) -> Result<RgbaImage, GetIconError> {
+/// Initializes COM on the current thread for the shell APIs, and balances it
+/// when dropped. Initializing again on the same thread only bumps a count, and
+/// a thread already initialized in another apartment is left as it is.
+pub(crate) struct ComGuard {
+    initialized: bool,
+}
+
+impl ComGuard {
+    pub(crate) fn new() -> Self {
+        let result = unsafe {
+            CoInitializeEx(
+                ptr::null(),
+                (COINIT_APARTMENTTHREADED | COINIT_DISABLE_OLE1DDE) as u32,
+            )
+        };
+
+        // S_OK and S_FALSE both need a matching CoUninitialize, RPC_E_CHANGED_MODE doesn't
+        Self {
+            initialized: result >= 0,
+        }
+    }
+}
+
+impl Drop for ComGuard {
+    fn drop(&mut self) {
+        if self.initialized {
+            unsafe { CoUninitialize() };
+        }
+    }
+}
+
+unsafe fn icon_to_image(icon: HICON) -> Result<RgbaImage, IconBitmapError> {
     let mut icon_info = MaybeUninit::uninit();
     if GetIconInfo(icon, icon_info.as_mut_ptr()) == 0 {
-        return Err(GetIconError::IconInfoError);
+        return Err(IconBitmapError::IconInfoError);
     }
     let icon_info = icon_info.assume_init();
 
@@ -55,7 +88,7 @@ unsafe fn icon_to_image(icon: HICON) -> Result<RgbaImage, GetIconError> {
     );
     if result == 0 {
         DeleteObject(icon_info.hbmColor);
-        return Err(GetIconError::IconInfoConversionError);
+        return Err(IconBitmapError::IconInfoConversionError);
     }
     let bitmap = bitmap.assume_init();
 
@@ -66,14 +99,14 @@ unsafe fn icon_to_image(icon: HICON) -> Result<RgbaImage, GetIconError> {
     let buf_size = width_usize
         .checked_mul(height_usize)
         .and_then(|size| size.checked_mul(4))
-        .ok_or(GetIconError::IconInfoConversionError)?;
+        .ok_or(IconBitmapError::IconInfoConversionError)?;
     let mut buf: Vec<u8> = Vec::with_capacity(buf_size);
 
     // device context
     let dc = GetDC(0);
     if dc == 0 {
         DeleteObject(icon_info.hbmColor);
-        return Err(GetIconError::IconInfoError);
+        return Err(IconBitmapError::IconInfoError);
     }
 
     let biheader_size_u32 = u32::try_from(mem::size_of::<BITMAPINFOHEADER>())?;
@@ -104,7 +137,7 @@ unsafe fn icon_to_image(icon: HICON) -> Result<RgbaImage, GetIconError> {
     if result == 0 {
         DeleteObject(icon_info.hbmColor);
         ReleaseDC(0, dc);
-        return Err(GetIconError::IconInfoConversionError);
+        return Err(IconBitmapError::IconInfoConversionError);
     }
     buf.set_len(buf.capacity());
 
@@ -117,20 +150,38 @@ unsafe fn icon_to_image(icon: HICON) -> Result<RgbaImage, GetIconError> {
         mem::swap(b, r);
     }
 
-    RgbaImage::from_vec(width_u32, height_u32, buf).ok_or(GetIconError::ImageSaveError)
+    RgbaImage::from_vec(width_u32, height_u32, buf).ok_or(IconBitmapError::ImageConversionError)
 }
 
-pub fn get_icon(app_path: &Path, save_path: &Path, _icon_size: f64) -> Result<(), GetIconError> {
+fn check_app_path(app_path: &Path) -> Result<(), GetAppIconError> {
     if !app_path.exists() {
-        return Err(GetIconError::AppPathDoesNotExist);
+        return Err(GetAppIconError::not_found(app_path));
     }
 
-    let parent = save_path
-        .parent()
-        .ok_or(GetIconError::SavePathParentDirDoesNotExist)?;
+    Ok(())
+}
+
+/// Executables, libraries and macOS bundles: `ExtractIconExW` takes any file,
+/// but only these are applications.
+pub(crate) fn is_app_path(app_path: &Path) -> bool {
+    pe::is_pe_path(app_path) || bundle::is_app_bundle(app_path)
+}
+
+pub fn get_icon_image(app_path: &Path, icon_size: IconSize) -> Result<RgbaImage, GetAppIconError> {
+    check_app_path(app_path)?;
+
+    // macOS bundles, e.g. on a mounted volume, carry their own .icns
+    if bundle::is_app_bundle(app_path) {
+        return bundle::get_icon_image(app_path, icon_size)
+            .map_err(|error| GetAppIconError::bundle(app_path, error));
+    }
 
-    if !parent.exists() {
-        return Err(GetIconError::SavePathParentDirDoesNotExist);
+    // reading the resources directly gives the entry matching the size (up to
+    // 256px), ExtractIconExW stays as the fallback for anything else
+    if pe::is_pe_path(app_path) {
+        if let Ok(image) = pe::get_icon_image(app_path, icon_size) {
+            return Ok(image);
+        }
     }
 
     let path: Vec<u16> = app_path.as_os_str().encode_wide().chain(Some(0)).collect();
@@ -138,25 +189,83 @@ pub fn get_icon(app_path: &Path, save_path: &Path, _icon_size: f64) -> Result<()
     let mut large_icon: isize = 0;
     let mut small_icon: isize = 0;
 
+    let _com = ComGuard::new();
+
     unsafe {
         let count = ExtractIconExW(path.as_ptr(), 0, &mut large_icon, &mut small_icon, 1);
         if count == 0 {
-            CoUninitialize();
-            return Err(GetIconError::IconExtractionError);
+            return Err(GetAppIconError::no_icon(app_path));
         }
 
-        let image = icon_to_image(large_icon).map_err(|e| {
-            DestroyIcon(large_icon);
-            CoUninitialize();
-            e
-        })?;
+        if small_icon != 0 {
+            DestroyIcon(small_icon);
+        }
 
+        let image = icon_to_image(large_icon);
         DestroyIcon(large_icon);
-        image
-            .save(save_path)
-            .map_err(|_| GetIconError::ImageSaveError)?;
-        CoUninitialize();
+
+        let image = image.map_err(GetAppIconError::decode)?;
+
+        Ok(resample::resample(image, &icon_size))
     }
+}
 
-    Ok(())
+/// The icon of the app, Windows icons have no appearance variants but the
+/// `.icns` of macOS bundles may.
+pub fn get_icon_variant(
+    app_path: &Path,
+    icon_size: IconSize,
+    options: &IconOptions,
+) -> Result<IconVariant, GetAppIconError> {
+    if bundle::is_app_bundle(app_path) {
+        return bundle::get_icon_variant(
+            app_path,
+            icon_size,
+            options.appearance.unwrap_or_default(),
+        )
+        .map_err(|error| GetAppIconError::bundle(app_path, error));
+    }
+
+    get_icon_image(app_path, icon_size).map(IconVariant::light)
+}
+
+/// Finds the executable named `app_id` (`notepad` or `notepad.exe`) in `PATH`.
+pub fn find_app_path(app_id: &str) -> Result<PathBuf, GetAppIconError> {
+    let file_names = if Path::new(app_id).extension().is_some() {
+        vec![app_id.to_string()]
+    } else {
+        vec![format!("{app_id}.exe"), app_id.to_string()]
+    };
+
+    env::var_os("PATH")
+        .iter()
+        .flat_map(env::split_paths)
+        .flat_map(|dir| file_names.iter().map(move |name| dir.join(name)))
+        .find(|path| path.is_file())
+        .ok_or_else(|| GetAppIconError::NotFound(app_id.to_string()))
+}
+
+pub fn get_icon_bytes(
+    app_path: &Path,
+    icon_size: IconSize,
+    format: IconFormat,
+) -> Result<Vec<u8>, GetAppIconError> {
+    let image = get_icon_image(app_path, icon_size)?;
+
+    encode::encode(&image, format).map_err(GetAppIconError::Encode)
+}
+
+pub fn get_icon(
+    app_path: &Path,
+    save_path: &Path,
+    icon_size: IconSize,
+    format: IconFormat,
+) -> Result<(), GetAppIconError> {
+    check_app_path(app_path)?;
+
+    crate::check_save_path(save_path)?;
+
+    let bytes = get_icon_bytes(app_path, icon_size, format)?;
+
+    fs::write(save_path, bytes).map_err(|error| GetAppIconError::io(save_path, error))
 }
@@ -1,26 +1,27 @@
 #![cfg(test)]
 use std::path::Path;
 
-use super::{get_icon, GetIconError};
+use super::{find_app_path, get_icon, get_icon_bytes, get_icon_image};
+use crate::{GetAppIconError, IconFormat, IconSize};
 
 #[test]
 fn app_path_does_not_exist() {
     let app_path = Path::new(r"C:\foo\bar");
     let save_path = Path::new(r"C:\foo\temp");
-    assert_eq!(
-        get_icon(app_path, save_path, 32.0).unwrap_err(),
-        GetIconError::AppPathDoesNotExist
-    );
+    assert!(matches!(
+        get_icon(app_path, save_path, IconSize::square(32.0), IconFormat::Png).unwrap_err(),
+        GetAppIconError::NotFound(_)
+    ));
 }
 
 #[test]
 fn save_path_parent_does_not_exist() {
     let app_path = Path::new(r"C:\Program Files (x86)\Microsoft\Edge\Application\msedge.exe");
     let save_path = Path::new(r"Windows\Temp\edge.png");
-    assert_eq!(
-        get_icon(app_path, save_path, 32.0).unwrap_err(),
-        GetIconError::SavePathParentDirDoesNotExist
-    );
+    assert!(matches!(
+        get_icon(app_path, save_path, IconSize::square(32.0), IconFormat::Png).unwrap_err(),
+        GetAppIconError::Io { path, .. } if path == Path::new(r"Windows\Temp")
+    ));
 }
 
 #[test]
@@ -28,14 +29,44 @@ fn image_save_failure() {
     let app_path = Path::new(r"C:\Program Files (x86)\Microsoft\Edge\Application\msedge.exe");
     // eleveted access required to write to this folder
     let save_path = Path::new(r"C:\Windows\System32\forbidden_icon.png"); 
-    let result = get_icon(app_path, save_path, 32.0);
+    let result = get_icon(app_path, save_path, IconSize::square(32.0), IconFormat::Png);
     assert!(result.is_err());
-    assert_eq!(result.unwrap_err(), GetIconError::ImageSaveError);
+    assert!(matches!(
+        result.unwrap_err(),
+        GetAppIconError::Io { path, .. } if path == save_path
+    ));
 }
 
 #[test]
 fn it_works() {
     let app_path = Path::new(r"C:\Program Files (x86)\Microsoft\Edge\Application\msedge.exe");
     let save_path = Path::new(r"C:\Windows\Temp\edge.png");
-    assert!(get_icon(app_path, save_path, 32.0).is_ok());
+    assert!(get_icon(app_path, save_path, IconSize::square(32.0), IconFormat::Png).is_ok());
+}
+
+#[test]
+fn it_returns_image() {
+    let app_path = Path::new(r"C:\Program Files (x86)\Microsoft\Edge\Application\msedge.exe");
+    let image = get_icon_image(app_path, IconSize::square(32.0)).unwrap();
+    assert!(image.width() > 0 && image.height() > 0);
+}
+
+#[test]
+fn it_returns_png_bytes() {
+    let app_path = Path::new(r"C:\Program Files (x86)\Microsoft\Edge\Application\msedge.exe");
+    let bytes = get_icon_bytes(app_path, IconSize::square(32.0), IconFormat::Png).unwrap();
+    assert!(image::load_from_memory_with_format(&bytes, image::ImageFormat::Png).is_ok());
+}
+
+#[test]
+fn it_returns_high_resolution_image() {
+    let app_path = Path::new(r"C:\Program Files (x86)\Microsoft\Edge\Application\msedge.exe");
+    let image = get_icon_image(app_path, IconSize::square(256.0)).unwrap();
+    assert_eq!(image.dimensions(), (256, 256));
+}
+
+#[test]
+fn it_finds_apps_in_path() {
+    let app_path = find_app_path("notepad").unwrap();
+    assert!(app_path.ends_with("notepad.exe"));
 }
@@ -0,0 +1,113 @@
+//! The coordinates of the crate, which every backend reports monitors in: the
+//! virtual desktop of Tauri's `set_position`, with y going down. Its origin is
+//! the top-left corner of the primary monitor on Windows and macOS, of the
+//! root window on X11, and the compositor's on Wayland, which usually places
+//! a monitor there but not always the primary one. A point of a monitor is
+//! in physical pixels, its logical position times the monitor's scale factor.
+//! So with monitors of different scale factors, which are laid out in logical
+//! pixels, their physical rectangles can overlap or leave gaps, and a physical
+//! position is on the first monitor containing it.
+//!
+//! Native coordinates are the physical ones on Windows and X11, and the
+//! logical ones on Wayland. On macOS, they're Cocoa's points, from the
+//! bottom-left corner of the primary screen with y going up.
+
+use dpi::{LogicalPosition, PhysicalPosition, PhysicalSize};
+
+use crate::{MonitorLayout, Rect};
+
+mod tests;
+
+/// The top of a rectangle from the top of the primary screen, given its
+/// bottom from the bottom of the primary screen, as Cocoa places it.
+pub(crate) fn flip_y(y: f64, height: f64, primary_height: f64) -> f64 {
+    primary_height - y - height
+}
+
+impl MonitorLayout {
+    /// The logical rectangles of the monitors, with the scale factor of each.
+    fn logical_rects(&self) -> impl Iterator<Item = (Rect, f64)> + '_ {
+        self.monitors().iter().map(|monitor| {
+            let scale_factor = monitor.scale_factor();
+            let position = monitor.logical_position();
+            let size = monitor.logical_size();
+
+            (
+                Rect::new(
+                    PhysicalPosition::new(position.x, position.y),
+                    PhysicalSize::new(size.width, size.height),
+                ),
+                scale_factor,
+            )
+        })
+    }
+
+    /// The scale factor of the monitor the physical position is on, or is
+    /// the nearest to.
+    fn physical_scale_factor(&self, position: PhysicalPosition<f64>) -> Option<f64> {
+        self.nearest_monitor(position)
+            .map(|monitor| monitor.scale_factor())
+    }
+
+    /// The scale factor of the monitor the logical position is on, or is the
+    /// nearest to.
+    fn logical_scale_factor(&self, position: LogicalPosition<f64>) -> Option<f64> {
+        let point = PhysicalPosition::new(position.x, position.y);
+
+        self.logical_rects()
+            .map(|(rect, scale_factor)| (rect.distance_to(point), scale_factor))
+            .fold(
+                None,
+                |nearest: Option<(f64, f64)>, (distance, scale_factor)| match nearest {
+                    Some((nearest_distance, _)) if nearest_distance <= distance => nearest,
+                    _ => Some((distance, scale_factor)),
+                },
+            )
+            .map(|(_, scale_factor)| scale_factor)
+    }
+
+    /// The logical position of a physical position, scaled by the monitor
+    /// it's on, e.g. for `WebviewWindow::set_position` with a
+    /// `LogicalPosition`. `None` when there are no monitors.
+    pub fn to_logical(&self, position: PhysicalPosition<f64>) -> Option<LogicalPosition<f64>> {
+        let scale_factor = self.physical_scale_factor(position)?;
+
+        Some(position.to_logical(scale_factor))
+    }
+
+    /// The physical position of a logical position, scaled by the monitor
+    /// it's on.
+    pub fn to_physical(&self, position: LogicalPosition<f64>) -> Option<PhysicalPosition<f64>> {
+        let scale_factor = self.logical_scale_factor(position)?;
+
+        Some(position.to_physical(scale_factor))
+    }
+
+    /// The height of the primary monitor, in points, which Cocoa's y axis is
+    /// flipped around.
+    fn primary_height(&self) -> Option<f64> {
+        self.monitors()
+            .iter()
+            .find(|monitor| monitor.is_primary())
+            .or_else(|| self.monitors().first())
+            .map(|monitor| monitor.logical_size().height)
+    }
+
+    /// The position in Cocoa's coordinates, e.g. for `NSWindow`.
+    pub fn to_cocoa(&self, position: PhysicalPosition<f64>) -> Option<LogicalPosition<f64>> {
+        let logical = self.to_logical(position)?;
+
+        Some(LogicalPosition::new(
+            logical.x,
+            flip_y(logical.y, 0.0, self.primary_height()?),
+        ))
+    }
+
+    /// The position of Cocoa's coordinates, e.g. of `NSEvent.mouseLocation`.
+    pub fn from_cocoa(&self, position: LogicalPosition<f64>) -> Option<PhysicalPosition<f64>> {
+        let logical =
+            LogicalPosition::new(position.x, flip_y(position.y, 0.0, self.primary_height()?));
+
+        self.to_physical(logical)
+    }
+}
@@ -0,0 +1,101 @@
+#![cfg(test)]
+
+use dpi::{LogicalPosition, PhysicalPosition};
+
+use super::flip_y;
+use crate::{Monitor, MonitorLayout};
+
+/// A monitor at a logical position, as the backends place them.
+fn monitor(id: u32, x: f64, y: f64, width: f64, height: f64, scale_factor: f64) -> Monitor {
+    Monitor::test(id)
+        .with_rect(
+            x * scale_factor,
+            y * scale_factor,
+            width * scale_factor,
+            height * scale_factor,
+        )
+        .with_scale_factor(scale_factor)
+        .with_primary(id == 1)
+}
+
+#[test]
+fn it_flips_cocoa_frames() {
+    // a 1440x900 laptop with a 1920x1080 monitor stacked above it, and
+    // another below it
+    let primary_height = 900.0;
+
+    assert_eq!(flip_y(0.0, 900.0, primary_height), 0.0);
+    assert_eq!(flip_y(900.0, 1080.0, primary_height), -1080.0);
+    assert_eq!(flip_y(-1080.0, 1080.0, primary_height), 900.0);
+
+    // a 1920x1080 monitor at the right, its bottom 100 points below the
+    // laptop's
+    assert_eq!(flip_y(-100.0, 1080.0, primary_height), -80.0);
+}
+
+/// A laptop scaled by 2, a monitor stacked above it and offset to the right,
+/// and a monitor scaled by 1.5 at its right, lower by 300 points.
+fn layout() -> MonitorLayout {
+    MonitorLayout::new(vec![
+        monitor(1, 0.0, 0.0, 1440.0, 900.0, 2.0),
+        monitor(2, 200.0, -1080.0, 1920.0, 1080.0, 1.0),
+        monitor(3, 1440.0, 300.0, 2560.0, 1440.0, 1.5),
+    ])
+}
+
+#[test]
+fn it_converts_between_physical_and_logical_positions() {
+    let layout = layout();
+
+    assert_eq!(
+        layout.monitors()[2].position(),
+        PhysicalPosition::new(2160.0, 450.0)
+    );
+    assert_eq!(
+        layout.monitors()[2].logical_position(),
+        LogicalPosition::new(1440.0, 300.0)
+    );
+
+    for (physical, logical) in [
+        ((100.0, 100.0), (50.0, 50.0)),
+        ((300.0, -500.0), (300.0, -500.0)),
+        ((3000.0, 1950.0), (2000.0, 1300.0)),
+        ((3000.0, 1500.0), (2000.0, 1000.0)),
+    ] {
+        let physical = PhysicalPosition::new(physical.0, physical.1);
+        let logical = LogicalPosition::new(logical.0, logical.1);
+
+        assert_eq!(layout.to_logical(physical), Some(logical));
+        assert_eq!(layout.to_physical(logical), Some(physical));
+    }
+
+    // off the monitors, the nearest one's scale factor is used
+    assert_eq!(
+        layout.to_logical(PhysicalPosition::new(-100.0, 200.0)),
+        Some(LogicalPosition::new(-50.0, 100.0))
+    );
+
+    assert_eq!(
+        MonitorLayout::new(vec![]).to_logical(PhysicalPosition::new(0.0, 0.0)),
+        None
+    );
+}
+
+#[test]
+fn it_converts_between_cocoa_and_top_left_positions() {
+    let layout = layout();
+
+    for (physical, cocoa) in [
+        // the top-left corner of the laptop is the top of Cocoa's primary screen
+        ((0.0, 0.0), (0.0, 900.0)),
+        ((100.0, 100.0), (50.0, 850.0)),
+        ((300.0, -500.0), (300.0, 1400.0)),
+        ((3000.0, 1500.0), (2000.0, -100.0)),
+    ] {
+        let physical = PhysicalPosition::new(physical.0, physical.1);
+        let cocoa = LogicalPosition::new(cocoa.0, cocoa.1);
+
+        assert_eq!(layout.to_cocoa(physical), Some(cocoa));
+        assert_eq!(layout.from_cocoa(cocoa), Some(physical));
+    }
+}
@@ -0,0 +1,570 @@
+//! A parser of the EDID monitors describe themselves with: the base block,
+//! and the CTA-861 and DisplayID extension blocks of HDMI and DisplayPort
+//! monitors.
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+mod tests;
+
+/// The length of an EDID block, the base block is the first one.
+const BLOCK_LEN: usize = 128;
+
+const HEADER: [u8; 8] = [0x00, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x00];
+
+/// The length of a detailed timing or display descriptor.
+const DESCRIPTOR_LEN: usize = 18;
+
+const CTA_EXTENSION: u8 = 0x02;
+const DISPLAYID_EXTENSION: u8 = 0x70;
+
+/// The extended data block of CTA-861, and its HDR static metadata block.
+const CTA_EXTENDED_TAG: u8 = 0x07;
+const CTA_HDR_STATIC_METADATA: u8 = 0x06;
+
+/// The display descriptors of the base block.
+const SERIAL_NUMBER_DESCRIPTOR: u8 = 0xff;
+const MODEL_NAME_DESCRIPTOR: u8 = 0xfc;
+
+/// The data blocks of DisplayID 1.x and 2.x.
+const DISPLAYID_PRODUCT_ID: [u8; 2] = [0x00, 0x20];
+const DISPLAYID_DISPLAY_PARAMETERS: [u8; 2] = [0x01, 0x21];
+const DISPLAYID_TYPE_I_TIMING: u8 = 0x03;
+const DISPLAYID_TYPE_VII_TIMING: u8 = 0x22;
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum EdidError {
+    #[error("the EDID is shorter than its base block")]
+    TooShort,
+    #[error("the EDID doesn't start with the EDID header")]
+    InvalidHeader,
+    #[error("the checksum of the EDID base block doesn't match")]
+    InvalidChecksum,
+}
+
+/// When the monitor was made, or the model year when the EDID doesn't tell.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ManufactureDate {
+    week: Option<u8>,
+    year: u16,
+    is_model_year: bool,
+}
+
+impl ManufactureDate {
+    /// The week of the year, from 1 to 54, if the EDID tells it.
+    pub fn week(&self) -> Option<u8> {
+        self.week
+    }
+
+    pub fn year(&self) -> u16 {
+        self.year
+    }
+
+    /// Whether the year is the year of the model rather than of manufacture.
+    pub fn is_model_year(&self) -> bool {
+        self.is_model_year
+    }
+}
+
+/// A video mode, in pixels.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct Mode {
+    width: u32,
+    height: u32,
+    refresh_rate: f64,
+}
+
+impl Mode {
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// The refresh rate, in Hz.
+    pub fn refresh_rate(&self) -> f64 {
+        self.refresh_rate
+    }
+}
+
+/// What an HDR monitor tells about itself in the HDR static metadata block of
+/// its CTA-861 extension. The luminances are in cd/m².
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct HdrStaticMetadata {
+    supports_pq: bool,
+    supports_hlg: bool,
+    max_luminance: Option<f64>,
+    max_frame_average_luminance: Option<f64>,
+    min_luminance: Option<f64>,
+}
+
+impl HdrStaticMetadata {
+    /// Whether the monitor takes signals with the SMPTE ST 2084 transfer
+    /// function, which HDR10 and Dolby Vision use.
+    pub fn supports_pq(&self) -> bool {
+        self.supports_pq
+    }
+
+    /// Whether the monitor takes Hybrid Log-Gamma signals, which broadcasts use.
+    pub fn supports_hlg(&self) -> bool {
+        self.supports_hlg
+    }
+
+    /// The peak luminance content should have for the monitor to show it.
+    pub fn max_luminance(&self) -> Option<f64> {
+        self.max_luminance
+    }
+
+    /// The luminance a whole frame can have on average.
+    pub fn max_frame_average_luminance(&self) -> Option<f64> {
+        self.max_frame_average_luminance
+    }
+
+    pub fn min_luminance(&self) -> Option<f64> {
+        self.min_luminance
+    }
+
+    fn parse(payload: &[u8]) -> Option<Self> {
+        // the transfer functions are SDR, HDR, PQ and HLG from the lowest bit
+        let eotfs = *payload.first()?;
+        // the luminances are coded as 50 * 2^(value / 32), and the minimum
+        // as a fraction of the maximum
+        let luminance = |index: usize| {
+            payload
+                .get(index)
+                .filter(|value| **value != 0)
+                .map(|value| 50.0 * 2f64.powf(f64::from(*value) / 32.0))
+        };
+        let max_luminance = luminance(2);
+
+        Some(Self {
+            supports_pq: eotfs & 0x04 != 0,
+            supports_hlg: eotfs & 0x08 != 0,
+            max_luminance,
+            max_frame_average_luminance: luminance(3),
+            min_luminance: max_luminance.and_then(|max| {
+                let value = f64::from(*payload.get(4)?);
+
+                Some(max * (value / 255.0).powi(2) / 100.0)
+            }),
+        })
+    }
+}
+
+/// What a monitor tells about itself through its EDID, which is the same on
+/// every OS and port the monitor is plugged in.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Edid {
+    version: (u8, u8),
+    manufacturer: String,
+    product_code: u16,
+    serial_number: Option<String>,
+    model_name: Option<String>,
+    manufacture_date: Option<ManufactureDate>,
+    physical_size_mm: Option<(u32, u32)>,
+    preferred_mode: Option<Mode>,
+    bits_per_component: Option<u8>,
+    hdr_static_metadata: Option<HdrStaticMetadata>,
+}
+
+impl Edid {
+    /// The EDID version, e.g. `(1, 4)`.
+    pub fn version(&self) -> (u8, u8) {
+        self.version
+    }
+
+    /// The PNP id of the manufacturer, e.g. `DEL` for Dell.
+    pub fn manufacturer(&self) -> &str {
+        &self.manufacturer
+    }
+
+    pub fn product_code(&self) -> u16 {
+        self.product_code
+    }
+
+    /// The serial number of the serial number descriptor, which is the one
+    /// printed on the monitor, or else of the base block.
+    pub fn serial_number(&self) -> Option<&String> {
+        self.serial_number.as_ref()
+    }
+
+    pub fn model_name(&self) -> Option<&String> {
+        self.model_name.as_ref()
+    }
+
+    pub fn manufacture_date(&self) -> Option<ManufactureDate> {
+        self.manufacture_date
+    }
+
+    /// The width and the height of the image, in millimeters.
+    pub fn physical_size_mm(&self) -> Option<(u32, u32)> {
+        self.physical_size_mm
+    }
+
+    /// The native mode of the monitor.
+    pub fn preferred_mode(&self) -> Option<Mode> {
+        self.preferred_mode
+    }
+
+    /// The bits per color component the monitor takes, which EDID 1.4 tells
+    /// for digital inputs.
+    pub fn bits_per_component(&self) -> Option<u8> {
+        self.bits_per_component
+    }
+
+    /// The HDR capabilities of the monitor, or `None` for an SDR monitor.
+    pub fn hdr_static_metadata(&self) -> Option<HdrStaticMetadata> {
+        self.hdr_static_metadata
+    }
+
+    pub fn parse(edid: &[u8]) -> Result<Self, EdidError> {
+        let base = edid.get(..BLOCK_LEN).ok_or(EdidError::TooShort)?;
+
+        if base[..HEADER.len()] != HEADER {
+            return Err(EdidError::InvalidHeader);
+        }
+
+        if !has_valid_checksum(base) {
+            return Err(EdidError::InvalidChecksum);
+        }
+
+        let descriptors = base[54..126].chunks_exact(DESCRIPTOR_LEN);
+        let display_descriptor = |tag: u8| {
+            descriptors
+                .clone()
+                .find(|descriptor| descriptor[..3] == [0, 0, 0] && descriptor[3] == tag)
+                .map(|descriptor| descriptor_text(&descriptor[5..]))
+                .filter(|text| !text.is_empty())
+        };
+
+        let serial_number = u32::from_le_bytes([base[12], base[13], base[14], base[15]]);
+        // 6 bits and up in steps of 2, from 1 in bits 6 to 4 of the video
+        // input definition, when its top bit is set for a digital input
+        let bit_depth = (base[20] >> 4) & 0x07;
+        let version = (base[18], base[19]);
+
+        let mut parsed = Self {
+            version,
+            manufacturer: pnp_id([base[8], base[9]]),
+            product_code: u16::from_le_bytes([base[10], base[11]]),
+            serial_number: display_descriptor(SERIAL_NUMBER_DESCRIPTOR)
+                .or_else(|| (serial_number != 0).then(|| serial_number.to_string())),
+            model_name: display_descriptor(MODEL_NAME_DESCRIPTOR),
+            manufacture_date: manufacture_date(base[16], 1990 + u16::from(base[17])),
+            physical_size_mm: None,
+            preferred_mode: None,
+            bits_per_component: (version >= (1, 4)
+                && base[20] & 0x80 != 0
+                && (1..=6).contains(&bit_depth))
+            .then(|| 4 + bit_depth * 2),
+            hdr_static_metadata: None,
+        };
+
+        // the first detailed timing is the preferred one since EDID 1.3
+        let mut preferred_timing = descriptors.clone().find_map(DetailedTiming::parse);
+        let mut displayid_size_mm = None;
+
+        let extension_count = usize::from(base[126]);
+        let extensions = edid[BLOCK_LEN..]
+            .chunks_exact(BLOCK_LEN)
+            .take(extension_count)
+            .filter(|block| has_valid_checksum(block));
+
+        for block in extensions {
+            match block[0] {
+                CTA_EXTENSION => {
+                    preferred_timing = preferred_timing.or_else(|| cta_timings(block).next());
+                    parsed.hdr_static_metadata = parsed
+                        .hdr_static_metadata
+                        .or_else(|| cta_hdr_static_metadata(block));
+                }
+                DISPLAYID_EXTENSION => {
+                    let displayid = DisplayId::parse(block);
+
+                    parsed.model_name = parsed.model_name.or(displayid.model_name);
+                    displayid_size_mm = displayid_size_mm.or(displayid.size_mm);
+                    preferred_timing = preferred_timing.or(displayid.preferred_timing);
+                }
+                _ => {}
+            }
+        }
+
+        // the base block has the size in centimeters, or the aspect ratio
+        // when one of them is 0
+        let base_size_mm = (base[21] != 0 && base[22] != 0)
+            .then(|| (u32::from(base[21]) * 10, u32::from(base[22]) * 10));
+
+        parsed.physical_size_mm = preferred_timing
+            .and_then(|timing| timing.size_mm)
+            .or(displayid_size_mm)
+            .or(base_size_mm);
+        parsed.preferred_mode = preferred_timing.map(|timing| timing.mode);
+
+        Ok(parsed)
+    }
+}
+
+fn has_valid_checksum(block: &[u8]) -> bool {
+    block.iter().fold(0u8, |sum, byte| sum.wrapping_add(*byte)) == 0
+}
+
+/// The three letters of a PNP id, packed in 5 bits each from `A` = 1.
+fn pnp_id(bytes: [u8; 2]) -> String {
+    let id = u16::from_be_bytes(bytes);
+
+    [10, 5, 0]
+        .iter()
+        .map(|shift| char::from(b'@' + ((id >> shift) & 0x1f) as u8))
+        .collect()
+}
+
+fn manufacture_date(week: u8, year: u16) -> Option<ManufactureDate> {
+    // a year before the first EDID means it wasn't set
+    (year > 1990).then_some(ManufactureDate {
+        week: (1..=54).contains(&week).then_some(week),
+        year,
+        is_model_year: week == 0xff,
+    })
+}
+
+/// The text of a display descriptor, which ends with a line feed and is
+/// padded with spaces.
+fn descriptor_text(bytes: &[u8]) -> String {
+    let text = bytes
+        .split(|byte| *byte == b'\n')
+        .next()
+        .unwrap_or_default();
+
+    String::from_utf8_lossy(text).trim().to_string()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct DetailedTiming {
+    mode: Mode,
+    size_mm: Option<(u32, u32)>,
+}
+
+impl DetailedTiming {
+    /// The timing of a detailed timing descriptor, which display descriptors
+    /// share the room of, with a pixel clock of 0.
+    fn parse(bytes: &[u8]) -> Option<Self> {
+        let pixel_clock = u16::from_le_bytes([bytes[0], bytes[1]]);
+        if pixel_clock == 0 {
+            return None;
+        }
+
+        let with_high_bits = |low: u8, high: u8| u32::from(low) | (u32::from(high) << 8);
+        let width = with_high_bits(bytes[2], bytes[4] >> 4);
+        let h_blank = with_high_bits(bytes[3], bytes[4] & 0x0f);
+        let height = with_high_bits(bytes[5], bytes[7] >> 4);
+        let v_blank = with_high_bits(bytes[6], bytes[7] & 0x0f);
+        let width_mm = with_high_bits(bytes[12], bytes[14] >> 4);
+        let height_mm = with_high_bits(bytes[13], bytes[14] & 0x0f);
+
+        Some(Self {
+            mode: Mode {
+                width,
+                height,
+                refresh_rate: refresh_rate(
+                    f64::from(pixel_clock) * 10_000.0,
+                    width + h_blank,
+                    height + v_blank,
+                )?,
+            },
+            size_mm: (width_mm != 0 && height_mm != 0).then_some((width_mm, height_mm)),
+        })
+    }
+}
+
+fn refresh_rate(pixel_clock_hz: f64, h_total: u32, v_total: u32) -> Option<f64> {
+    let pixels = f64::from(h_total) * f64::from(v_total);
+
+    (pixels > 0.0).then(|| pixel_clock_hz / pixels)
+}
+
+/// The detailed timings of a CTA-861 extension, which follow its data blocks.
+fn cta_timings(block: &[u8]) -> impl Iterator<Item = DetailedTiming> + '_ {
+    // an offset of 0 means there are neither data blocks nor timings
+    let timings = match usize::from(block[2]) {
+        start @ 4..=127 => &block[start..BLOCK_LEN - 1],
+        _ => &[],
+    };
+
+    timings
+        .chunks_exact(DESCRIPTOR_LEN)
+        .map_while(DetailedTiming::parse)
+}
+
+/// The HDR static metadata block of a CTA-861 extension, among its data
+/// blocks.
+fn cta_hdr_static_metadata(block: &[u8]) -> Option<HdrStaticMetadata> {
+    let data_end = usize::from(block[2]).clamp(4, BLOCK_LEN - 1);
+    let mut data_blocks = &block[4..data_end];
+
+    // each data block is a header with its tag in the top 3 bits and the
+    // length of the payload in the others, and the payload
+    while let [header, rest @ ..] = data_blocks {
+        let Some(payload) = rest.get(..usize::from(header & 0x1f)) else {
+            break;
+        };
+        data_blocks = &rest[payload.len()..];
+
+        // an extended data block has its own tag in the first byte
+        if header >> 5 == CTA_EXTENDED_TAG && payload.first() == Some(&CTA_HDR_STATIC_METADATA) {
+            return HdrStaticMetadata::parse(&payload[1..]);
+        }
+    }
+
+    None
+}
+
+/// What a DisplayID extension tells.
+#[derive(Debug, Default)]
+struct DisplayId {
+    model_name: Option<String>,
+    size_mm: Option<(u32, u32)>,
+    preferred_timing: Option<DetailedTiming>,
+}
+
+impl DisplayId {
+    fn parse(block: &[u8]) -> Self {
+        let mut displayid = Self::default();
+        let section_end = (5 + usize::from(block[2])).min(BLOCK_LEN - 1);
+        let mut data_blocks = block.get(5..section_end).unwrap_or_default();
+        let mut first_timing = None;
+
+        // each data block is a tag, a revision, the length of the payload
+        // and the payload
+        while let [tag, revision, len, rest @ ..] = data_blocks {
+            let Some(payload) = rest.get(..usize::from(*len)) else {
+                break;
+            };
+            data_blocks = &rest[payload.len()..];
+
+            match *tag {
+                tag if DISPLAYID_PRODUCT_ID.contains(&tag) => {
+                    // OUI, product code, serial number, week, year, name length, name
+                    let name = payload
+                        .get(11)
+                        .and_then(|name_len| payload.get(12..12 + usize::from(*name_len)));
+
+                    if let Some(name) = name.map(descriptor_text).filter(|name| !name.is_empty()) {
+                        displayid.model_name = Some(name);
+                    }
+                }
+                tag if DISPLAYID_DISPLAY_PARAMETERS.contains(&tag) => {
+                    if payload.len() < 4 {
+                        continue;
+                    }
+
+                    // in tenths of millimeters, or in millimeters when 2.x
+                    // sets the top bit of the revision
+                    let unit = if tag == DISPLAYID_DISPLAY_PARAMETERS[1] && revision & 0x80 != 0 {
+                        1.0
+                    } else {
+                        0.1
+                    };
+                    let width = f64::from(u16::from_le_bytes([payload[0], payload[1]])) * unit;
+                    let height = f64::from(u16::from_le_bytes([payload[2], payload[3]])) * unit;
+
+                    displayid.size_mm = (width >= 1.0 && height >= 1.0)
+                        .then(|| (width.round() as u32, height.round() as u32));
+                }
+                DISPLAYID_TYPE_I_TIMING | DISPLAYID_TYPE_VII_TIMING => {
+                    // Type I counts the pixel clock in 10 kHz, Type VII in kHz
+                    let clock_unit = if *tag == DISPLAYID_TYPE_I_TIMING {
+                        10_000.0
+                    } else {
+                        1_000.0
+                    };
+
+                    for descriptor in payload.chunks_exact(20) {
+                        let Some((timing, preferred)) = displayid_timing(descriptor, clock_unit)
+                        else {
+                            continue;
+                        };
+
+                        first_timing = first_timing.or(Some(timing));
+                        if preferred && displayid.preferred_timing.is_none() {
+                            displayid.preferred_timing = Some(timing);
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        displayid.preferred_timing = displayid.preferred_timing.or(first_timing);
+
+        displayid
+    }
+}
+
+/// A timing of a DisplayID Type I or VII block, and whether it's preferred.
+/// Its values are stored minus 1.
+fn displayid_timing(bytes: &[u8], clock_unit: f64) -> Option<(DetailedTiming, bool)> {
+    let value =
+        |offset: usize| u32::from(u16::from_le_bytes([bytes[offset], bytes[offset + 1]])) + 1;
+    let pixel_clock = u32::from_le_bytes([bytes[0], bytes[1], bytes[2], 0]) + 1;
+
+    let width = value(4);
+    let height = value(12);
+
+    Some((
+        DetailedTiming {
+            mode: Mode {
+                width,
+                height,
+                refresh_rate: refresh_rate(
+                    f64::from(pixel_clock) * clock_unit,
+                    width + value(6),
+                    height + value(14),
+                )?,
+            },
+            size_mm: None,
+        },
+        bytes[3] & 0x80 != 0,
+    ))
+}
+
+/// FNV-1a from `basis`, to spread the EDID over the 128 bits of a UUID.
+fn fnv1a(basis: u64, bytes: &[u8]) -> u64 {
+    bytes.iter().fold(basis, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+/// A UUID identifying the monitor by its EDID base block, which holds its
+/// manufacturer, product code and serial number, so it stays the same across
+/// ports and reboots. It is formatted like the UUIDs of macOS displays.
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+pub(crate) fn edid_uuid(edid: &[u8]) -> Option<String> {
+    let base = edid.get(..BLOCK_LEN)?;
+
+    if base[..HEADER.len()] != HEADER {
+        return None;
+    }
+
+    let high = fnv1a(0xcbf2_9ce4_8422_2325, base);
+    let low = fnv1a(0x6c62_272e_07bb_0142, base);
+    let mut bytes = [0; 16];
+    bytes[..8].copy_from_slice(&high.to_be_bytes());
+    bytes[8..].copy_from_slice(&low.to_be_bytes());
+
+    // version 8 (custom) and the RFC 4122 variant
+    bytes[6] = (bytes[6] & 0x0f) | 0x80;
+    bytes[8] = (bytes[8] & 0x3f) | 0x80;
+
+    let hex: String = bytes.iter().map(|byte| format!("{byte:02X}")).collect();
+
+    Some(format!(
+        "{}-{}-{}-{}-{}",
+        &hex[..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..]
+    ))
+}
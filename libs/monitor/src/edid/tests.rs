@@ -0,0 +1,117 @@
+#![cfg(test)]
+
+use super::{Edid, EdidError};
+
+// EDIDs laid out like the ones of these monitors, with their checksums
+const DELL_U2720Q: &[u8] = include_bytes!("fixtures/dell-u2720q.bin");
+const BOE_NE160QDM: &[u8] = include_bytes!("fixtures/boe-ne160qdm.bin");
+const APPLE_STUDIO_DISPLAY: &[u8] = include_bytes!("fixtures/apple-studio-display.bin");
+
+#[test]
+fn it_parses_the_base_block() {
+    let edid = Edid::parse(DELL_U2720Q).unwrap();
+
+    assert_eq!(edid.version(), (1, 4));
+    assert_eq!(edid.manufacturer(), "DEL");
+    assert_eq!(edid.product_code(), 0xa0ee);
+    // the serial number descriptor wins over the number of the base block
+    assert_eq!(edid.serial_number().map(String::as_str), Some("8D1LX53"));
+    assert_eq!(edid.model_name().map(String::as_str), Some("DELL U2720Q"));
+
+    let date = edid.manufacture_date().unwrap();
+    assert_eq!(date.week(), Some(23));
+    assert_eq!(date.year(), 2021);
+    assert!(!date.is_model_year());
+
+    // the millimeters of the preferred timing, rather than the centimeters
+    assert_eq!(edid.physical_size_mm(), Some((597, 336)));
+
+    // the CTA-861 extension has a 1440p timing, but the base block's is preferred
+    let mode = edid.preferred_mode().unwrap();
+    assert_eq!((mode.width(), mode.height()), (3840, 2160));
+    assert!((mode.refresh_rate() - 59.997).abs() < 0.001);
+
+    assert_eq!(edid.bits_per_component(), Some(10));
+}
+
+#[test]
+fn it_parses_hdr_static_metadata() {
+    let hdr = Edid::parse(DELL_U2720Q)
+        .unwrap()
+        .hdr_static_metadata()
+        .unwrap();
+
+    assert!(hdr.supports_pq());
+    assert!(!hdr.supports_hlg());
+    // coded as 98 for 50 * 2^(98 / 32)
+    assert!((hdr.max_luminance().unwrap() - 417.7).abs() < 0.1);
+    assert!((hdr.max_frame_average_luminance().unwrap() - 417.7).abs() < 0.1);
+    assert_eq!(hdr.min_luminance(), Some(0.0));
+
+    // SDR monitors have no HDR static metadata block
+    let edid = Edid::parse(APPLE_STUDIO_DISPLAY).unwrap();
+    assert_eq!(edid.hdr_static_metadata(), None);
+}
+
+#[test]
+fn it_parses_panels_without_names() {
+    let edid = Edid::parse(BOE_NE160QDM).unwrap();
+
+    assert_eq!(edid.manufacturer(), "BOE");
+    assert_eq!(edid.product_code(), 0x0a4b);
+    assert_eq!(edid.serial_number(), None);
+    // unspecified text isn't the model name
+    assert_eq!(edid.model_name(), None);
+
+    let date = edid.manufacture_date().unwrap();
+    assert_eq!(date.week(), None);
+    assert_eq!(date.year(), 2022);
+
+    assert_eq!(edid.physical_size_mm(), Some((344, 215)));
+    assert_eq!(edid.bits_per_component(), Some(8));
+
+    let mode = edid.preferred_mode().unwrap();
+    assert_eq!((mode.width(), mode.height()), (2560, 1600));
+    assert!((mode.refresh_rate() - 59.97).abs() < 0.01);
+}
+
+#[test]
+fn it_parses_displayid_extensions() {
+    let edid = Edid::parse(APPLE_STUDIO_DISPLAY).unwrap();
+
+    assert_eq!(edid.manufacturer(), "APP");
+    assert_eq!(
+        edid.model_name().map(String::as_str),
+        Some("Studio Display")
+    );
+
+    let date = edid.manufacture_date().unwrap();
+    assert_eq!(date.year(), 2020);
+    assert!(date.is_model_year());
+
+    // in tenths of millimeters in the display parameters block
+    assert_eq!(edid.physical_size_mm(), Some((597, 336)));
+
+    let mode = edid.preferred_mode().unwrap();
+    assert_eq!((mode.width(), mode.height()), (5120, 2880));
+    assert!((mode.refresh_rate() - 60.009).abs() < 0.001);
+}
+
+#[test]
+fn it_rejects_invalid_edids() {
+    assert_eq!(Edid::parse(&DELL_U2720Q[..100]), Err(EdidError::TooShort));
+    assert_eq!(Edid::parse(&[0; 128]), Err(EdidError::InvalidHeader));
+
+    let mut corrupted = DELL_U2720Q.to_vec();
+    corrupted[20] ^= 0xff;
+    assert_eq!(Edid::parse(&corrupted), Err(EdidError::InvalidChecksum));
+
+    // a corrupted extension is skipped
+    let mut corrupted = APPLE_STUDIO_DISPLAY.to_vec();
+    corrupted[140] ^= 0xff;
+    let edid = Edid::parse(&corrupted).unwrap();
+    assert_eq!(edid.model_name(), None);
+    assert_eq!(edid.preferred_mode(), None);
+    // the base block has the size in centimeters
+    assert_eq!(edid.physical_size_mm(), Some((600, 340)));
+}
@@ -0,0 +1,181 @@
+//! Events telling when monitors are plugged, unplugged or reconfigured, so
+//! apps don't have to poll `get_monitors`.
+
+use std::io;
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::{get_monitors, Monitor};
+
+#[cfg(target_os = "linux")]
+use crate::linux::watcher::Watcher;
+#[cfg(target_os = "macos")]
+use crate::macos::watcher::Watcher;
+
+mod tests;
+
+/// The name of the Tauri event `emit_monitor_events` emits.
+#[cfg(feature = "tauri-events")]
+pub const MONITOR_EVENT: &str = "monitor://changed";
+
+// events are rare, and matching monitors is simpler without boxes
+#[allow(clippy::large_enum_variant)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum MonitorEvent {
+    /// A monitor was plugged in, or turned on.
+    Added(Monitor),
+    /// A monitor was unplugged, or turned off.
+    Removed(Monitor),
+    /// Anything about a monitor changed, e.g. its position, size, scale
+    /// factor, visible area, primary state, refresh rate or HDR support. The
+    /// cursor moving to another monitor isn't a change.
+    Changed { before: Monitor, after: Monitor },
+}
+
+#[derive(Error, Debug)]
+#[non_exhaustive]
+pub enum WatchMonitorsError {
+    /// There's no display server telling when monitors change, e.g. in a
+    /// Wayland session without XWayland, or watching isn't implemented for
+    /// the OS yet, as on Windows.
+    #[error("no display server notifies of monitor changes")]
+    Unsupported,
+    #[error("failed to start watching monitors: {0}")]
+    Io(#[from] io::Error),
+}
+
+/// Whether another monitor of `monitors` has the UUID of `monitor`, as
+/// identical monitors without serial numbers do.
+fn shares_uuid(monitor: &Monitor, monitors: &[Monitor]) -> bool {
+    monitor.uuid().is_some()
+        && monitors
+            .iter()
+            .filter(|other| other.uuid() == monitor.uuid())
+            .count()
+            > 1
+}
+
+/// Whether two snapshots are of the same monitor: the one with the same UUID,
+/// which is stable across ports and reboots, or else with the same id. The
+/// ids also tell apart the monitors sharing a UUID in `before` or `after`.
+fn is_same_monitor(a: &Monitor, b: &Monitor, before: &[Monitor], after: &[Monitor]) -> bool {
+    match (a.uuid(), b.uuid()) {
+        (Some(uuid), Some(other)) if uuid == other => {
+            a.id() == b.id() || !(shares_uuid(a, before) || shares_uuid(a, after))
+        }
+        (Some(_), Some(_)) => false,
+        _ => a.id() == b.id(),
+    }
+}
+
+fn is_reconfigured(before: &Monitor, after: &Monitor) -> bool {
+    Monitor {
+        has_cursor: after.has_cursor,
+        ..before.clone()
+    } != *after
+}
+
+/// The events turning the monitors `before` into the monitors `after`: the
+/// removed ones first, then the changed and the added ones in the order of
+/// `after`.
+pub fn diff_monitors(before: &[Monitor], after: &[Monitor]) -> Vec<MonitorEvent> {
+    let removed = before
+        .iter()
+        .filter(|monitor| {
+            !after
+                .iter()
+                .any(|other| is_same_monitor(other, monitor, before, after))
+        })
+        .map(|monitor| MonitorEvent::Removed(monitor.clone()));
+
+    let added_or_changed = after.iter().filter_map(|monitor| {
+        match before
+            .iter()
+            .find(|other| is_same_monitor(other, monitor, before, after))
+        {
+            None => Some(MonitorEvent::Added(monitor.clone())),
+            Some(previous) if is_reconfigured(previous, monitor) => Some(MonitorEvent::Changed {
+                before: previous.clone(),
+                after: monitor.clone(),
+            }),
+            Some(_) => None,
+        }
+    });
+
+    removed.chain(added_or_changed).collect()
+}
+
+/// Keeps the last snapshot of the monitors to hand the handler the events of
+/// the next one.
+pub(crate) struct Differ {
+    monitors: Vec<Monitor>,
+    handler: Box<dyn FnMut(MonitorEvent) + Send>,
+}
+
+impl Differ {
+    fn new(handler: impl FnMut(MonitorEvent) + Send + 'static) -> Self {
+        Self {
+            monitors: get_monitors(),
+            handler: Box::new(handler),
+        }
+    }
+
+    pub(crate) fn update(&mut self, monitors: Vec<Monitor>) {
+        for event in diff_monitors(&self.monitors, &monitors) {
+            (self.handler)(event);
+        }
+
+        self.monitors = monitors;
+    }
+}
+
+/// Calls its handler when monitors change, until it's dropped.
+pub struct MonitorWatcher {
+    #[cfg(any(target_os = "linux", target_os = "macos"))]
+    _watcher: Watcher,
+}
+
+impl MonitorWatcher {
+    /// Stops watching the monitors, which dropping the watcher also does.
+    pub fn stop(self) {}
+}
+
+/// Calls `handler` with the events of the monitors being plugged, unplugged
+/// or reconfigured, from another thread on Linux and from the main thread on
+/// macOS, whose run loop must be running, as it is in a Tauri app.
+///
+/// On Linux, RandR tells when the monitors change, including XWayland in a
+/// Wayland session. On Windows this fails with
+/// [`WatchMonitorsError::Unsupported`] for now.
+pub fn watch_monitors<F>(handler: F) -> Result<MonitorWatcher, WatchMonitorsError>
+where
+    F: FnMut(MonitorEvent) + Send + 'static,
+{
+    #[cfg(not(any(target_os = "linux", target_os = "macos")))]
+    {
+        let _ = handler;
+        Err(WatchMonitorsError::Unsupported)
+    }
+
+    #[cfg(any(target_os = "linux", target_os = "macos"))]
+    {
+        Ok(MonitorWatcher {
+            _watcher: Watcher::new(Differ::new(handler))?,
+        })
+    }
+}
+
+/// Emits the events of `watch_monitors` to the frontend as `MONITOR_EVENT`,
+/// with the `MonitorEvent` as payload, until the watcher is dropped.
+#[cfg(feature = "tauri-events")]
+pub fn emit_monitor_events<R: tauri::Runtime>(
+    app: tauri::AppHandle<R>,
+) -> Result<MonitorWatcher, WatchMonitorsError> {
+    use tauri::Emitter;
+
+    watch_monitors(move |event| {
+        let _ = app.emit(MONITOR_EVENT, event);
+    })
+}
@@ -0,0 +1,107 @@
+#![cfg(test)]
+
+use std::slice;
+
+use super::{diff_monitors, MonitorEvent};
+use crate::Monitor;
+
+fn monitor(id: u32, uuid: Option<&str>, x: f64) -> Monitor {
+    let monitor = Monitor::test(id)
+        .with_name(&format!("DP-{id}"))
+        .with_rect(x, 0.0, 1920.0, 1080.0)
+        .with_primary(x == 0.0);
+
+    match uuid {
+        Some(uuid) => monitor.with_uuid(uuid),
+        None => monitor,
+    }
+}
+
+#[test]
+fn it_diffs_monitors() {
+    let laptop = monitor(1, Some("A"), 0.0);
+    let external = monitor(2, Some("B"), 1920.0);
+
+    assert!(diff_monitors(slice::from_ref(&laptop), slice::from_ref(&laptop)).is_empty());
+
+    assert_eq!(
+        diff_monitors(
+            slice::from_ref(&laptop),
+            &[laptop.clone(), external.clone()]
+        ),
+        vec![MonitorEvent::Added(external.clone())]
+    );
+    assert_eq!(
+        diff_monitors(
+            &[laptop.clone(), external.clone()],
+            slice::from_ref(&laptop)
+        ),
+        vec![MonitorEvent::Removed(external.clone())]
+    );
+
+    // the external monitor moves to the left of the laptop, and becomes primary
+    let moved_laptop = monitor(1, Some("A"), 1920.0);
+    let moved_external = monitor(2, Some("B"), 0.0);
+    assert_eq!(
+        diff_monitors(
+            &[laptop.clone(), external.clone()],
+            &[moved_laptop.clone(), moved_external.clone()]
+        ),
+        vec![
+            MonitorEvent::Changed {
+                before: laptop.clone(),
+                after: moved_laptop
+            },
+            MonitorEvent::Changed {
+                before: external,
+                after: moved_external
+            },
+        ]
+    );
+
+    // the cursor moving isn't a change
+    let laptop_with_cursor = laptop.clone().with_cursor(true);
+    assert!(diff_monitors(&[laptop], &[laptop_with_cursor]).is_empty());
+}
+
+#[test]
+fn it_matches_monitors_by_uuid_then_id() {
+    // plugged in another port, the monitor gets another id but keeps its uuid
+    let before = monitor(2, Some("B"), 1920.0);
+    let after = monitor(3, Some("B"), 1920.0);
+    assert!(matches!(
+        diff_monitors(slice::from_ref(&before), slice::from_ref(&after)).as_slice(),
+        [MonitorEvent::Changed { .. }]
+    ));
+
+    // another monitor in the same port
+    let other = monitor(2, Some("C"), 1920.0);
+    assert_eq!(
+        diff_monitors(slice::from_ref(&before), slice::from_ref(&other)),
+        vec![MonitorEvent::Removed(before), MonitorEvent::Added(other)]
+    );
+
+    // without uuids, the ids are compared
+    let before = monitor(2, None, 1920.0);
+    let after = monitor(2, None, 0.0);
+    assert!(matches!(
+        diff_monitors(&[before], &[after]).as_slice(),
+        [MonitorEvent::Changed { .. }]
+    ));
+}
+
+#[test]
+fn it_tells_apart_monitors_sharing_a_uuid() {
+    // identical monitors without serial numbers have the same uuid
+    let left = monitor(2, Some("B"), 0.0);
+    let right = monitor(3, Some("B"), 1920.0);
+
+    assert_eq!(
+        diff_monitors(&[left.clone(), right.clone()], slice::from_ref(&left)),
+        vec![MonitorEvent::Removed(right.clone())]
+    );
+    assert_eq!(
+        diff_monitors(slice::from_ref(&left), &[left.clone(), right.clone()]),
+        vec![MonitorEvent::Added(right)]
+    );
+}
@@ -0,0 +1,228 @@
+//! The geometry of the monitors as a whole, e.g. to find the monitor a window
+//! is on, or where to move it to.
+
+use dpi::{PhysicalPosition, PhysicalSize};
+use serde::{Deserialize, Serialize};
+
+use crate::{get_monitors, Monitor};
+
+mod tests;
+
+/// How far apart the edges of two monitors can be for them to be neighbours,
+/// as fractional scales can leave a pixel between them.
+const EDGE_TOLERANCE: f64 = 1.0;
+
+/// A rectangle of the virtual desktop, in physical pixels.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct Rect {
+    position: PhysicalPosition<f64>,
+    size: PhysicalSize<f64>,
+}
+
+impl Rect {
+    pub fn new(position: PhysicalPosition<f64>, size: PhysicalSize<f64>) -> Self {
+        Self { position, size }
+    }
+
+    pub fn position(&self) -> PhysicalPosition<f64> {
+        self.position
+    }
+
+    pub fn size(&self) -> PhysicalSize<f64> {
+        self.size
+    }
+
+    pub fn left(&self) -> f64 {
+        self.position.x
+    }
+
+    pub fn top(&self) -> f64 {
+        self.position.y
+    }
+
+    pub fn right(&self) -> f64 {
+        self.position.x + self.size.width
+    }
+
+    pub fn bottom(&self) -> f64 {
+        self.position.y + self.size.height
+    }
+
+    /// Whether the point is in the rectangle, whose right and bottom edges
+    /// belong to the next one.
+    pub fn contains(&self, point: PhysicalPosition<f64>) -> bool {
+        point.x >= self.left()
+            && point.x < self.right()
+            && point.y >= self.top()
+            && point.y < self.bottom()
+    }
+
+    pub fn intersection(&self, other: &Rect) -> Option<Rect> {
+        let left = self.left().max(other.left());
+        let top = self.top().max(other.top());
+        let right = self.right().min(other.right());
+        let bottom = self.bottom().min(other.bottom());
+
+        (right > left && bottom > top).then(|| {
+            Rect::new(
+                PhysicalPosition::new(left, top),
+                PhysicalSize::new(right - left, bottom - top),
+            )
+        })
+    }
+
+    pub fn union(&self, other: &Rect) -> Rect {
+        let left = self.left().min(other.left());
+        let top = self.top().min(other.top());
+
+        Rect::new(
+            PhysicalPosition::new(left, top),
+            PhysicalSize::new(
+                self.right().max(other.right()) - left,
+                self.bottom().max(other.bottom()) - top,
+            ),
+        )
+    }
+
+    /// How far the point is from the nearest edge, or 0 when it's inside.
+    pub fn distance_to(&self, point: PhysicalPosition<f64>) -> f64 {
+        let dx = (self.left() - point.x).max(point.x - self.right()).max(0.0);
+        let dy = (self.top() - point.y).max(point.y - self.bottom()).max(0.0);
+
+        dx.hypot(dy)
+    }
+}
+
+/// A side of a monitor.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Direction {
+    Left,
+    Right,
+    Above,
+    Below,
+}
+
+/// A snapshot of the monitors, to answer questions about where they are.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct MonitorLayout {
+    monitors: Vec<Monitor>,
+}
+
+impl From<Vec<Monitor>> for MonitorLayout {
+    fn from(monitors: Vec<Monitor>) -> Self {
+        Self::new(monitors)
+    }
+}
+
+impl MonitorLayout {
+    pub fn new(monitors: Vec<Monitor>) -> Self {
+        Self { monitors }
+    }
+
+    /// The layout of the monitors connected now.
+    pub fn current() -> Self {
+        Self::new(get_monitors())
+    }
+
+    pub fn monitors(&self) -> &[Monitor] {
+        &self.monitors
+    }
+
+    /// The monitor the point is on.
+    pub fn monitor_at(&self, point: PhysicalPosition<f64>) -> Option<&Monitor> {
+        self.monitors
+            .iter()
+            .find(|monitor| monitor.rect().contains(point))
+    }
+
+    /// The monitor most of the rectangle, e.g. a window, is on, or `None`
+    /// when it's on none of them.
+    pub fn monitor_with_largest_overlap(&self, rect: &Rect) -> Option<&Monitor> {
+        self.monitors
+            .iter()
+            .filter_map(|monitor| {
+                let overlap = monitor.rect().intersection(rect)?;
+
+                Some((monitor, overlap.size.width * overlap.size.height))
+            })
+            .fold(
+                None,
+                |largest: Option<(&Monitor, f64)>, (monitor, area)| match largest {
+                    Some((_, largest_area)) if largest_area >= area => largest,
+                    _ => Some((monitor, area)),
+                },
+            )
+            .map(|(monitor, _)| monitor)
+    }
+
+    /// The monitor whose nearest edge is the closest to the point, which is
+    /// the monitor it's on when there is one.
+    pub fn nearest_monitor(&self, point: PhysicalPosition<f64>) -> Option<&Monitor> {
+        self.monitors
+            .iter()
+            .map(|monitor| (monitor, monitor.rect().distance_to(point)))
+            .fold(
+                None,
+                |nearest: Option<(&Monitor, f64)>, (monitor, distance)| match nearest {
+                    Some((_, nearest_distance)) if nearest_distance <= distance => nearest,
+                    _ => Some((monitor, distance)),
+                },
+            )
+            .map(|(monitor, _)| monitor)
+    }
+
+    /// The bounding box of the monitors, which is the size of the virtual
+    /// desktop, including the parts no monitor shows.
+    pub fn bounds(&self) -> Option<Rect> {
+        self.monitors
+            .iter()
+            .map(Monitor::rect)
+            .reduce(|bounds, rect| bounds.union(&rect))
+    }
+
+    /// The monitors touching the side of `monitor` in `direction`, from the
+    /// top or the left.
+    pub fn neighbours(&self, monitor: &Monitor, direction: Direction) -> Vec<&Monitor> {
+        let rect = monitor.rect();
+        let overlaps = |start: f64, end: f64, other_start: f64, other_end: f64| {
+            start.max(other_start) < end.min(other_end)
+        };
+        let touches = |edge: f64, other_edge: f64| (edge - other_edge).abs() <= EDGE_TOLERANCE;
+
+        let mut neighbours: Vec<_> = self
+            .monitors
+            .iter()
+            .filter(|other| *other != monitor)
+            .filter(|other| {
+                let other = other.rect();
+
+                match direction {
+                    Direction::Left => {
+                        touches(rect.left(), other.right())
+                            && overlaps(rect.top(), rect.bottom(), other.top(), other.bottom())
+                    }
+                    Direction::Right => {
+                        touches(rect.right(), other.left())
+                            && overlaps(rect.top(), rect.bottom(), other.top(), other.bottom())
+                    }
+                    Direction::Above => {
+                        touches(rect.top(), other.bottom())
+                            && overlaps(rect.left(), rect.right(), other.left(), other.right())
+                    }
+                    Direction::Below => {
+                        touches(rect.bottom(), other.top())
+                            && overlaps(rect.left(), rect.right(), other.left(), other.right())
+                    }
+                }
+            })
+            .collect();
+
+        neighbours.sort_by(|a, b| match direction {
+            Direction::Left | Direction::Right => a.position.y.total_cmp(&b.position.y),
+            Direction::Above | Direction::Below => a.position.x.total_cmp(&b.position.x),
+        });
+
+        neighbours
+    }
+}
@@ -0,0 +1,136 @@
+#![cfg(test)]
+
+use dpi::{PhysicalPosition, PhysicalSize};
+
+use super::{Direction, MonitorLayout, Rect};
+use crate::Monitor;
+
+fn monitor(id: u32, x: f64, y: f64, width: f64, height: f64) -> Monitor {
+    Monitor::test(id)
+        .with_rect(x, y, width, height)
+        .with_primary(id == 1)
+}
+
+fn rect(x: f64, y: f64, width: f64, height: f64) -> Rect {
+    Rect::new(
+        PhysicalPosition::new(x, y),
+        PhysicalSize::new(width, height),
+    )
+}
+
+fn ids(monitors: Vec<&Monitor>) -> Vec<u32> {
+    monitors.into_iter().map(Monitor::id).collect()
+}
+
+/// A laptop (1) with a 4K monitor (2) at its right, aligned on their bottom
+/// edges, two 1080p monitors (3 and 4) side by side above the 4K one, and a
+/// gap at the left of the laptop before a portrait monitor (5).
+fn layout() -> MonitorLayout {
+    MonitorLayout::new(vec![
+        monitor(1, 0.0, 1080.0, 2560.0, 1600.0),
+        monitor(2, 2560.0, 520.0, 3840.0, 2160.0),
+        monitor(3, 2560.0, -560.0, 1920.0, 1080.0),
+        monitor(4, 4480.0, -560.0, 1920.0, 1080.0),
+        monitor(5, -1280.0, 0.0, 1080.0, 1920.0),
+    ])
+}
+
+#[test]
+fn it_finds_the_monitor_at_a_point() {
+    let layout = layout();
+    let at = |x, y| {
+        layout
+            .monitor_at(PhysicalPosition::new(x, y))
+            .map(Monitor::id)
+    };
+
+    assert_eq!(at(0.0, 1080.0), Some(1));
+    assert_eq!(at(2559.0, 2679.0), Some(1));
+    // the right edge belongs to the next monitor
+    assert_eq!(at(2560.0, 1080.0), Some(2));
+    assert_eq!(at(-1000.0, 100.0), Some(5));
+    assert_eq!(at(-100.0, 100.0), None);
+    assert_eq!(at(0.0, 0.0), None);
+}
+
+#[test]
+fn it_finds_the_monitor_with_the_largest_overlap() {
+    let layout = layout();
+    let overlap = |rect: Rect| layout.monitor_with_largest_overlap(&rect).map(Monitor::id);
+
+    assert_eq!(overlap(rect(100.0, 1200.0, 800.0, 600.0)), Some(1));
+    // more of the window is on the 4K monitor
+    assert_eq!(overlap(rect(2300.0, 1200.0, 800.0, 600.0)), Some(2));
+    // ties go to the first monitor
+    assert_eq!(overlap(rect(2300.0, 1200.0, 520.0, 600.0)), Some(1));
+    // a window straddling the two monitors above
+    assert_eq!(overlap(rect(4000.0, -400.0, 800.0, 200.0)), Some(3));
+    assert_eq!(overlap(rect(-150.0, 0.0, 100.0, 100.0)), None);
+}
+
+#[test]
+fn it_finds_the_nearest_monitor() {
+    let layout = layout();
+    let nearest = |x, y| {
+        layout
+            .nearest_monitor(PhysicalPosition::new(x, y))
+            .map(Monitor::id)
+    };
+
+    assert_eq!(nearest(100.0, 1200.0), Some(1));
+    // in the gap, nearer to the portrait monitor
+    assert_eq!(nearest(-150.0, 500.0), Some(5));
+    assert_eq!(nearest(-50.0, 1500.0), Some(1));
+    // far above the laptop, nearer to the monitors above the 4K one
+    assert_eq!(nearest(2000.0, -1000.0), Some(3));
+    assert_eq!(nearest(10000.0, 0.0), Some(4));
+
+    assert_eq!(
+        MonitorLayout::new(vec![]).nearest_monitor(PhysicalPosition::new(0.0, 0.0)),
+        None
+    );
+}
+
+#[test]
+fn it_computes_the_bounds() {
+    assert_eq!(
+        layout().bounds(),
+        Some(rect(-1280.0, -560.0, 7680.0, 3240.0))
+    );
+    assert_eq!(MonitorLayout::new(vec![]).bounds(), None);
+}
+
+#[test]
+fn it_finds_neighbours() {
+    let layout = layout();
+    let neighbours = |id: u32, direction| {
+        let monitor = layout.monitors().iter().find(|m| m.id() == id).unwrap();
+
+        ids(layout.neighbours(monitor, direction))
+    };
+
+    assert_eq!(neighbours(1, Direction::Right), vec![2]);
+    assert_eq!(neighbours(2, Direction::Left), vec![1]);
+    assert_eq!(neighbours(2, Direction::Above), vec![3, 4]);
+    assert_eq!(neighbours(3, Direction::Below), vec![2]);
+    assert_eq!(neighbours(3, Direction::Right), vec![4]);
+    // the gap keeps the portrait monitor from being a neighbour
+    assert!(neighbours(1, Direction::Left).is_empty());
+    assert!(neighbours(5, Direction::Right).is_empty());
+    assert!(neighbours(1, Direction::Above).is_empty());
+
+    // monitors only touching by a corner aren't neighbours
+    let layout = MonitorLayout::new(vec![
+        monitor(1, 0.0, 0.0, 1920.0, 1080.0),
+        monitor(2, 1920.0, 1080.0, 1920.0, 1080.0),
+        // a pixel apart, as fractional scales leave them
+        monitor(3, 1921.0, 0.0, 1920.0, 1080.0),
+    ]);
+    assert_eq!(
+        ids(layout.neighbours(&layout.monitors()[0], Direction::Right)),
+        vec![3]
+    );
+    assert!(layout
+        .neighbours(&layout.monitors()[0], Direction::Below)
+        .is_empty());
+}
@@ -1,10 +1,24 @@
+use dpi::{LogicalPosition, LogicalSize, PhysicalPosition, PhysicalSize};
 use serde::{Deserialize, Serialize};
-use tauri::{PhysicalPosition, PhysicalSize};
+
+mod coordinates;
+mod edid;
+mod events;
+mod layout;
 
 #[cfg(target_os = "macos")]
 mod macos;
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[cfg(target_os = "linux")]
+mod linux;
+
+pub use edid::{Edid, EdidError, HdrStaticMetadata, ManufactureDate, Mode};
+pub use events::{diff_monitors, watch_monitors, MonitorEvent, MonitorWatcher, WatchMonitorsError};
+#[cfg(feature = "tauri-events")]
+pub use events::{emit_monitor_events, MONITOR_EVENT};
+pub use layout::{Direction, MonitorLayout, Rect};
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct VisibleArea {
     size: PhysicalSize<f64>,
     position: PhysicalPosition<f64>,
@@ -20,17 +34,116 @@ impl VisibleArea {
     }
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+/// How far a monitor is turned from its native orientation, counter-clockwise
+/// as RandR and Wayland count it.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Rotation {
+    Normal,
+    Rotate90,
+    Rotate180,
+    Rotate270,
+}
+
+impl Rotation {
+    /// The rotation of `degrees` counter-clockwise, if it's a quarter turn.
+    pub(crate) fn from_degrees(degrees: i64) -> Option<Self> {
+        match degrees.rem_euclid(360) {
+            0 => Some(Self::Normal),
+            90 => Some(Self::Rotate90),
+            180 => Some(Self::Rotate180),
+            270 => Some(Self::Rotate270),
+            _ => None,
+        }
+    }
+
+    pub fn degrees(&self) -> u16 {
+        match self {
+            Self::Normal => 0,
+            Self::Rotate90 => 90,
+            Self::Rotate180 => 180,
+            Self::Rotate270 => 270,
+        }
+    }
+
+    /// Whether the monitor is on its side, so its width and height are
+    /// swapped.
+    pub fn is_sideways(&self) -> bool {
+        matches!(self, Self::Rotate90 | Self::Rotate270)
+    }
+}
+
+/// What a monitor can show beyond standard dynamic range: HDR signals on
+/// Linux, and EDR on macOS. The luminances are in cd/m².
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct Hdr {
+    is_supported: bool,
+    max_luminance: Option<f64>,
+    min_luminance: Option<f64>,
+    max_edr_value: Option<f64>,
+}
+
+impl Hdr {
+    /// What the EDID of the monitor tells, which has no HDR static metadata
+    /// for an SDR monitor.
+    #[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+    pub(crate) fn from_edid(edid: &Edid) -> Self {
+        let metadata = edid.hdr_static_metadata();
+
+        Self {
+            is_supported: metadata.map_or(false, |metadata| {
+                metadata.supports_pq() || metadata.supports_hlg()
+            }),
+            max_luminance: metadata.and_then(|metadata| metadata.max_luminance()),
+            min_luminance: metadata.and_then(|metadata| metadata.min_luminance()),
+            max_edr_value: None,
+        }
+    }
+
+    /// Whether the monitor shows HDR content: it takes PQ or HLG signals, or
+    /// has EDR headroom on macOS.
+    pub fn is_supported(&self) -> bool {
+        self.is_supported
+    }
+
+    pub fn max_luminance(&self) -> Option<f64> {
+        self.max_luminance
+    }
+
+    pub fn min_luminance(&self) -> Option<f64> {
+        self.min_luminance
+    }
+
+    /// How much brighter than SDR white the monitor can show, as the largest
+    /// color component value of an EDR window, on macOS.
+    pub fn max_edr_value(&self) -> Option<f64> {
+        self.max_edr_value
+    }
+}
+
+/// A monitor of the virtual desktop, whose position and visible area are in
+/// physical pixels from its top-left corner, with y going down, on every OS.
+/// That's the top-left corner of the primary monitor on Windows and macOS, of
+/// the X root window on X11, and the compositor's origin on Wayland.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct Monitor {
     id: u32,
     uuid: Option<String>,
     name: Option<String>,
+    description: Option<String>,
+    edid: Option<Edid>,
     size: PhysicalSize<f64>,
     position: PhysicalPosition<f64>,
     scale_factor: f64,
     has_cursor: bool,
     is_primary: bool,
     visible_area: VisibleArea,
+    refresh_rate: Option<f64>,
+    rotation: Option<Rotation>,
+    bits_per_component: Option<u8>,
+    is_builtin: Option<bool>,
+    physical_size_mm: Option<(u32, u32)>,
+    hdr: Option<Hdr>,
 }
 
 impl Monitor {
@@ -46,10 +159,35 @@ impl Monitor {
         self.name.as_ref()
     }
 
+    pub fn description(&self) -> Option<&String> {
+        self.description.as_ref()
+    }
+
+    /// What the monitor tells about itself, e.g. its manufacturer, model and
+    /// serial number, when the OS shares its EDID.
+    pub fn edid(&self) -> Option<&Edid> {
+        self.edid.as_ref()
+    }
+
     pub fn size(&self) -> PhysicalSize<f64> {
         self.size
     }
 
+    /// The position of the monitor in logical pixels, e.g. for
+    /// `WebviewWindow::set_position` with a `LogicalPosition`.
+    pub fn logical_position(&self) -> LogicalPosition<f64> {
+        self.position.to_logical(self.scale_factor)
+    }
+
+    pub fn logical_size(&self) -> LogicalSize<f64> {
+        self.size.to_logical(self.scale_factor)
+    }
+
+    /// The rectangle the monitor covers in the virtual desktop.
+    pub fn rect(&self) -> Rect {
+        Rect::new(self.position, self.size)
+    }
+
     pub fn visible_area(&self) -> VisibleArea {
         self.visible_area.clone()
     }
@@ -69,6 +207,111 @@ impl Monitor {
     pub fn is_primary(&self) -> bool {
         self.is_primary
     }
+
+    /// The refresh rate of the current mode, in Hz.
+    pub fn refresh_rate(&self) -> Option<f64> {
+        self.refresh_rate
+    }
+
+    pub fn rotation(&self) -> Option<Rotation> {
+        self.rotation
+    }
+
+    /// The bits per color component the monitor shows: the ones it takes on
+    /// Linux, and the ones of the screen's depth on macOS.
+    pub fn bits_per_component(&self) -> Option<u8> {
+        self.bits_per_component
+    }
+
+    /// Whether the monitor is the panel of a laptop, rather than plugged in.
+    pub fn is_builtin(&self) -> Option<bool> {
+        self.is_builtin
+    }
+
+    /// The width and the height of the image, in millimeters, turned with the
+    /// monitor.
+    pub fn physical_size_mm(&self) -> Option<(u32, u32)> {
+        self.physical_size_mm
+    }
+
+    /// The pixels per inch along the diagonal of the monitor, from its size in
+    /// pixels and in millimeters.
+    pub fn ppi(&self) -> Option<f64> {
+        let (width_mm, height_mm) = self.physical_size_mm?;
+        let diagonal_mm = f64::from(width_mm).hypot(f64::from(height_mm));
+
+        (diagonal_mm > 0.0).then(|| self.size.width.hypot(self.size.height) / diagonal_mm * 25.4)
+    }
+
+    pub fn hdr(&self) -> Option<Hdr> {
+        self.hdr
+    }
+}
+
+/// A builder of the monitors of the tests, which only set what they're about.
+#[cfg(test)]
+impl Monitor {
+    /// A 1920x1080 monitor at the origin, at a scale factor of 1.
+    pub(crate) fn test(id: u32) -> Self {
+        let size = PhysicalSize::new(1920.0, 1080.0);
+        let position = PhysicalPosition::new(0.0, 0.0);
+
+        Self {
+            id,
+            uuid: None,
+            name: None,
+            description: None,
+            edid: None,
+            size,
+            position,
+            scale_factor: 1.0,
+            has_cursor: false,
+            is_primary: false,
+            visible_area: VisibleArea { size, position },
+            refresh_rate: None,
+            rotation: None,
+            bits_per_component: None,
+            is_builtin: None,
+            physical_size_mm: None,
+            hdr: None,
+        }
+    }
+
+    pub(crate) fn with_uuid(mut self, uuid: &str) -> Self {
+        self.uuid = Some(uuid.to_string());
+        self
+    }
+
+    pub(crate) fn with_name(mut self, name: &str) -> Self {
+        self.name = Some(name.to_string());
+        self
+    }
+
+    /// Places the monitor, whose visible area is all of it.
+    pub(crate) fn with_rect(mut self, x: f64, y: f64, width: f64, height: f64) -> Self {
+        self.size = PhysicalSize::new(width, height);
+        self.position = PhysicalPosition::new(x, y);
+        self.visible_area = VisibleArea {
+            size: self.size,
+            position: self.position,
+        };
+        self
+    }
+
+    pub(crate) fn with_scale_factor(mut self, scale_factor: f64) -> Self {
+        self.scale_factor = scale_factor;
+        self
+    }
+
+    pub(crate) fn with_primary(mut self, is_primary: bool) -> Self {
+        self.is_primary = is_primary;
+        self
+    }
+
+    pub(crate) fn with_cursor(mut self, has_cursor: bool) -> Self {
+        self.has_cursor = has_cursor;
+        self
+    }
 }
 
 pub fn get_monitor_with_cursor() -> Option<Monitor> {
@@ -79,7 +322,7 @@ pub fn get_monitor_with_cursor() -> Option<Monitor> {
 
     #[cfg(target_os = "linux")]
     {
-        unimplemented!()
+        linux::monitor::get_monitor_with_cursor()
     }
 
     #[cfg(target_os = "macos")]
@@ -96,7 +339,7 @@ pub fn get_monitors() -> Vec<Monitor> {
 
     #[cfg(target_os = "linux")]
     {
-        unimplemented!()
+        linux::monitor::get_monitors()
     }
 
     #[cfg(target_os = "macos")]
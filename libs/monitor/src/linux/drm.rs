@@ -0,0 +1,38 @@
+use std::{fs, path::Path};
+
+/// Where the kernel lists the connectors of the GPUs.
+pub(super) const DRM: &str = "/sys/class/drm";
+
+/// The EDID the kernel read from the monitor plugged in the DRM connector
+/// `name` (e.g. `DP-1`), for compositors which don't share it with clients.
+/// With several GPUs, the connector of each has the name, and the one with
+/// a monitor plugged in has a non-empty EDID.
+pub(super) fn drm_edid(drm: &Path, name: &str) -> Option<Vec<u8>> {
+    fs::read_dir(drm)
+        .ok()?
+        .filter_map(Result::ok)
+        .filter(|entry| {
+            // connectors are named `<card>-<connector>`, e.g. `card1-DP-1`
+            entry
+                .file_name()
+                .to_str()
+                .and_then(|file_name| file_name.split_once('-'))
+                .map_or(false, |(card, connector)| {
+                    card.starts_with("card") && connector == name
+                })
+        })
+        .filter_map(|entry| fs::read(entry.path().join("edid")).ok())
+        .find(|edid| !edid.is_empty())
+}
+
+/// Whether the DRM connector `name` is the one of a laptop panel: eDP, LVDS
+/// or DSI. X.Org's drivers drop the dash, e.g. `eDP1`.
+pub(super) fn is_builtin(name: &str) -> bool {
+    ["eDP", "LVDS", "DSI"].iter().any(|kind| {
+        name.strip_prefix(kind).map_or(false, |index| {
+            index
+                .chars()
+                .all(|char| char.is_ascii_digit() || char == '-')
+        })
+    })
+}
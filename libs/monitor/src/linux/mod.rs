@@ -0,0 +1,6 @@
+mod drm;
+pub mod monitor;
+mod tests;
+pub(crate) mod watcher;
+mod wayland;
+mod x11;
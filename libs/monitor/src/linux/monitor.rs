@@ -0,0 +1,17 @@
+use crate::Monitor;
+
+use super::{wayland, x11};
+
+pub fn get_monitor_with_cursor() -> Option<Monitor> {
+    get_monitors()
+        .into_iter()
+        .find(|monitor| monitor.has_cursor)
+}
+
+pub fn get_monitors() -> Vec<Monitor> {
+    // XWayland only sees the outputs the compositor lends it, at a scale of 1,
+    // so the compositor is asked first
+    wayland::get_monitors()
+        .or_else(x11::get_monitors)
+        .unwrap_or_default()
+}
@@ -0,0 +1,518 @@
+#![cfg(test)]
+
+use std::{
+    collections::HashMap, fs, os::unix::net::UnixStream, process::Command, sync::mpsc, thread,
+    time::Duration,
+};
+
+use dpi::{PhysicalPosition, PhysicalSize};
+
+use crate::{edid::edid_uuid, get_monitor_with_cursor, watch_monitors, MonitorEvent, Rotation};
+
+use super::{
+    drm::{drm_edid, is_builtin},
+    monitor::get_monitors,
+    wayland::{
+        self, outputs, wl_callback, wl_display, wl_output, wl_registry, zxdg_output_manager_v1,
+        zxdg_output_v1, Message, WL_DISPLAY, WL_REGISTRY,
+    },
+    x11::{self, refresh_rate, scale_factor, to_monitor, xft_dpi, RandrMonitor, Rect, Screen},
+};
+
+fn edid(serial: u8) -> Vec<u8> {
+    let mut edid = vec![0; 128];
+    edid[..8].copy_from_slice(&[0x00, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x00]);
+    // "DEL", product 0xa0a1
+    edid[8..12].copy_from_slice(&[0x10, 0xac, 0xa1, 0xa0]);
+    edid[12] = serial;
+    edid
+}
+
+#[test]
+fn it_reads_the_scale_factor() {
+    let resources = "Xcursor.size:\t24\nXft.antialias:\t1\nXft.dpi:\t144\n";
+
+    assert_eq!(xft_dpi(resources), Some(144.0));
+    assert_eq!(xft_dpi("Xft.antialias:\t1\n"), None);
+
+    assert_eq!(scale_factor(None, xft_dpi(resources)), 1.5);
+    assert_eq!(scale_factor(Some("2"), xft_dpi(resources)), 2.0);
+    assert_eq!(scale_factor(Some("nope"), None), 1.0);
+}
+
+#[test]
+fn it_derives_the_uuid_from_the_edid() {
+    let uuid = edid_uuid(&edid(1)).unwrap();
+
+    assert_eq!(uuid.len(), 36);
+    assert_eq!(&uuid[14..15], "8");
+    assert_eq!(edid_uuid(&edid(1)), Some(uuid.clone()));
+    assert_ne!(edid_uuid(&edid(2)), Some(uuid));
+
+    assert_eq!(edid_uuid(&[0; 128]), None);
+    assert_eq!(edid_uuid(&edid(1)[..64]), None);
+}
+
+#[test]
+fn it_reads_the_edid_of_drm_connectors() {
+    let drm = std::env::temp_dir().join(format!("monitor-{}-drm", std::process::id()));
+    let _ = fs::remove_dir_all(&drm);
+    for connector in ["card0-DP-1", "card1-DP-1", "card1-DP-10", "card1-HDMI-A-1"] {
+        fs::create_dir_all(drm.join(connector)).unwrap();
+    }
+    fs::write(drm.join("card1-DP-1/edid"), edid(1)).unwrap();
+    fs::write(drm.join("card1-DP-10/edid"), edid(10)).unwrap();
+    // a connector without a monitor has an empty EDID, which doesn't hide
+    // the connector of the same name of another GPU
+    fs::write(drm.join("card0-DP-1/edid"), []).unwrap();
+    fs::write(drm.join("card1-HDMI-A-1/edid"), []).unwrap();
+
+    assert_eq!(drm_edid(&drm, "DP-1"), Some(edid(1)));
+    assert_eq!(drm_edid(&drm, "HDMI-A-1"), None);
+    assert_eq!(drm_edid(&drm, "eDP-1"), None);
+}
+
+#[test]
+fn it_builds_monitors() {
+    let screen = Screen {
+        scale_factor: 2.0,
+        pointer: Some((2000, 100)),
+        // a panel along the top of the left monitor
+        workarea: Some(Rect {
+            x: 0,
+            y: 32,
+            width: 4480,
+            height: 1408,
+        }),
+    };
+
+    let left = to_monitor(
+        RandrMonitor {
+            id: 66,
+            name: Some("eDP-1".to_string()),
+            edid: Some(edid(1)),
+            rect: Rect {
+                x: 0,
+                y: 0,
+                width: 1920,
+                height: 1080,
+            },
+            primary: true,
+            refresh_rate: Some(60.0),
+            rotation: Some(Rotation::Normal),
+            size_mm: Some((344, 194)),
+            is_builtin: Some(true),
+        },
+        &screen,
+    );
+
+    assert_eq!(left.id(), 66);
+    assert_eq!(left.name().map(String::as_str), Some("eDP-1"));
+    assert!(left.uuid().is_some());
+    assert_eq!(left.size(), PhysicalSize::new(1920.0, 1080.0));
+    assert_eq!(left.position(), PhysicalPosition::new(0.0, 0.0));
+    assert_eq!(left.scale_factor(), 2.0);
+    assert!(left.is_primary());
+    assert!(!left.has_cursor());
+    assert_eq!(
+        left.visible_area().position(),
+        PhysicalPosition::new(0.0, 32.0)
+    );
+    assert_eq!(
+        left.visible_area().size(),
+        PhysicalSize::new(1920.0, 1048.0)
+    );
+    assert_eq!(left.refresh_rate(), Some(60.0));
+    assert_eq!(left.is_builtin(), Some(true));
+    assert!((left.ppi().unwrap() - 141.7).abs() < 0.1);
+    // the EDID lacks its checksum
+    assert_eq!(left.edid(), None);
+    assert_eq!(left.hdr(), None);
+
+    let right = to_monitor(
+        RandrMonitor {
+            id: 67,
+            name: Some("HDMI-1".to_string()),
+            edid: None,
+            rect: Rect {
+                x: 1920,
+                y: 0,
+                width: 2560,
+                height: 1440,
+            },
+            primary: false,
+            refresh_rate: Some(143.98),
+            rotation: Some(Rotation::Rotate90),
+            size_mm: None,
+            is_builtin: Some(false),
+        },
+        &screen,
+    );
+
+    assert!(right.uuid().is_none());
+    assert!(right.has_cursor());
+    assert!(!right.is_primary());
+    assert_eq!(
+        right.visible_area().size(),
+        PhysicalSize::new(2560.0, 1408.0)
+    );
+    assert_eq!(right.rotation(), Some(Rotation::Rotate90));
+    assert_eq!(right.physical_size_mm(), None);
+    assert_eq!(right.ppi(), None);
+    assert_eq!(right.bits_per_component(), None);
+}
+
+#[test]
+fn it_reads_monitors_from_their_edid() {
+    let screen = Screen {
+        scale_factor: 1.0,
+        pointer: None,
+        workarea: None,
+    };
+
+    let monitor = to_monitor(
+        RandrMonitor {
+            id: 68,
+            name: Some("DP-2".to_string()),
+            edid: Some(include_bytes!("../edid/fixtures/dell-u2720q.bin").to_vec()),
+            rect: Rect {
+                x: 0,
+                y: 0,
+                width: 2160,
+                height: 3840,
+            },
+            primary: true,
+            refresh_rate: Some(60.0),
+            rotation: Some(Rotation::Rotate270),
+            size_mm: None,
+            is_builtin: Some(false),
+        },
+        &screen,
+    );
+
+    // the size of the EDID is turned with the monitor
+    assert_eq!(monitor.physical_size_mm(), Some((336, 597)));
+    assert!((monitor.ppi().unwrap() - 163.4).abs() < 0.1);
+    assert_eq!(monitor.bits_per_component(), Some(10));
+
+    let hdr = monitor.hdr().unwrap();
+    assert!(hdr.is_supported());
+    assert!((hdr.max_luminance().unwrap() - 417.7).abs() < 0.1);
+    assert_eq!(hdr.min_luminance(), Some(0.0));
+    assert_eq!(hdr.max_edr_value(), None);
+}
+
+#[test]
+fn it_reads_randr_modes() {
+    assert_eq!(x11::rotation(1), Some(Rotation::Normal));
+    assert_eq!(x11::rotation(2), Some(Rotation::Rotate90));
+    // turned by 270 degrees and reflected along x
+    assert_eq!(x11::rotation(8 | 16), Some(Rotation::Rotate270));
+    assert_eq!(x11::rotation(0), None);
+
+    // 3840x2160 at 60 Hz, with the CVT reduced blanking
+    let refresh = refresh_rate(533_250_000, 4000, 2222, 0).unwrap();
+    assert!((refresh - 59.997).abs() < 0.001);
+    // 1920x1080i at 60 Hz draws 1125 lines over two refreshes
+    let refresh = refresh_rate(74_250_000, 2200, 1125, 0x10).unwrap();
+    assert!((refresh - 60.0).abs() < 0.001);
+    assert_eq!(refresh_rate(0, 2200, 1125, 0), None);
+}
+
+#[test]
+fn it_tells_builtin_connectors() {
+    for name in ["eDP-1", "eDP1", "eDP-1-1", "LVDS1", "DSI-1"] {
+        assert!(is_builtin(name), "{name}");
+    }
+
+    for name in ["DP-1", "HDMI-A-1", "DisplayPort-0", "eDPI", "Virtual-1"] {
+        assert!(!is_builtin(name), "{name}");
+    }
+}
+
+/// A compositor answering a client over `stream`, with a 4K output scaled by
+/// 1.5 and, at its right, a 1080p output turned by 90 degrees which only
+/// supports `wl_output` v3.
+fn fake_compositor(mut stream: UnixStream, with_xdg_output: bool) {
+    let send = |stream: &mut UnixStream, message: Message| message.write_to(stream).unwrap();
+    let mut outputs = HashMap::new();
+    let mut manager = None;
+
+    while let Ok(request) = Message::read_from(&mut stream) {
+        let mut args = request.args();
+
+        match (request.object, request.opcode) {
+            (WL_DISPLAY, wl_display::GET_REGISTRY) => {
+                for (name, interface, version) in [
+                    (1, "wl_compositor", 6),
+                    (10, "wl_output", 4),
+                    (11, "wl_output", 3),
+                    (12, "zxdg_output_manager_v1", 3),
+                ] {
+                    if interface.starts_with("zxdg") && !with_xdg_output {
+                        continue;
+                    }
+
+                    send(
+                        &mut stream,
+                        Message::new(WL_REGISTRY, wl_registry::GLOBAL)
+                            .with_uint(name)
+                            .with_string(interface)
+                            .with_uint(version),
+                    );
+                }
+            }
+            (WL_DISPLAY, wl_display::SYNC) => {
+                let callback = args.uint().unwrap();
+
+                send(
+                    &mut stream,
+                    Message::new(callback, wl_callback::DONE).with_uint(0),
+                );
+                // wl_display.delete_id
+                send(&mut stream, Message::new(WL_DISPLAY, 1).with_uint(callback));
+            }
+            (WL_REGISTRY, wl_registry::BIND) => {
+                let (name, interface, version, id) = (
+                    args.uint().unwrap(),
+                    args.string().unwrap(),
+                    args.uint().unwrap(),
+                    args.uint().unwrap(),
+                );
+
+                if interface == "zxdg_output_manager_v1" {
+                    manager = Some(id);
+                    continue;
+                }
+
+                assert_eq!(interface, "wl_output");
+                assert_eq!(version, if name == 10 { 4 } else { 3 });
+                outputs.insert(id, name);
+
+                let (x, transform, mode, scale) = if name == 10 {
+                    (0, 0, (3840, 2160), 2)
+                } else {
+                    (2560, 1, (1920, 1080), 1)
+                };
+                let mut events = vec![
+                    Message::new(id, wl_output::GEOMETRY)
+                        .with_int(x)
+                        .with_int(0)
+                        .with_int(600)
+                        .with_int(340)
+                        .with_int(0)
+                        .with_string("Dell Inc.")
+                        .with_string("DELL U2720Q")
+                        .with_int(transform),
+                    Message::new(id, wl_output::MODE)
+                        .with_uint(0)
+                        .with_int(1280)
+                        .with_int(720)
+                        .with_int(60000),
+                    Message::new(id, wl_output::MODE)
+                        .with_uint(wl_output::MODE_CURRENT)
+                        .with_int(mode.0)
+                        .with_int(mode.1)
+                        .with_int(60000),
+                    Message::new(id, wl_output::SCALE).with_int(scale),
+                ];
+                if version >= 4 {
+                    events.push(Message::new(id, wl_output::NAME).with_string("DP-1"));
+                    events.push(
+                        Message::new(id, wl_output::DESCRIPTION)
+                            .with_string("Dell Inc. DELL U2720Q (DP-1)"),
+                    );
+                }
+                // wl_output.done
+                events.push(Message::new(id, 2));
+
+                for event in events {
+                    send(&mut stream, event);
+                }
+            }
+            (object, zxdg_output_manager_v1::GET_XDG_OUTPUT) if Some(object) == manager => {
+                let (id, output) = (args.uint().unwrap(), args.uint().unwrap());
+
+                let (position, size, name) = if outputs[&output] == 10 {
+                    ((0, 0), (2560, 1440), "DP-1")
+                } else {
+                    ((2560, 0), (1080, 1920), "HDMI-A-1")
+                };
+
+                for event in [
+                    Message::new(id, zxdg_output_v1::LOGICAL_POSITION)
+                        .with_int(position.0)
+                        .with_int(position.1),
+                    Message::new(id, zxdg_output_v1::LOGICAL_SIZE)
+                        .with_int(size.0)
+                        .with_int(size.1),
+                    Message::new(id, zxdg_output_v1::NAME).with_string(name),
+                    Message::new(id, zxdg_output_v1::DESCRIPTION).with_string(""),
+                ] {
+                    send(&mut stream, event);
+                }
+            }
+            _ => panic!("unexpected request {request:?}"),
+        }
+    }
+}
+
+fn get_fake_monitors(with_xdg_output: bool) -> Vec<crate::Monitor> {
+    let (mut client, server) = UnixStream::pair().unwrap();
+    let compositor = thread::spawn(move || fake_compositor(server, with_xdg_output));
+
+    let outputs = outputs(&mut client).unwrap();
+    drop(client);
+    compositor.join().unwrap();
+
+    outputs.into_iter().map(wayland::to_monitor).collect()
+}
+
+#[test]
+fn it_gets_monitors_from_wayland_outputs() {
+    let monitors = get_fake_monitors(true);
+    assert_eq!(monitors.len(), 2);
+
+    let left = &monitors[0];
+    assert_eq!(left.id(), 10);
+    assert_eq!(left.name().map(String::as_str), Some("DP-1"));
+    assert_eq!(
+        left.description().map(String::as_str),
+        Some("Dell Inc. DELL U2720Q (DP-1)")
+    );
+    assert_eq!(left.size(), PhysicalSize::new(3840.0, 2160.0));
+    assert_eq!(left.position(), PhysicalPosition::new(0.0, 0.0));
+    assert_eq!(left.scale_factor(), 1.5);
+    assert!(left.is_primary());
+    assert!(!left.has_cursor());
+    assert_eq!(left.visible_area().size(), left.size());
+    assert_eq!(left.refresh_rate(), Some(60.0));
+    assert_eq!(left.rotation(), Some(Rotation::Normal));
+    assert_eq!(left.is_builtin(), Some(false));
+    assert_eq!(left.physical_size_mm(), Some((600, 340)));
+
+    let right = &monitors[1];
+    assert_eq!(right.id(), 11);
+    assert_eq!(right.name().map(String::as_str), Some("HDMI-A-1"));
+    assert_eq!(right.description().map(String::as_str), Some(""));
+    assert_eq!(right.size(), PhysicalSize::new(1080.0, 1920.0));
+    assert_eq!(right.position(), PhysicalPosition::new(2560.0, 0.0));
+    assert_eq!(right.scale_factor(), 1.0);
+    assert!(!right.is_primary());
+    assert_eq!(right.rotation(), Some(Rotation::Rotate90));
+    assert_eq!(right.physical_size_mm(), Some((340, 600)));
+    // the compositor doesn't share EDIDs
+    assert_eq!(right.hdr(), None);
+}
+
+#[test]
+fn it_gets_monitors_without_xdg_output() {
+    let monitors = get_fake_monitors(false);
+
+    // without logical sizes, the integer scales and `wl_output` positions are used
+    assert_eq!(monitors[0].scale_factor(), 2.0);
+    assert_eq!(monitors[0].size(), PhysicalSize::new(3840.0, 2160.0));
+    assert_eq!(monitors[1].name(), None);
+    assert_eq!(monitors[1].scale_factor(), 1.0);
+    assert_eq!(monitors[1].position(), PhysicalPosition::new(2560.0, 0.0));
+}
+
+#[test]
+fn it_fails_on_compositor_errors() {
+    let (mut client, mut server) = UnixStream::pair().unwrap();
+    let compositor = thread::spawn(move || {
+        // wl_display.get_registry and wl_display.sync
+        Message::read_from(&mut server).unwrap();
+        Message::read_from(&mut server).unwrap();
+
+        Message::new(WL_DISPLAY, wl_display::ERROR)
+            .with_uint(WL_DISPLAY)
+            .with_uint(1)
+            .with_string("invalid method")
+            .write_to(&mut server)
+            .unwrap();
+    });
+
+    let error = outputs(&mut client).unwrap_err();
+    compositor.join().unwrap();
+
+    assert_eq!(error.to_string(), "error 1 on object 1: invalid method");
+}
+
+#[test]
+#[ignore = "needs a Wayland compositor, e.g. `weston --backend=headless --width=2560 --height=1440 --scale=2 &`"]
+fn it_gets_monitors_from_a_compositor() {
+    let monitors = wayland::get_monitors().unwrap();
+
+    // the headless output, at 1280x720 logical pixels
+    let [monitor] = monitors.as_slice() else {
+        panic!("expected one monitor, got {monitors:?}");
+    };
+    assert!(monitor.name().is_some());
+    assert_eq!(monitor.scale_factor(), 2.0);
+    assert_eq!(monitor.size(), PhysicalSize::new(2560.0, 1440.0));
+    assert_eq!(monitor.position(), PhysicalPosition::new(0.0, 0.0));
+    assert!(monitor.is_primary());
+}
+
+#[test]
+#[ignore = "needs an X server, e.g. `xvfb-run -s '-screen 0 3840x1080x24' cargo test -- --ignored`"]
+fn it_gets_monitors_from_randr() {
+    let xrandr = |args: &[&str]| {
+        assert!(Command::new("xrandr")
+            .args(args)
+            .status()
+            .unwrap()
+            .success());
+    };
+
+    // splits the screen in two monitors without outputs
+    xrandr(&["--setmonitor", "left", "1920/508x1080/286+0+0", "none"]);
+    xrandr(&["--setmonitor", "right", "1920/508x1080/286+1920+0", "none"]);
+
+    let monitors = get_monitors();
+
+    xrandr(&["--delmonitor", "left"]);
+    xrandr(&["--delmonitor", "right"]);
+
+    let left = monitors
+        .iter()
+        .find(|monitor| monitor.name().map(String::as_str) == Some("left"))
+        .unwrap();
+    assert_eq!(left.size(), PhysicalSize::new(1920.0, 1080.0));
+    assert_eq!(left.position(), PhysicalPosition::new(0.0, 0.0));
+
+    let right = monitors
+        .iter()
+        .find(|monitor| monitor.name().map(String::as_str) == Some("right"))
+        .unwrap();
+    assert_eq!(right.position(), PhysicalPosition::new(1920.0, 0.0));
+
+    assert!(get_monitor_with_cursor().is_some());
+}
+
+#[test]
+#[ignore = "needs an X server, e.g. `xvfb-run -s '-screen 0 3840x1080x24' cargo test -- --ignored`"]
+fn it_watches_randr_monitors() {
+    let (sender, receiver) = mpsc::channel();
+    let watcher = watch_monitors(move |event| sender.send(event).unwrap()).unwrap();
+
+    let xrandr = |args: &[&str]| {
+        assert!(Command::new("xrandr")
+            .args(args)
+            .status()
+            .unwrap()
+            .success());
+    };
+
+    xrandr(&["--setmonitor", "watched", "1920/508x1080/286+0+0", "none"]);
+    let added = receiver.recv_timeout(Duration::from_secs(5));
+    xrandr(&["--delmonitor", "watched"]);
+    let removed = receiver.recv_timeout(Duration::from_secs(5));
+    watcher.stop();
+
+    assert!(
+        matches!(added, Ok(MonitorEvent::Added(monitor)) if monitor.name().map(String::as_str) == Some("watched"))
+    );
+    assert!(matches!(removed, Ok(MonitorEvent::Removed(_))));
+}
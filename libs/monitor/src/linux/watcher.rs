@@ -0,0 +1,61 @@
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+use crate::events::{Differ, WatchMonitorsError};
+
+use super::{monitor::get_monitors, x11::ScreenChanges};
+
+/// How often the thread checks whether it should stop.
+const STOP_INTERVAL: Duration = Duration::from_millis(200);
+
+/// A thread taking a snapshot of the monitors each time the X server tells
+/// they changed.
+pub(crate) struct Watcher {
+    stop: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Watcher {
+    pub fn new(mut differ: Differ) -> Result<Self, WatchMonitorsError> {
+        let changes = ScreenChanges::open().ok_or(WatchMonitorsError::Unsupported)?;
+        let stop = Arc::new(AtomicBool::new(false));
+
+        let thread = thread::Builder::new()
+            .name("monitor-watcher".to_string())
+            .spawn({
+                let stop = stop.clone();
+
+                move || {
+                    while !stop.load(Ordering::Relaxed) {
+                        if changes.wait(STOP_INTERVAL) {
+                            differ.update(get_monitors());
+                        }
+                    }
+                }
+            })?;
+
+        Ok(Self {
+            stop,
+            thread: Some(thread),
+        })
+    }
+}
+
+impl Drop for Watcher {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+
+        // the handler may drop the watcher, from the thread itself
+        if let Some(thread) = self.thread.take() {
+            if thread.thread().id() != thread::current().id() {
+                let _ = thread.join();
+            }
+        }
+    }
+}
@@ -0,0 +1,542 @@
+//! A client of the Wayland wire protocol, just enough to list the outputs of
+//! the compositor. None of its requests pass file descriptors, so it talks
+//! over the socket itself rather than loading libwayland-client.
+
+use std::{
+    collections::HashMap,
+    env,
+    io::{self, Read, Write},
+    os::unix::net::UnixStream,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use dpi::{PhysicalPosition, PhysicalSize};
+
+use super::drm::{drm_edid, is_builtin, DRM};
+use crate::{edid::edid_uuid, Edid, Hdr, Monitor, Rotation, VisibleArea};
+
+/// How long to wait for the compositor to answer before giving up on it.
+const TIMEOUT: Duration = Duration::from_secs(1);
+
+/// The ids the client gives to the display and its registry.
+pub(super) const WL_DISPLAY: u32 = 1;
+pub(super) const WL_REGISTRY: u32 = 2;
+
+/// The opcodes of the requests and events used, by interface.
+pub(super) mod wl_display {
+    pub const SYNC: u16 = 0;
+    pub const GET_REGISTRY: u16 = 1;
+
+    pub const ERROR: u16 = 0;
+}
+
+pub(super) mod wl_registry {
+    pub const BIND: u16 = 0;
+
+    pub const GLOBAL: u16 = 0;
+}
+
+pub(super) mod wl_callback {
+    pub const DONE: u16 = 0;
+}
+
+pub(super) mod wl_output {
+    pub const GEOMETRY: u16 = 0;
+    pub const MODE: u16 = 1;
+    pub const SCALE: u16 = 3;
+    pub const NAME: u16 = 4;
+    pub const DESCRIPTION: u16 = 5;
+
+    pub const MODE_CURRENT: u32 = 0x1;
+}
+
+pub(super) mod zxdg_output_manager_v1 {
+    pub const GET_XDG_OUTPUT: u16 = 1;
+}
+
+pub(super) mod zxdg_output_v1 {
+    pub const LOGICAL_POSITION: u16 = 0;
+    pub const LOGICAL_SIZE: u16 = 1;
+    pub const NAME: u16 = 3;
+    pub const DESCRIPTION: u16 = 4;
+}
+
+/// A request or an event of the wire protocol.
+#[derive(Debug, Clone, PartialEq)]
+pub(super) struct Message {
+    pub object: u32,
+    pub opcode: u16,
+    pub body: Vec<u8>,
+}
+
+impl Message {
+    pub fn new(object: u32, opcode: u16) -> Self {
+        Self {
+            object,
+            opcode,
+            body: Vec::new(),
+        }
+    }
+
+    pub fn with_uint(mut self, value: u32) -> Self {
+        self.body.extend(value.to_ne_bytes());
+        self
+    }
+
+    #[cfg(test)]
+    pub fn with_int(self, value: i32) -> Self {
+        self.with_uint(value as u32)
+    }
+
+    /// A string, which is sent with its NUL and padded to 32 bits.
+    pub fn with_string(mut self, value: &str) -> Self {
+        let len = value.len() + 1;
+
+        self = self.with_uint(len as u32);
+        self.body.extend(value.as_bytes());
+        self.body
+            .resize(self.body.len() + padded(len) - value.len(), 0);
+        self
+    }
+
+    pub fn args(&self) -> Args<'_> {
+        Args(&self.body)
+    }
+
+    pub fn write_to(&self, writer: &mut impl Write) -> io::Result<()> {
+        let size = 8 + self.body.len();
+        let mut bytes = Vec::with_capacity(size);
+
+        bytes.extend(self.object.to_ne_bytes());
+        bytes.extend(((size as u32) << 16 | u32::from(self.opcode)).to_ne_bytes());
+        bytes.extend(&self.body);
+
+        writer.write_all(&bytes)
+    }
+
+    pub fn read_from(reader: &mut impl Read) -> io::Result<Self> {
+        let mut header = [0; 8];
+        reader.read_exact(&mut header)?;
+
+        let object = u32::from_ne_bytes([header[0], header[1], header[2], header[3]]);
+        let size_opcode = u32::from_ne_bytes([header[4], header[5], header[6], header[7]]);
+        let size = (size_opcode >> 16) as usize;
+
+        if size < header.len() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "message shorter than its header",
+            ));
+        }
+
+        let mut body = vec![0; size - header.len()];
+        reader.read_exact(&mut body)?;
+
+        Ok(Self {
+            object,
+            opcode: size_opcode as u16,
+            body,
+        })
+    }
+}
+
+/// The length of `len` bytes padded to 32 bits.
+fn padded(len: usize) -> usize {
+    (len + 3) & !3
+}
+
+/// Reads the arguments of a message in order.
+pub(super) struct Args<'a>(&'a [u8]);
+
+impl Args<'_> {
+    pub fn uint(&mut self) -> Option<u32> {
+        let bytes = self.0.get(..4)?;
+        self.0 = &self.0[4..];
+
+        Some(u32::from_ne_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    pub fn int(&mut self) -> Option<i32> {
+        self.uint().map(|value| value as i32)
+    }
+
+    pub fn string(&mut self) -> Option<String> {
+        let len = self.uint()? as usize;
+        let bytes = self.0.get(..padded(len))?;
+        self.0 = &self.0[padded(len)..];
+
+        // a null string has no length, not even for the NUL
+        let string = bytes.get(..len.checked_sub(1)?)?;
+
+        Some(String::from_utf8_lossy(string).into_owned())
+    }
+}
+
+/// An output as the compositor describes it, through `wl_output` and the
+/// `zxdg_output_v1` of `zxdg_output_manager_v1`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub(super) struct WaylandOutput {
+    /// The name of the `wl_output` global, which the compositor doesn't reuse
+    /// for another output in the session.
+    pub global: u32,
+    /// The connector of the output, e.g. `DP-1`.
+    pub name: Option<String>,
+    /// What the user knows the output as, e.g. `Dell Inc. DELL U2720Q (DP-1)`.
+    pub description: Option<String>,
+    /// The position of `wl_output.geometry`, for compositors without
+    /// `zxdg_output_manager_v1`.
+    pub position: (i32, i32),
+    /// The `wl_output.transform` of the output, the odd ones rotate it by 90
+    /// or 270 degrees.
+    pub transform: i32,
+    /// The size of the image in millimeters, before the transform.
+    pub physical_size: Option<(i32, i32)>,
+    /// The size of the current mode, in pixels, before the transform.
+    pub mode: Option<(i32, i32)>,
+    /// The refresh rate of the current mode, in mHz.
+    pub refresh: Option<i32>,
+    /// The integer scale of the output, which buffers are drawn at.
+    pub scale: i32,
+    /// The position and size of the output in the compositor's space, in
+    /// logical pixels, after the transform and the fractional scale.
+    pub logical_position: Option<(i32, i32)>,
+    pub logical_size: Option<(i32, i32)>,
+    pub edid: Option<Vec<u8>>,
+}
+
+impl WaylandOutput {
+    fn handle_output_event(&mut self, event: &Message) {
+        let mut args = event.args();
+
+        match event.opcode {
+            wl_output::GEOMETRY => {
+                // x, y, physical width, physical height, subpixel, make, model, transform
+                let (Some(x), Some(y), Some(width), Some(height), _, _, _, Some(transform)) = (
+                    args.int(),
+                    args.int(),
+                    args.int(),
+                    args.int(),
+                    args.int(),
+                    args.string(),
+                    args.string(),
+                    args.int(),
+                ) else {
+                    return;
+                };
+
+                self.position = (x, y);
+                // virtual outputs and projectors have no size
+                self.physical_size = (width > 0 && height > 0).then_some((width, height));
+                self.transform = transform;
+            }
+            wl_output::MODE => {
+                let (Some(flags), Some(width), Some(height), Some(refresh)) =
+                    (args.uint(), args.int(), args.int(), args.int())
+                else {
+                    return;
+                };
+
+                if flags & wl_output::MODE_CURRENT != 0 {
+                    self.mode = Some((width, height));
+                    self.refresh = (refresh > 0).then_some(refresh);
+                }
+            }
+            wl_output::SCALE => {
+                if let Some(scale) = args.int() {
+                    self.scale = scale;
+                }
+            }
+            wl_output::NAME => self.name = args.string(),
+            wl_output::DESCRIPTION => self.description = args.string(),
+            _ => {}
+        }
+    }
+
+    fn handle_xdg_output_event(&mut self, event: &Message) {
+        let mut args = event.args();
+
+        match event.opcode {
+            zxdg_output_v1::LOGICAL_POSITION => {
+                if let (Some(x), Some(y)) = (args.int(), args.int()) {
+                    self.logical_position = Some((x, y));
+                }
+            }
+            zxdg_output_v1::LOGICAL_SIZE => {
+                if let (Some(width), Some(height)) = (args.int(), args.int()) {
+                    self.logical_size = Some((width, height));
+                }
+            }
+            // `wl_output` v4 tells the same, and is preferred
+            zxdg_output_v1::NAME if self.name.is_none() => self.name = args.string(),
+            zxdg_output_v1::DESCRIPTION if self.description.is_none() => {
+                self.description = args.string()
+            }
+            _ => {}
+        }
+    }
+}
+
+/// The monitor of an output. Wayland doesn't tell clients where the pointer
+/// is nor which output is primary, so the output at the origin of the
+/// compositor's space is taken as the primary one, and none has the cursor.
+pub(super) fn to_monitor(output: WaylandOutput) -> Monitor {
+    let integer_scale = f64::from(output.scale.max(1));
+    let logical_size = output
+        .logical_size
+        .filter(|(width, height)| *width > 0 && *height > 0);
+
+    let (width, height) = output
+        .mode
+        .map(|(width, height)| {
+            if output.transform % 2 == 1 {
+                (f64::from(height), f64::from(width))
+            } else {
+                (f64::from(width), f64::from(height))
+            }
+        })
+        .or_else(|| {
+            logical_size.map(|(width, height)| {
+                (
+                    f64::from(width) * integer_scale,
+                    f64::from(height) * integer_scale,
+                )
+            })
+        })
+        .unwrap_or((0.0, 0.0));
+
+    // the fractional scale is how much the compositor shrinks the mode to lay
+    // the output out, which it picks in steps of 1/120
+    let scale_factor = match logical_size {
+        Some((logical_width, _)) if width > 0.0 => {
+            (width / f64::from(logical_width) * 120.0).round() / 120.0
+        }
+        _ => integer_scale,
+    };
+
+    let (x, y) = output.logical_position.unwrap_or(output.position);
+    let size = PhysicalSize { width, height };
+
+    // the flipped transforms are the others, mirrored before being turned
+    let rotation = Rotation::from_degrees(i64::from(output.transform & 3) * 90);
+    let turn = |(width, height): (u32, u32)| match rotation {
+        Some(rotation) if rotation.is_sideways() => (height, width),
+        _ => (width, height),
+    };
+    let edid = output
+        .edid
+        .as_deref()
+        .and_then(|edid| Edid::parse(edid).ok());
+    let position = PhysicalPosition {
+        x: f64::from(x) * scale_factor,
+        y: f64::from(y) * scale_factor,
+    };
+
+    Monitor {
+        id: output.global,
+        uuid: output.edid.as_deref().and_then(edid_uuid),
+        is_builtin: output.name.as_deref().map(is_builtin),
+        name: output.name,
+        description: output.description,
+        size,
+        position,
+        scale_factor,
+        has_cursor: false,
+        is_primary: (x, y) == (0, 0),
+        // compositors keep the areas their panels take to themselves
+        visible_area: VisibleArea { size, position },
+        refresh_rate: output.refresh.map(|refresh| f64::from(refresh) / 1000.0),
+        rotation,
+        bits_per_component: edid.as_ref().and_then(Edid::bits_per_component),
+        physical_size_mm: output
+            .physical_size
+            .map(|(width, height)| (width as u32, height as u32))
+            .or_else(|| edid.as_ref()?.physical_size_mm())
+            .map(turn),
+        hdr: edid.as_ref().map(Hdr::from_edid),
+        edid,
+    }
+}
+
+/// Sends `wl_display.sync` and reads the events until its callback is done,
+/// handing the others to `handle`.
+fn roundtrip<S: Read + Write>(
+    stream: &mut S,
+    callback: u32,
+    mut handle: impl FnMut(&Message),
+) -> io::Result<()> {
+    Message::new(WL_DISPLAY, wl_display::SYNC)
+        .with_uint(callback)
+        .write_to(stream)?;
+
+    loop {
+        let event = Message::read_from(stream)?;
+
+        if event.object == callback && event.opcode == wl_callback::DONE {
+            return Ok(());
+        }
+
+        if event.object == WL_DISPLAY && event.opcode == wl_display::ERROR {
+            let mut args = event.args();
+            let (object, code, message) = (args.uint(), args.uint(), args.string());
+
+            return Err(io::Error::new(
+                io::ErrorKind::Other,
+                format!(
+                    "error {} on object {}: {}",
+                    code.unwrap_or_default(),
+                    object.unwrap_or_default(),
+                    message.unwrap_or_default()
+                ),
+            ));
+        }
+
+        handle(&event);
+    }
+}
+
+/// Binds the global `name` to the new object `id`.
+fn bind(
+    stream: &mut impl Write,
+    name: u32,
+    interface: &str,
+    version: u32,
+    id: u32,
+) -> io::Result<()> {
+    Message::new(WL_REGISTRY, wl_registry::BIND)
+        .with_uint(name)
+        .with_string(interface)
+        .with_uint(version)
+        .with_uint(id)
+        .write_to(stream)
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Object {
+    Output,
+    XdgOutput,
+}
+
+/// The outputs of the compositor at the other end of `stream`, a new
+/// connection to it.
+pub(super) fn outputs<S: Read + Write>(stream: &mut S) -> io::Result<Vec<WaylandOutput>> {
+    Message::new(WL_DISPLAY, wl_display::GET_REGISTRY)
+        .with_uint(WL_REGISTRY)
+        .write_to(stream)?;
+
+    let mut next_id = WL_REGISTRY + 1;
+    let mut globals = Vec::new();
+    let mut xdg_output_manager = None;
+
+    roundtrip(stream, next_id, |event| {
+        if event.object != WL_REGISTRY || event.opcode != wl_registry::GLOBAL {
+            return;
+        }
+
+        let mut args = event.args();
+        let (Some(name), Some(interface), Some(version)) =
+            (args.uint(), args.string(), args.uint())
+        else {
+            return;
+        };
+
+        match interface.as_str() {
+            "wl_output" => globals.push((name, version)),
+            "zxdg_output_manager_v1" => xdg_output_manager = Some((name, version)),
+            _ => {}
+        }
+    })?;
+
+    let mut new_id = || {
+        next_id += 1;
+        next_id
+    };
+
+    let xdg_output_manager = match xdg_output_manager {
+        Some((name, version)) => {
+            let manager = new_id();
+            bind(
+                stream,
+                name,
+                "zxdg_output_manager_v1",
+                version.min(3),
+                manager,
+            )?;
+            Some(manager)
+        }
+        None => None,
+    };
+
+    let mut outputs = Vec::with_capacity(globals.len());
+    let mut objects = HashMap::new();
+
+    for (index, (name, version)) in globals.into_iter().enumerate() {
+        // v4 adds the name and the description
+        let output = new_id();
+        bind(stream, name, "wl_output", version.min(4), output)?;
+        objects.insert(output, (index, Object::Output));
+
+        if let Some(manager) = xdg_output_manager {
+            let xdg_output = new_id();
+            Message::new(manager, zxdg_output_manager_v1::GET_XDG_OUTPUT)
+                .with_uint(xdg_output)
+                .with_uint(output)
+                .write_to(stream)?;
+            objects.insert(xdg_output, (index, Object::XdgOutput));
+        }
+
+        outputs.push(WaylandOutput {
+            global: name,
+            scale: 1,
+            ..Default::default()
+        });
+    }
+
+    roundtrip(stream, new_id(), |event| {
+        let Some(&(index, object)) = objects.get(&event.object) else {
+            return;
+        };
+
+        match object {
+            Object::Output => outputs[index].handle_output_event(event),
+            Object::XdgOutput => outputs[index].handle_xdg_output_event(event),
+        }
+    })?;
+
+    Ok(outputs)
+}
+
+/// The socket of the compositor of `$WAYLAND_DISPLAY`, which is relative to
+/// `$XDG_RUNTIME_DIR` unless it's absolute.
+fn socket_path() -> Option<PathBuf> {
+    let display = PathBuf::from(env::var_os("WAYLAND_DISPLAY").filter(|d| !d.is_empty())?);
+
+    if display.is_absolute() {
+        return Some(display);
+    }
+
+    Some(Path::new(&env::var_os("XDG_RUNTIME_DIR")?).join(display))
+}
+
+/// The monitors of the compositor of `$WAYLAND_DISPLAY`, or `None` when it
+/// isn't set or the compositor can't be reached, e.g. in an X11 session.
+pub(super) fn get_monitors() -> Option<Vec<Monitor>> {
+    let mut stream = UnixStream::connect(socket_path()?).ok()?;
+    stream.set_read_timeout(Some(TIMEOUT)).ok()?;
+
+    let outputs = outputs(&mut stream).ok()?;
+
+    Some(
+        outputs
+            .into_iter()
+            .map(|mut output| {
+                output.edid = output
+                    .name
+                    .as_deref()
+                    .and_then(|name| drm_edid(Path::new(DRM), name));
+
+                to_monitor(output)
+            })
+            .collect(),
+    )
+}
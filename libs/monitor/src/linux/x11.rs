@@ -0,0 +1,845 @@
+use std::{
+    env,
+    ffi::{CStr, CString},
+    mem,
+    os::raw::{c_int, c_long, c_uchar, c_uint, c_ulong},
+    path::Path,
+    ptr, slice,
+    sync::{
+        atomic::{AtomicBool, AtomicPtr, Ordering},
+        Mutex, PoisonError,
+    },
+    time::Duration,
+};
+
+use dpi::{PhysicalPosition, PhysicalSize};
+use x11_dl::{
+    xlib::{self, Atom, Display, Window, Xlib},
+    xrandr::{self, RRCrtc, RRMode, RROutput, XRRScreenResources, Xrandr},
+};
+
+use super::drm::{drm_edid, is_builtin, DRM};
+use crate::{edid::edid_uuid, Edid, Hdr, Monitor, Rotation, VisibleArea};
+
+/// The DPI X11 apps assume for a scale factor of 1.
+const BASE_DPI: f64 = 96.0;
+
+/// How many times the monitors are queried when X errors interrupt the
+/// queries, before falling back to the whole screen.
+const QUERY_ATTEMPTS: usize = 3;
+
+type ErrorHandler = unsafe extern "C" fn(*mut Display, *mut xlib::XErrorEvent) -> c_int;
+
+/// Whether an X error occurred on the display of [`Connection::trap_errors`]
+/// while it ran. The error handler of Xlib is global, so the traps are also
+/// serialized, and the errors of the other displays of the process (e.g.
+/// GTK's) are handed to the handler the trap replaced.
+static X_ERROR: AtomicBool = AtomicBool::new(false);
+static ERROR_TRAP: Mutex<()> = Mutex::new(());
+static TRAPPED_DISPLAY: AtomicPtr<Display> = AtomicPtr::new(ptr::null_mut());
+static PREVIOUS_HANDLER: Mutex<Option<ErrorHandler>> = Mutex::new(None);
+
+unsafe extern "C" fn record_error(display: *mut Display, event: *mut xlib::XErrorEvent) -> c_int {
+    if (*event).display == TRAPPED_DISPLAY.load(Ordering::SeqCst) {
+        X_ERROR.store(true, Ordering::SeqCst);
+        return 0;
+    }
+
+    let previous = *PREVIOUS_HANDLER
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner);
+
+    previous.map_or(0, |handler| handler(display, event))
+}
+
+/// A rectangle of the X screen, in pixels.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(super) struct Rect {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Rect {
+    fn right(&self) -> i64 {
+        i64::from(self.x) + i64::from(self.width)
+    }
+
+    fn bottom(&self) -> i64 {
+        i64::from(self.y) + i64::from(self.height)
+    }
+
+    pub fn contains(&self, (x, y): (i32, i32)) -> bool {
+        x >= self.x && i64::from(x) < self.right() && y >= self.y && i64::from(y) < self.bottom()
+    }
+
+    pub fn intersection(&self, other: &Rect) -> Option<Rect> {
+        let x = self.x.max(other.x);
+        let y = self.y.max(other.y);
+        let right = self.right().min(other.right());
+        let bottom = self.bottom().min(other.bottom());
+
+        (right > i64::from(x) && bottom > i64::from(y)).then(|| Rect {
+            x,
+            y,
+            width: (right - i64::from(x)) as u32,
+            height: (bottom - i64::from(y)) as u32,
+        })
+    }
+}
+
+/// A monitor as RandR describes it.
+#[derive(Debug, Clone, PartialEq)]
+pub(super) struct RandrMonitor {
+    /// The XID of the monitor's first output, or of its name for a monitor
+    /// without outputs (made with `xrandr --setmonitor <name> <geometry> none`).
+    pub id: u32,
+    pub name: Option<String>,
+    pub edid: Option<Vec<u8>>,
+    pub rect: Rect,
+    pub primary: bool,
+    pub refresh_rate: Option<f64>,
+    pub rotation: Option<Rotation>,
+    /// The size of the image in millimeters, turned with the monitor.
+    pub size_mm: Option<(u32, u32)>,
+    pub is_builtin: Option<bool>,
+}
+
+/// What RandR tells about an output.
+struct Output {
+    name: String,
+    connected: bool,
+    crtc: RRCrtc,
+    /// The size of the image in millimeters, as the monitor is made.
+    size_mm: Option<(u32, u32)>,
+}
+
+/// What RandR tells about a CRTC, which drives the outputs showing a part of
+/// the screen.
+struct Crtc {
+    rect: Rect,
+    refresh_rate: Option<f64>,
+    rotation: Option<Rotation>,
+}
+
+/// What the monitors of an X screen share.
+#[derive(Debug, Clone, PartialEq)]
+pub(super) struct Screen {
+    pub scale_factor: f64,
+    pub pointer: Option<(i32, i32)>,
+    /// The `_NET_WORKAREA` of the current desktop, which spans every monitor.
+    pub workarea: Option<Rect>,
+}
+
+/// The `Xft.dpi` of the X resources, which desktops set to scale apps.
+pub(super) fn xft_dpi(resources: &str) -> Option<f64> {
+    resources.lines().find_map(|line| {
+        let (name, value) = line.split_once(':')?;
+
+        (name.trim() == "Xft.dpi")
+            .then(|| value.trim().parse::<f64>().ok())
+            .flatten()
+            .filter(|dpi| *dpi > 0.0)
+    })
+}
+
+/// The scale factor GTK draws the webview at: `GDK_SCALE` when it's set, and
+/// else the `Xft.dpi` relative to 96 DPI.
+pub(super) fn scale_factor(gdk_scale: Option<&str>, xft_dpi: Option<f64>) -> f64 {
+    gdk_scale
+        .and_then(|scale| scale.trim().parse::<f64>().ok())
+        .filter(|scale| *scale >= 1.0)
+        .or_else(|| xft_dpi.map(|dpi| dpi / BASE_DPI))
+        .unwrap_or(1.0)
+}
+
+/// The rotation of a RandR rotation, whose reflections aren't rotations.
+pub(super) fn rotation(rotation: u16) -> Option<Rotation> {
+    match c_int::from(rotation) & 0x0f {
+        xrandr::RR_Rotate_0 => Some(Rotation::Normal),
+        xrandr::RR_Rotate_90 => Some(Rotation::Rotate90),
+        xrandr::RR_Rotate_180 => Some(Rotation::Rotate180),
+        xrandr::RR_Rotate_270 => Some(Rotation::Rotate270),
+        _ => None,
+    }
+}
+
+/// The refresh rate of a RandR mode, whose dot clock is in Hz.
+pub(super) fn refresh_rate(
+    dot_clock: c_ulong,
+    h_total: c_uint,
+    v_total: c_uint,
+    flags: c_ulong,
+) -> Option<f64> {
+    let mut v_total = f64::from(v_total);
+
+    // doublescan modes draw each line twice, and interlaced modes every other
+    // line on each refresh
+    if flags & xrandr::RR_DoubleScan as c_ulong != 0 {
+        v_total *= 2.0;
+    }
+    if flags & xrandr::RR_Interlace as c_ulong != 0 {
+        v_total /= 2.0;
+    }
+
+    let pixels = f64::from(h_total) * v_total;
+
+    (dot_clock > 0 && pixels > 0.0).then(|| dot_clock as f64 / pixels)
+}
+
+pub(super) fn to_monitor(monitor: RandrMonitor, screen: &Screen) -> Monitor {
+    let rect = monitor.rect;
+    let visible = screen
+        .workarea
+        .and_then(|workarea| workarea.intersection(&rect))
+        .unwrap_or(rect);
+    let edid = monitor
+        .edid
+        .as_deref()
+        .and_then(|edid| Edid::parse(edid).ok());
+
+    Monitor {
+        id: monitor.id,
+        uuid: monitor.edid.as_deref().and_then(edid_uuid),
+        name: monitor.name,
+        description: None,
+        size: PhysicalSize {
+            width: f64::from(rect.width),
+            height: f64::from(rect.height),
+        },
+        position: PhysicalPosition {
+            x: f64::from(rect.x),
+            y: f64::from(rect.y),
+        },
+        scale_factor: screen.scale_factor,
+        has_cursor: screen
+            .pointer
+            .map_or(false, |pointer| rect.contains(pointer)),
+        is_primary: monitor.primary,
+        visible_area: VisibleArea {
+            size: PhysicalSize {
+                width: f64::from(visible.width),
+                height: f64::from(visible.height),
+            },
+            position: PhysicalPosition {
+                x: f64::from(visible.x),
+                y: f64::from(visible.y),
+            },
+        },
+        refresh_rate: monitor.refresh_rate,
+        rotation: monitor.rotation,
+        bits_per_component: edid.as_ref().and_then(Edid::bits_per_component),
+        is_builtin: monitor.is_builtin,
+        // the EDID tells the size of the monitor as it's made
+        physical_size_mm: monitor.size_mm.or_else(|| {
+            let (width, height) = edid.as_ref()?.physical_size_mm()?;
+
+            Some(match monitor.rotation {
+                Some(rotation) if rotation.is_sideways() => (height, width),
+                _ => (width, height),
+            })
+        }),
+        hdr: edid.as_ref().map(Hdr::from_edid),
+        edid,
+    }
+}
+
+/// A connection to the X server of `$DISPLAY`, with libX11 and libXrandr
+/// loaded at runtime so the crate doesn't link against them.
+struct Connection {
+    xlib: Xlib,
+    xrandr: Option<Xrandr>,
+    display: *mut Display,
+    root: Window,
+}
+
+impl Drop for Connection {
+    fn drop(&mut self) {
+        unsafe { (self.xlib.XCloseDisplay)(self.display) };
+    }
+}
+
+impl Connection {
+    fn open() -> Option<Self> {
+        let xlib = Xlib::open().ok()?;
+        let display = unsafe { (xlib.XOpenDisplay)(ptr::null()) };
+
+        if display.is_null() {
+            return None;
+        }
+
+        let root = unsafe { (xlib.XDefaultRootWindow)(display) };
+
+        Some(Self {
+            xlib,
+            xrandr: Xrandr::open().ok(),
+            display,
+            root,
+        })
+    }
+
+    /// Runs `queries` with an X error handler recording the errors, instead
+    /// of the default one which exits the process, e.g. on the `BadRROutput`
+    /// of an output unplugged while its info is asked for. Returns `None`
+    /// when an error occurred.
+    fn trap_errors<T>(&self, queries: impl FnOnce() -> T) -> Option<T> {
+        let _trap = ERROR_TRAP.lock().unwrap_or_else(PoisonError::into_inner);
+
+        unsafe {
+            X_ERROR.store(false, Ordering::SeqCst);
+            TRAPPED_DISPLAY.store(self.display, Ordering::SeqCst);
+            // locked while swapped, so an error of another thread waits for it
+            let mut previous_handler = PREVIOUS_HANDLER
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner);
+            let previous = (self.xlib.XSetErrorHandler)(Some(record_error));
+            *previous_handler = previous;
+            drop(previous_handler);
+
+            let result = queries();
+
+            // the errors of the last requests arrive with their replies
+            (self.xlib.XSync)(self.display, xlib::False);
+            (self.xlib.XSetErrorHandler)(previous);
+            TRAPPED_DISPLAY.store(ptr::null_mut(), Ordering::SeqCst);
+
+            (!X_ERROR.swap(false, Ordering::SeqCst)).then_some(result)
+        }
+    }
+
+    fn atom(&self, name: &str) -> Option<Atom> {
+        let name = CString::new(name).ok()?;
+        let atom = unsafe { (self.xlib.XInternAtom)(self.display, name.as_ptr(), xlib::True) };
+
+        (atom != 0).then_some(atom)
+    }
+
+    fn atom_name(&self, atom: Atom) -> Option<String> {
+        unsafe {
+            let name = (self.xlib.XGetAtomName)(self.display, atom);
+            if name.is_null() {
+                return None;
+            }
+
+            let string = CStr::from_ptr(name).to_string_lossy().into_owned();
+            (self.xlib.XFree)(name.cast());
+
+            Some(string)
+        }
+    }
+
+    /// The values of a 32-bit `CARDINAL` property of the root window.
+    fn root_cardinals(&self, name: &str) -> Option<Vec<c_long>> {
+        let atom = self.atom(name)?;
+
+        let mut actual_type: Atom = 0;
+        let mut actual_format: c_int = 0;
+        let mut nitems: c_ulong = 0;
+        let mut bytes_after: c_ulong = 0;
+        let mut data: *mut c_uchar = ptr::null_mut();
+
+        unsafe {
+            let status = (self.xlib.XGetWindowProperty)(
+                self.display,
+                self.root,
+                atom,
+                0,
+                1024,
+                xlib::False,
+                xlib::XA_CARDINAL,
+                &mut actual_type,
+                &mut actual_format,
+                &mut nitems,
+                &mut bytes_after,
+                &mut data,
+            );
+
+            if status != c_int::from(xlib::Success) || data.is_null() {
+                return None;
+            }
+
+            // 32-bit properties come back as an array of longs
+            let values = (actual_format == 32)
+                .then(|| slice::from_raw_parts(data.cast::<c_long>(), nitems as usize).to_vec());
+            (self.xlib.XFree)(data.cast());
+
+            values
+        }
+    }
+
+    fn workarea(&self) -> Option<Rect> {
+        let desktop = self
+            .root_cardinals("_NET_CURRENT_DESKTOP")
+            .and_then(|values| values.first().copied())
+            .unwrap_or(0);
+        let workareas = self.root_cardinals("_NET_WORKAREA")?;
+        let index = usize::try_from(desktop).ok()? * 4;
+        let area = workareas.get(index..index + 4)?;
+
+        Some(Rect {
+            x: area[0] as i32,
+            y: area[1] as i32,
+            width: area[2] as u32,
+            height: area[3] as u32,
+        })
+    }
+
+    fn pointer(&self) -> Option<(i32, i32)> {
+        let mut root: Window = 0;
+        let mut child: Window = 0;
+        let (mut root_x, mut root_y, mut win_x, mut win_y): (c_int, c_int, c_int, c_int) =
+            (0, 0, 0, 0);
+        let mut mask: c_uint = 0;
+
+        let same_screen = unsafe {
+            (self.xlib.XQueryPointer)(
+                self.display,
+                self.root,
+                &mut root,
+                &mut child,
+                &mut root_x,
+                &mut root_y,
+                &mut win_x,
+                &mut win_y,
+                &mut mask,
+            )
+        };
+
+        (same_screen != 0).then_some((root_x, root_y))
+    }
+
+    fn scale_factor(&self) -> f64 {
+        let resources = unsafe {
+            let resources = (self.xlib.XResourceManagerString)(self.display);
+
+            (!resources.is_null()).then(|| CStr::from_ptr(resources).to_string_lossy().into_owned())
+        };
+
+        scale_factor(
+            env::var("GDK_SCALE").ok().as_deref(),
+            resources.as_deref().and_then(xft_dpi),
+        )
+    }
+
+    /// The RandR version of the server, if it has the extension.
+    fn randr_version(&self, xrandr: &Xrandr) -> Option<(c_int, c_int)> {
+        let (mut event_base, mut error_base) = (0, 0);
+        let (mut major, mut minor) = (0, 0);
+
+        unsafe {
+            if (xrandr.XRRQueryExtension)(self.display, &mut event_base, &mut error_base) == 0 {
+                return None;
+            }
+
+            if (xrandr.XRRQueryVersion)(self.display, &mut major, &mut minor) == 0 {
+                return None;
+            }
+        }
+
+        Some((major, minor))
+    }
+
+    fn output(
+        &self,
+        xrandr: &Xrandr,
+        resources: *mut XRRScreenResources,
+        output: RROutput,
+    ) -> Option<Output> {
+        unsafe {
+            let info = (xrandr.XRRGetOutputInfo)(self.display, resources, output);
+            if info.is_null() {
+                return None;
+            }
+
+            let name = slice::from_raw_parts((*info).name.cast::<u8>(), (*info).nameLen as usize);
+            let output = Output {
+                name: String::from_utf8_lossy(name).into_owned(),
+                connected: c_int::from((*info).connection) == xrandr::RR_Connected,
+                crtc: (*info).crtc,
+                size_mm: ((*info).mm_width > 0 && (*info).mm_height > 0)
+                    .then(|| ((*info).mm_width as u32, (*info).mm_height as u32)),
+            };
+            (xrandr.XRRFreeOutputInfo)(info);
+
+            Some(output)
+        }
+    }
+
+    /// The refresh rate of the mode `mode` of the screen resources.
+    fn mode_refresh_rate(&self, resources: *mut XRRScreenResources, mode: RRMode) -> Option<f64> {
+        let modes = unsafe {
+            slice::from_raw_parts((*resources).modes, (*resources).nmode.max(0) as usize)
+        };
+        let mode = modes.iter().find(|info| info.id == mode)?;
+
+        refresh_rate(mode.dotClock, mode.hTotal, mode.vTotal, mode.modeFlags)
+    }
+
+    fn crtc(
+        &self,
+        xrandr: &Xrandr,
+        resources: *mut XRRScreenResources,
+        crtc: RRCrtc,
+    ) -> Option<Crtc> {
+        if crtc == 0 {
+            return None;
+        }
+
+        unsafe {
+            let info = (xrandr.XRRGetCrtcInfo)(self.display, resources, crtc);
+            if info.is_null() {
+                return None;
+            }
+
+            let crtc = Crtc {
+                rect: Rect {
+                    x: (*info).x,
+                    y: (*info).y,
+                    width: (*info).width,
+                    height: (*info).height,
+                },
+                refresh_rate: self.mode_refresh_rate(resources, (*info).mode),
+                rotation: rotation((*info).rotation),
+            };
+            (xrandr.XRRFreeCrtcInfo)(info);
+
+            Some(crtc)
+        }
+    }
+
+    fn output_edid(&self, xrandr: &Xrandr, output: RROutput) -> Option<Vec<u8>> {
+        let atom = self.atom("EDID")?;
+
+        let mut actual_type: Atom = 0;
+        let mut actual_format: c_int = 0;
+        let mut nitems: c_ulong = 0;
+        let mut bytes_after: c_ulong = 0;
+        let mut data: *mut c_uchar = ptr::null_mut();
+
+        unsafe {
+            let status = (xrandr.XRRGetOutputProperty)(
+                self.display,
+                output,
+                atom,
+                0,
+                // in 32-bit units, enough for the base block and a few extensions
+                256,
+                xlib::False,
+                xlib::False,
+                xlib::AnyPropertyType as Atom,
+                &mut actual_type,
+                &mut actual_format,
+                &mut nitems,
+                &mut bytes_after,
+                &mut data,
+            );
+
+            if status != c_int::from(xlib::Success) || data.is_null() {
+                return None;
+            }
+
+            let edid = (actual_format == 8 && nitems > 0)
+                .then(|| slice::from_raw_parts(data, nitems as usize).to_vec());
+            (self.xlib.XFree)(data.cast());
+
+            edid
+        }
+    }
+
+    /// The monitors of RandR 1.5, which include the ones set up with
+    /// `xrandr --setmonitor`, e.g. to split an ultrawide display.
+    fn randr_monitors(
+        &self,
+        xrandr: &Xrandr,
+        resources: *mut XRRScreenResources,
+    ) -> Option<Vec<RandrMonitor>> {
+        let mut count: c_int = 0;
+
+        unsafe {
+            let infos = (xrandr.XRRGetMonitors)(self.display, self.root, xlib::True, &mut count);
+            if infos.is_null() {
+                return None;
+            }
+
+            let monitors = slice::from_raw_parts(infos, count.max(0) as usize)
+                .iter()
+                .map(|info| {
+                    let output =
+                        (info.noutput > 0 && !info.outputs.is_null()).then(|| *info.outputs);
+                    let output_info =
+                        output.and_then(|output| self.output(xrandr, resources, output));
+                    let crtc = output_info
+                        .as_ref()
+                        .and_then(|output| self.crtc(xrandr, resources, output.crtc));
+
+                    RandrMonitor {
+                        id: output.unwrap_or(info.name) as u32,
+                        is_builtin: output_info.as_ref().map(|output| is_builtin(&output.name)),
+                        name: output_info
+                            .map(|output| output.name)
+                            .or_else(|| self.atom_name(info.name)),
+                        edid: output.and_then(|output| self.output_edid(xrandr, output)),
+                        rect: Rect {
+                            x: info.x,
+                            y: info.y,
+                            width: info.width.max(0) as u32,
+                            height: info.height.max(0) as u32,
+                        },
+                        primary: info.primary != 0,
+                        refresh_rate: crtc.as_ref().and_then(|crtc| crtc.refresh_rate),
+                        rotation: crtc.and_then(|crtc| crtc.rotation),
+                        // the server turns the size of the outputs with them
+                        size_mm: (info.mwidth > 0 && info.mheight > 0)
+                            .then_some((info.mwidth as u32, info.mheight as u32)),
+                    }
+                })
+                .collect();
+
+            (xrandr.XRRFreeMonitors)(infos);
+
+            Some(monitors)
+        }
+    }
+
+    /// The connected outputs driven by a CRTC, for servers older than RandR 1.5.
+    fn randr_outputs(
+        &self,
+        xrandr: &Xrandr,
+        resources: *mut XRRScreenResources,
+    ) -> Vec<RandrMonitor> {
+        unsafe {
+            let primary = (xrandr.XRRGetOutputPrimary)(self.display, self.root);
+            let outputs =
+                slice::from_raw_parts((*resources).outputs, (*resources).noutput.max(0) as usize);
+
+            outputs
+                .iter()
+                .filter_map(|&id| {
+                    let output = self.output(xrandr, resources, id)?;
+                    if !output.connected {
+                        return None;
+                    }
+
+                    let crtc = self.crtc(xrandr, resources, output.crtc)?;
+
+                    Some(RandrMonitor {
+                        id: id as u32,
+                        is_builtin: Some(is_builtin(&output.name)),
+                        name: Some(output.name),
+                        edid: self.output_edid(xrandr, id),
+                        rect: crtc.rect,
+                        primary: id == primary,
+                        refresh_rate: crtc.refresh_rate,
+                        rotation: crtc.rotation,
+                        size_mm: output.size_mm.map(|(width, height)| match crtc.rotation {
+                            Some(rotation) if rotation.is_sideways() => (height, width),
+                            _ => (width, height),
+                        }),
+                    })
+                })
+                .collect()
+        }
+    }
+
+    /// The whole X screen as one monitor, when the server has no RandR.
+    fn screen_monitor(&self) -> RandrMonitor {
+        unsafe {
+            let screen = (self.xlib.XDefaultScreen)(self.display);
+
+            RandrMonitor {
+                id: screen as u32,
+                name: None,
+                edid: None,
+                rect: Rect {
+                    x: 0,
+                    y: 0,
+                    width: (self.xlib.XDisplayWidth)(self.display, screen).max(0) as u32,
+                    height: (self.xlib.XDisplayHeight)(self.display, screen).max(0) as u32,
+                },
+                primary: true,
+                refresh_rate: None,
+                rotation: None,
+                size_mm: None,
+                is_builtin: None,
+            }
+        }
+    }
+
+    fn monitors(&self) -> Vec<RandrMonitor> {
+        let Some(xrandr) = self.xrandr.as_ref() else {
+            return vec![self.screen_monitor()];
+        };
+
+        let Some(version) = self.randr_version(xrandr) else {
+            return vec![self.screen_monitor()];
+        };
+
+        unsafe {
+            let resources = (xrandr.XRRGetScreenResourcesCurrent)(self.display, self.root);
+            if resources.is_null() {
+                return vec![self.screen_monitor()];
+            }
+
+            let monitors = if version >= (1, 5) {
+                self.randr_monitors(xrandr, resources)
+            } else {
+                None
+            }
+            .unwrap_or_else(|| self.randr_outputs(xrandr, resources));
+
+            (xrandr.XRRFreeScreenResources)(resources);
+
+            monitors
+        }
+    }
+}
+
+/// The monitors of the X server of `$DISPLAY`, or `None` when it can't be
+/// reached, e.g. in a Wayland session without XWayland.
+pub(super) fn get_monitors() -> Option<Vec<Monitor>> {
+    let connection = Connection::open()?;
+
+    let query = || {
+        let screen = Screen {
+            scale_factor: connection.scale_factor(),
+            pointer: connection.pointer(),
+            workarea: connection.workarea(),
+        };
+
+        (screen, connection.monitors())
+    };
+
+    // the outputs change while the server is reconfigured, which is when
+    // the watcher asks for them, so the queries are tried again after errors
+    let (screen, monitors) = (0..QUERY_ATTEMPTS)
+        .find_map(|_| connection.trap_errors(query))
+        .unwrap_or_else(|| {
+            let screen = Screen {
+                scale_factor: connection.scale_factor(),
+                pointer: None,
+                workarea: None,
+            };
+
+            (screen, vec![connection.screen_monitor()])
+        });
+
+    Some(
+        monitors
+            .into_iter()
+            .map(|mut monitor| {
+                // drivers which don't set the EDID output property still name
+                // outputs after their DRM connectors, e.g. `DP-1`
+                if monitor.edid.is_none() {
+                    monitor.edid = monitor
+                        .name
+                        .as_deref()
+                        .and_then(|name| drm_edid(Path::new(DRM), name));
+                }
+
+                to_monitor(monitor, &screen)
+            })
+            .collect(),
+    )
+}
+
+/// The notifications of the X server that the monitors may have changed:
+/// RandR's, and the changes of the work area and of the X resources, which
+/// hold `Xft.dpi`.
+pub(super) struct ScreenChanges {
+    connection: Connection,
+    randr_event_base: c_int,
+    properties: [Atom; 2],
+}
+
+// the connection is only used by the thread waiting for the notifications
+unsafe impl Send for ScreenChanges {}
+
+impl ScreenChanges {
+    /// Listens to the notifications, if the server has RandR.
+    pub fn open() -> Option<Self> {
+        let connection = Connection::open()?;
+        let xrandr = connection.xrandr.as_ref()?;
+        let (mut event_base, mut error_base) = (0, 0);
+
+        unsafe {
+            if (xrandr.XRRQueryExtension)(connection.display, &mut event_base, &mut error_base) == 0
+            {
+                return None;
+            }
+
+            (xrandr.XRRSelectInput)(
+                connection.display,
+                connection.root,
+                xrandr::RRScreenChangeNotifyMask
+                    | xrandr::RRCrtcChangeNotifyMask
+                    | xrandr::RROutputChangeNotifyMask,
+            );
+            (connection.xlib.XSelectInput)(
+                connection.display,
+                connection.root,
+                xlib::PropertyChangeMask,
+            );
+            (connection.xlib.XFlush)(connection.display);
+        }
+
+        let properties = [
+            connection.atom("_NET_WORKAREA").unwrap_or(0),
+            xlib::XA_RESOURCE_MANAGER,
+        ];
+
+        Some(Self {
+            connection,
+            randr_event_base: event_base,
+            properties,
+        })
+    }
+
+    /// Waits up to `timeout` for notifications, and tells whether one was
+    /// about the monitors. The notifications that arrive together are read
+    /// at once, as changing the layout sends a few of them.
+    pub fn wait(&self, timeout: Duration) -> bool {
+        let Connection {
+            xlib,
+            xrandr,
+            display,
+            ..
+        } = &self.connection;
+        let display = *display;
+
+        unsafe {
+            if (xlib.XPending)(display) == 0 {
+                let mut fd = libc::pollfd {
+                    fd: (xlib.XConnectionNumber)(display),
+                    events: libc::POLLIN,
+                    revents: 0,
+                };
+
+                if libc::poll(&mut fd, 1, timeout.as_millis() as c_int) <= 0 {
+                    return false;
+                }
+            }
+
+            let mut changed = false;
+
+            while (xlib.XPending)(display) > 0 {
+                let mut event: xlib::XEvent = mem::zeroed();
+                (xlib.XNextEvent)(display, &mut event);
+
+                let kind = event.get_type();
+                if kind == self.randr_event_base + xrandr::RRScreenChangeNotify {
+                    if let Some(xrandr) = xrandr {
+                        (xrandr.XRRUpdateConfiguration)(&mut event);
+                    }
+                    changed = true;
+                } else if kind == self.randr_event_base + xrandr::RRNotify {
+                    changed = true;
+                } else if kind == xlib::PropertyNotify {
+                    changed |= self.properties.contains(&event.property.atom);
+                }
+            }
+
+            changed
+        }
+    }
+}
@@ -1,3 +1,4 @@
 pub mod monitor;
 mod tests;
 mod utils;
+pub(crate) mod watcher;
@@ -1,9 +1,9 @@
 use std::ffi::CString;
 
 use cocoa::{
-    appkit::CGFloat,
+    appkit::{CGFloat, NSWindowDepth},
     base::{id, nil},
-    foundation::{NSPoint, NSRect},
+    foundation::{NSInteger, NSPoint, NSRect},
 };
 use core_foundation::{
     base::{kCFAllocatorDefault, CFRelease},
@@ -11,15 +11,15 @@ use core_foundation::{
     string::CFStringRef,
     uuid::CFUUIDRef,
 };
-use core_graphics::display::{CGDirectDisplayID, CGMainDisplayID};
+use core_graphics::display::{CGDirectDisplayID, CGDisplay, CGMainDisplayID};
+use dpi::{PhysicalPosition, PhysicalSize};
 use objc::{
     class, msg_send,
     runtime::{BOOL, NO, YES},
     sel, sel_impl,
 };
-use tauri::{PhysicalPosition, PhysicalSize};
 
-use crate::{Monitor, VisibleArea};
+use crate::{coordinates::flip_y, Hdr, Monitor, Rotation, VisibleArea};
 
 use super::utils::nsstring_to_string;
 
@@ -35,6 +35,87 @@ extern "C" {
     fn CFUUIDCreateString(allocator: CFAllocatorRef, uuid: CFUUIDRef) -> CFStringRef;
 }
 
+#[link(name = "AppKit", kind = "framework")]
+extern "C" {
+    fn NSBitsPerSampleFromDepth(depth: NSWindowDepth) -> NSInteger;
+}
+
+/// What Quartz and the screen tell about a display, besides where it is.
+struct DisplayDetails {
+    refresh_rate: Option<f64>,
+    rotation: Option<Rotation>,
+    bits_per_component: Option<u8>,
+    is_builtin: Option<bool>,
+    physical_size_mm: Option<(u32, u32)>,
+    hdr: Option<Hdr>,
+}
+
+impl DisplayDetails {
+    fn new(screen: id, display_id: CGDirectDisplayID) -> Self {
+        let display = CGDisplay::new(display_id);
+
+        let size = display.screen_size();
+
+        let depth: NSWindowDepth = unsafe { msg_send![screen, depth] };
+
+        let bits_per_sample = unsafe { NSBitsPerSampleFromDepth(depth) };
+
+        // EDR came with macOS 10.15
+        let has_edr: BOOL = unsafe {
+            msg_send![
+                screen,
+                respondsToSelector: sel!(maximumPotentialExtendedDynamicRangeColorComponentValue)
+            ]
+        };
+
+        let hdr = (has_edr == YES).then(|| {
+            let max_edr_value: CGFloat = unsafe {
+                msg_send![
+                    screen,
+                    maximumPotentialExtendedDynamicRangeColorComponentValue
+                ]
+            };
+
+            // it's 1.0 for screens without EDR
+            Hdr {
+                is_supported: max_edr_value > 1.0,
+                max_luminance: None,
+                min_luminance: None,
+                max_edr_value: Some(max_edr_value),
+            }
+        });
+
+        Self {
+            // it's 0 for the panels of older laptops
+            refresh_rate: display
+                .display_mode()
+                .map(|mode| mode.refresh_rate())
+                .filter(|refresh_rate| *refresh_rate > 0.0),
+            // Quartz counts the degrees clockwise
+            rotation: Rotation::from_degrees(-(display.rotation().round() as i64)),
+            bits_per_component: u8::try_from(bits_per_sample).ok().filter(|bits| *bits > 0),
+            is_builtin: Some(display.is_builtin()),
+            physical_size_mm: (size.width >= 1.0 && size.height >= 1.0)
+                .then(|| (size.width.round() as u32, size.height.round() as u32)),
+            hdr,
+        }
+    }
+}
+
+/// The height of the first screen, which has the menu bar and the origin of
+/// Cocoa's coordinates at its bottom-left corner.
+fn primary_height(screens: id) -> CGFloat {
+    let primary_screen: id = unsafe { msg_send![screens, firstObject] };
+
+    if primary_screen == nil {
+        return 0.0;
+    }
+
+    let frame: NSRect = unsafe { msg_send![primary_screen, frame] };
+
+    frame.size.height
+}
+
 pub fn get_monitor_with_cursor() -> Option<Monitor> {
     objc::rc::autoreleasepool(|| {
         let main_display_id: CGDirectDisplayID = unsafe { CGMainDisplayID() };
@@ -43,6 +124,8 @@ pub fn get_monitor_with_cursor() -> Option<Monitor> {
 
         let screens: id = unsafe { msg_send![class!(NSScreen), screens] };
 
+        let primary_height = primary_height(screens);
+
         let screens_iter: id = unsafe { msg_send![screens, objectEnumerator] };
 
         let mut next_screen: id;
@@ -84,6 +167,8 @@ pub fn get_monitor_with_cursor() -> Option<Monitor> {
 
             let monitor_id: CGDirectDisplayID = unsafe { msg_send![monitor_id, unsignedIntValue] };
 
+            let details = DisplayDetails::new(next_screen, monitor_id);
+
             let uuid: Option<String> = {
                 let uuid_ref: CFUUIDRef = unsafe { CGDisplayCreateUUIDFromDisplayID(monitor_id) };
 
@@ -109,9 +194,11 @@ pub fn get_monitor_with_cursor() -> Option<Monitor> {
                 id: monitor_id,
                 uuid,
                 name: screen_name,
+                description: None,
+                edid: None,
                 position: PhysicalPosition {
                     x: frame.origin.x * scale_factor,
-                    y: frame.origin.y * scale_factor,
+                    y: flip_y(frame.origin.y, frame.size.height, primary_height) * scale_factor,
                 },
                 size: PhysicalSize {
                     width: frame.size.width * scale_factor,
@@ -124,12 +211,22 @@ pub fn get_monitor_with_cursor() -> Option<Monitor> {
                     },
                     position: PhysicalPosition {
                         x: visible_frame.origin.x * scale_factor,
-                        y: visible_frame.origin.y * scale_factor,
+                        y: flip_y(
+                            visible_frame.origin.y,
+                            visible_frame.size.height,
+                            primary_height,
+                        ) * scale_factor,
                     },
                 },
                 scale_factor,
                 has_cursor: true,
                 is_primary: monitor_id == main_display_id,
+                refresh_rate: details.refresh_rate,
+                rotation: details.rotation,
+                bits_per_component: details.bits_per_component,
+                is_builtin: details.is_builtin,
+                physical_size_mm: details.physical_size_mm,
+                hdr: details.hdr,
             });
         }
 
@@ -145,6 +242,8 @@ pub fn get_monitors() -> Vec<Monitor> {
 
         let screens: id = unsafe { msg_send![class!(NSScreen), screens] };
 
+        let primary_height = primary_height(screens);
+
         let screens_iter: id = unsafe { msg_send![screens, objectEnumerator] };
 
         let mut next_screen: id;
@@ -182,6 +281,8 @@ pub fn get_monitors() -> Vec<Monitor> {
 
             let monitor_id: CGDirectDisplayID = unsafe { msg_send![monitor_id, unsignedIntValue] };
 
+            let details = DisplayDetails::new(next_screen, monitor_id);
+
             let uuid: Option<String> = {
                 let uuid_ref: CFUUIDRef = unsafe { CGDisplayCreateUUIDFromDisplayID(monitor_id) };
 
@@ -207,9 +308,11 @@ pub fn get_monitors() -> Vec<Monitor> {
                 id: monitor_id,
                 uuid,
                 name: screen_name,
+                description: None,
+                edid: None,
                 position: PhysicalPosition {
                     x: frame.origin.x * scale_factor,
-                    y: frame.origin.y * scale_factor,
+                    y: flip_y(frame.origin.y, frame.size.height, primary_height) * scale_factor,
                 },
                 size: PhysicalSize {
                     width: frame.size.width * scale_factor,
@@ -222,12 +325,22 @@ pub fn get_monitors() -> Vec<Monitor> {
                     },
                     position: PhysicalPosition {
                         x: visible_frame.origin.x * scale_factor,
-                        y: visible_frame.origin.y * scale_factor,
+                        y: flip_y(
+                            visible_frame.origin.y,
+                            visible_frame.size.height,
+                            primary_height,
+                        ) * scale_factor,
                     },
                 },
                 scale_factor,
                 has_cursor: is_mouse_in_screen_frame == YES,
                 is_primary: monitor_id == main_display_id,
+                refresh_rate: details.refresh_rate,
+                rotation: details.rotation,
+                bits_per_component: details.bits_per_component,
+                is_builtin: details.is_builtin,
+                physical_size_mm: details.physical_size_mm,
+                hdr: details.hdr,
             });
         }
 
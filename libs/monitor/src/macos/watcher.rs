@@ -0,0 +1,156 @@
+use std::{
+    ffi::c_void,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex, Once,
+    },
+};
+
+use cocoa::base::{id, nil};
+use objc::{
+    class,
+    declare::ClassDecl,
+    msg_send,
+    runtime::{Class, Object, Sel},
+    sel, sel_impl,
+};
+
+use crate::events::{Differ, WatchMonitorsError};
+
+use super::monitor::get_monitors;
+
+#[link(name = "AppKit", kind = "framework")]
+extern "C" {
+    static NSApplicationDidChangeScreenParametersNotification: id;
+}
+
+#[repr(C)]
+struct DispatchQueue {
+    _private: [u8; 0],
+}
+
+extern "C" {
+    static _dispatch_main_q: DispatchQueue;
+    fn dispatch_async_f(
+        queue: *const DispatchQueue,
+        context: *mut c_void,
+        work: extern "C" fn(*mut c_void),
+    );
+}
+
+const STATE_IVAR: &str = "state";
+
+/// The class of the observers of the screen parameters, which keep a
+/// reference to their `State` in an ivar.
+fn observer_class() -> &'static Class {
+    static REGISTER: Once = Once::new();
+
+    REGISTER.call_once(|| {
+        let mut decl = ClassDecl::new("MonitorScreenParametersObserver", class!(NSObject))
+            .expect("the observer class is registered once");
+
+        decl.add_ivar::<*const c_void>(STATE_IVAR);
+
+        unsafe {
+            decl.add_method(
+                sel!(screenParametersDidChange:),
+                screen_parameters_did_change as extern "C" fn(&Object, Sel, id),
+            );
+        }
+
+        decl.register();
+    });
+
+    class!(MonitorScreenParametersObserver)
+}
+
+/// What the observer and its `Watcher` share. The observer's reference is
+/// released on the main thread, once it no longer observes.
+struct State {
+    observer: id,
+    differ: Mutex<Differ>,
+    /// Set when the watcher is dropped, before the observer is torn down.
+    stopped: AtomicBool,
+}
+
+// the observer is only messaged on the main thread, after it's set up
+unsafe impl Send for State {}
+unsafe impl Sync for State {}
+
+extern "C" fn screen_parameters_did_change(this: &Object, _: Sel, _notification: id) {
+    let state = unsafe { *this.get_ivar::<*const c_void>(STATE_IVAR) } as *const State;
+
+    // a reference of its own, as the handler may drop the watcher
+    let state = unsafe {
+        Arc::increment_strong_count(state);
+        Arc::from_raw(state)
+    };
+
+    if state.stopped.load(Ordering::Acquire) {
+        return;
+    }
+
+    if let Ok(mut differ) = state.differ.lock() {
+        differ.update(get_monitors());
+    }
+}
+
+/// Removes the observer of the `State` in `context` and releases it, on the
+/// main queue, where its notifications are handled, so never during one.
+extern "C" fn tear_down(context: *mut c_void) {
+    let state = unsafe { Arc::from_raw(context as *const State) };
+
+    unsafe {
+        let center: id = msg_send![class!(NSNotificationCenter), defaultCenter];
+        let _: () = msg_send![center, removeObserver: state.observer];
+
+        // the observer's own reference
+        let observer_state = *(*state.observer).get_ivar::<*const c_void>(STATE_IVAR);
+        drop(Arc::from_raw(observer_state as *const State));
+
+        let _: () = msg_send![state.observer, release];
+    }
+}
+
+/// An observer of `NSApplicationDidChangeScreenParametersNotification`, which
+/// AppKit posts on the main thread when a screen is plugged, unplugged or
+/// rearranged, or its resolution changes.
+pub(crate) struct Watcher {
+    state: Arc<State>,
+}
+
+impl Watcher {
+    pub fn new(differ: Differ) -> Result<Self, WatchMonitorsError> {
+        unsafe {
+            let observer: id = msg_send![observer_class(), new];
+            let state = Arc::new(State {
+                observer,
+                differ: Mutex::new(differ),
+                stopped: AtomicBool::new(false),
+            });
+            (*observer).set_ivar::<*const c_void>(STATE_IVAR, Arc::into_raw(state.clone()).cast());
+
+            let center: id = msg_send![class!(NSNotificationCenter), defaultCenter];
+            let _: () = msg_send![
+                center,
+                addObserver: observer
+                selector: sel!(screenParametersDidChange:)
+                name: NSApplicationDidChangeScreenParametersNotification
+                object: nil
+            ];
+
+            Ok(Self { state })
+        }
+    }
+}
+
+impl Drop for Watcher {
+    fn drop(&mut self) {
+        self.state.stopped.store(true, Ordering::Release);
+
+        // asynchronously, as waiting for the main thread may deadlock, e.g.
+        // when it's joining the dropping thread
+        let context = Arc::into_raw(self.state.clone()) as *mut c_void;
+        unsafe { dispatch_async_f(&_dispatch_main_q, context, tear_down) };
+    }
+}